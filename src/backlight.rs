@@ -0,0 +1,82 @@
+//! Backlight of the LCD panel.
+//!
+//! The brightness is driven by the LEDC PWM on gpio2.  The model owns the `Backlight`, the UI
+//! reports every touch with `ModelRequest::UserActivity` and the model calls `update` on every
+//! loop.  The backlight is at the brightness setting while the screen is in use, dimmed after
+//! `dim_after_min` minutes without a touch, and at the night brightness, or off, at night in the
+//! home city.  The idle brightness is ramped between the day and the night levels around sunset
+//! and sunrise, see daylight.rs.  The levels and the gamma curve are in brightness.rs.
+use crate::brightness::{backlight_level, gamma_duty, is_awake};
+use crate::settings::Settings;
+
+use esp_idf_svc::hal::ledc::LedcDriver;
+
+use core::time::Duration;
+use std::time::Instant;
+
+use log::warn;
+
+pub struct Backlight {
+    ledc: LedcDriver<'static>,
+    percent: Option<u8>,
+    preview: Option<u8>,
+    last_activity: Instant,
+}
+
+impl Backlight {
+    /// Takes the LEDC driver of the backlight, the backlight is turned on at the default
+    /// brightness until the settings are read
+    pub fn new(ledc: LedcDriver<'static>) -> Self {
+        let mut backlight = Self {
+            ledc,
+            percent: None,
+            preview: None,
+            last_activity: Instant::now(),
+        };
+        backlight.set_percent(Settings::default().brightness);
+        backlight
+    }
+
+    /// A touch on the screen, the backlight goes back to the brightness setting
+    pub fn touched(&mut self) {
+        self.last_activity = Instant::now();
+    }
+
+    /// Time since the last touch
+    pub fn idle(&self) -> Duration {
+        self.last_activity.elapsed()
+    }
+
+    /// Whether the backlight is dimmed or off, the next touch only wakes the screen
+    pub fn is_asleep(&self, settings: &Settings, night_part: f32) -> bool {
+        !is_awake(settings, self.last_activity.elapsed(), night_part)
+    }
+
+    /// Sets the backlight for the time without a touch and how far into the night it is in the
+    /// home city, from 0 by day to 1 by night
+    pub fn update(&mut self, settings: &Settings, night_part: f32) {
+        let percent = match self.preview {
+            Some(percent) => percent,
+            None => backlight_level(settings, self.last_activity.elapsed(), night_part),
+        };
+        self.set_percent(percent);
+    }
+
+    /// Shows a brightness that is not saved yet, None goes back to the settings
+    pub fn preview(&mut self, percent: Option<u8>) {
+        self.last_activity = Instant::now();
+        self.preview = percent;
+    }
+
+    fn set_percent(&mut self, percent: u8) {
+        if self.percent == Some(percent) {
+            return;
+        }
+
+        let duty = gamma_duty(percent, self.ledc.get_max_duty());
+        match self.ledc.set_duty(duty) {
+            Ok(()) => self.percent = Some(percent),
+            Err(e) => warn!("Backlight error = {}", e),
+        }
+    }
+}
@@ -1,14 +1,20 @@
 //============================================================================
 //                          Cities Settings Pane
 //============================================================================
+use crate::cities::{self, CityInfo};
+use crate::i18n;
+use crate::layout;
 use crate::lvgl_misc;
-use crate::model::CityInfo;
+use crate::model::ModelRequest;
+use crate::pane::{pane_navigation, Navigation, Pane, PaneId};
+use crate::theme::{self, FontSize, Palette};
+use crate::ui::UiRequest;
+use crate::ui_event::{Button, TextField, UiEvent, UiEventQueue};
 use cstr_core::CString;
-use lvgl::font::Font;
 use lvgl::style::Style;
 use lvgl::widgets::{Btn, Dropdown, Keyboard, Label, Textarea};
-use lvgl::{Align, Color, Obj, Part, Screen, TextAlign, Widget};
-use lvgl_sys::*;
+use lvgl::{Align, Obj, Part, Screen, TextAlign, Widget};
+use std::sync::mpsc::Sender;
 
 const STATES: &str = "AL\nAK\nAZ\nAR\nCA\nCO\nCT\nDE\nDC\nFL\nGA\nGU\nHI\nID\nIL\nIN\n\
     IA\nKS\nKY\nLA\nMD\nMA\nMI\nMN\nMS\nMO\nMT\nNE\nNV\nNH\nNJ\nNM\nNY\nNC\nND\nOH\nOK\n\
@@ -27,21 +33,29 @@ pub struct CityWidgets<'a> {
 
 pub struct CitiesSettingsPane<'a> {
     pane: Obj<'a>,
-    pub keyboard: Keyboard<'a>,
-    pub cities_widgets: Vec<CityWidgets<'a>>,
-    pub edit_btn: Btn<'a>,
-    pub save_btn: Btn<'a>,
-    pub exit_btn: Btn<'a>,
+    keyboard: Keyboard<'a>,
+    cities_widgets: Vec<CityWidgets<'a>>,
+    save_btn: Btn<'a>,
     cities_info: Vec<CityInfo>,
     error_message: Label<'a>,
+    tx: Sender<ModelRequest>,
 }
 
-impl<'a> CitiesSettingsPane<'a> {
-    pub fn new(cities_settings_pane_parent: &'a mut Screen) -> Self {
+impl<'a> Pane<'a> for CitiesSettingsPane<'a> {
+    fn create(
+        cities_settings_pane_parent: &'a mut Screen,
+        tx: &Sender<ModelRequest>,
+        events: &UiEventQueue,
+    ) -> Self {
         let mut pane = Obj::create(cities_settings_pane_parent).unwrap();
-        pane.set_size(800, 429);
-        pane.set_align(Align::TopLeft, 0, 51);
-        pane.add_style(Part::Main, Box::leak(settings_pane_style()));
+        pane.set_size(layout::screen_width(), layout::pane_height());
+        pane.set_align(Align::TopLeft, 0, layout::nav_bar_height());
+
+        // In portrait each city is on two lines, the zipcode and the timezone are on the second
+        let zipcode_x = layout::pick(350, 60);
+        let timezone_x = layout::pick(470, 180);
+        let second_line = layout::pick(0, 16);
+        pane.add_style(Part::Main, theme::style(settings_pane_style));
 
         let mut cities_widgets: Vec<CityWidgets> = Vec::new();
         let cities_info: Vec<CityInfo> = Vec::new();
@@ -49,39 +63,43 @@ impl<'a> CitiesSettingsPane<'a> {
         // Name title
         let mut label = Label::create(&mut pane).unwrap();
         label.set_align(Align::TopLeft, 60, 10);
-        label.add_style(Part::Main, Box::leak(font_12_color_white_style()));
-        let mut text = CString::new("Name").unwrap();
+        label.add_style(Part::Main, theme::style(column_title_style));
+        let mut text = CString::new(i18n::text().name).unwrap();
         label.set_text(text.as_c_str()).unwrap();
 
         // State title
         label = Label::create(&mut pane).unwrap();
         label.set_align(Align::TopLeft, 250, 10);
-        label.add_style(Part::Main, Box::leak(font_12_color_white_style()));
-        text = CString::new("State").unwrap();
+        label.add_style(Part::Main, theme::style(column_title_style));
+        text = CString::new(i18n::text().state).unwrap();
         label.set_text(text.as_c_str()).unwrap();
 
         // Zipcode title
         label = Label::create(&mut pane).unwrap();
-        label.set_align(Align::TopLeft, 350, 10);
-        label.add_style(Part::Main, Box::leak(font_12_color_white_style()));
-        text = CString::new("Zipcode").unwrap();
+        label.set_align(Align::TopLeft, zipcode_x, 10 + second_line);
+        label.add_style(Part::Main, theme::style(column_title_style));
+        text = CString::new(i18n::text().zipcode).unwrap();
         label.set_text(text.as_c_str()).unwrap();
 
         // Timezone title
         label = Label::create(&mut pane).unwrap();
-        label.set_align(Align::TopLeft, 470, 10);
-        label.add_style(Part::Main, Box::leak(font_12_color_white_style()));
-        text = CString::new("Timezone").unwrap();
+        label.set_align(Align::TopLeft, timezone_x, 10 + second_line);
+        label.add_style(Part::Main, theme::style(column_title_style));
+        text = CString::new(i18n::text().timezone).unwrap();
         label.set_text(text.as_c_str()).unwrap();
 
-        const ROW_SPACING: i32 = 40;
+        let first_row = layout::pick(30, 50);
+        let row_spacing = layout::pick(40, 80);
+        let second_line = layout::pick(0, 40);
 
         for i in 0..=3 {
+            let row_y = first_row + (i * row_spacing);
+
             // Row title
             label = Label::create(&mut pane).unwrap();
-            label.set_align(Align::TopLeft, 10, 30 + (i * ROW_SPACING));
-            label.add_style(Part::Main, Box::leak(font_12_color_yellow_style()));
-            let row_title = format!("City {}:", i + 1);
+            label.set_align(Align::TopLeft, 10, row_y);
+            label.add_style(Part::Main, theme::style(row_title_style));
+            let row_title = i18n::fill(i18n::text().city_row, &[&(i + 1)]);
             text = CString::new(row_title.as_str()).unwrap();
             label.set_text(text.as_c_str()).unwrap();
 
@@ -89,30 +107,30 @@ impl<'a> CitiesSettingsPane<'a> {
             let mut city_name = Textarea::create(&mut pane).unwrap();
             let _ = city_name.set_one_line(true);
             city_name.set_width(160);
-            city_name.add_style(Part::Main, Box::leak(text_area_style()));
-            city_name.set_align(Align::TopLeft, 60, 30 + (i * ROW_SPACING));
+            city_name.add_style(Part::Main, theme::style(text_area_style));
+            city_name.set_align(Align::TopLeft, 60, row_y);
 
             // State dropdown
             let mut state = Dropdown::create(&mut pane).unwrap();
             let state_txt = CString::new(STATES).unwrap();
             let _ = state.set_options(state_txt.as_c_str());
-            state.add_style(Part::Main, Box::leak(text_area_style()));
+            state.add_style(Part::Main, theme::style(text_area_style));
             state.set_width(80);
-            state.set_align(Align::TopLeft, 250, 30 + (i * ROW_SPACING));
+            state.set_align(Align::TopLeft, 250, row_y);
 
             // Zipcode text area
             let mut zipcode = Textarea::create(&mut pane).unwrap();
             let _ = zipcode.set_one_line(true);
-            zipcode.add_style(Part::Main, Box::leak(text_area_style()));
+            zipcode.add_style(Part::Main, theme::style(text_area_style));
             zipcode.set_width(100);
-            zipcode.set_align(Align::TopLeft, 350, 30 + (i * ROW_SPACING));
+            zipcode.set_align(Align::TopLeft, zipcode_x, row_y + second_line);
 
             // Timezone dropdown
             let mut timezone = Dropdown::create(&mut pane).unwrap();
             let timezone_txt = CString::new(TIMEZONES).unwrap();
             let _ = timezone.set_options(timezone_txt.as_c_str());
-            timezone.add_style(Part::Main, Box::leak(text_area_style()));
-            timezone.set_align(Align::TopLeft, 470, 30 + (i * ROW_SPACING));
+            timezone.add_style(Part::Main, theme::style(text_area_style));
+            timezone.set_align(Align::TopLeft, timezone_x, row_y + second_line);
 
             cities_widgets.push(CityWidgets {
                 city_name,
@@ -124,71 +142,147 @@ impl<'a> CitiesSettingsPane<'a> {
 
         // Error message
         let mut error_message = Label::create(&mut pane).unwrap();
-        error_message.add_style(Part::Main, Box::leak(error_message_style()));
-        error_message.set_width(740);
-        error_message.set_align(Align::TopLeft, 10, 182);
+        error_message.add_style(Part::Main, theme::style(error_message_style));
+        error_message.set_width(layout::screen_width() - 60);
+        error_message.set_align(Align::TopLeft, 10, layout::pick(182, 382));
         text = CString::new("").unwrap();
         error_message.set_text(text.as_c_str()).unwrap();
 
         // Edit button
         let mut edit_btn = Btn::create(&mut pane).unwrap();
         edit_btn.set_size(70, 40);
-        edit_btn.add_style(Part::Main, Box::leak(button_style()));
-        edit_btn.set_align(Align::TopRight, -50, 30);
+        edit_btn.add_style(Part::Main, theme::style(button_style));
+        edit_btn.set_align(Align::TopRight, layout::pick(-50, -20), 30);
         let mut btn_label = Label::create(&mut edit_btn).unwrap();
         btn_label.set_align(Align::Center, 0, 0);
-        text = CString::new("Edit").unwrap();
+        text = CString::new(i18n::text().edit).unwrap();
         btn_label.set_text(text.as_c_str()).unwrap();
 
         // Save button
         let mut save_btn = Btn::create(&mut pane).unwrap();
         save_btn.set_size(70, 40);
-        save_btn.add_style(Part::Main, Box::leak(button_style()));
-        save_btn.set_align(Align::TopRight, -50, 80);
+        save_btn.add_style(Part::Main, theme::style(button_style));
+        save_btn.set_align(Align::TopRight, layout::pick(-50, -20), 80);
         btn_label = Label::create(&mut save_btn).unwrap();
         btn_label.set_align(Align::Center, 0, 0);
-        text = CString::new("Save").unwrap();
+        text = CString::new(i18n::text().save).unwrap();
         btn_label.set_text(text.as_c_str()).unwrap();
 
         // Exit button
         let mut exit_btn = Btn::create(&mut pane).unwrap();
         exit_btn.set_size(70, 40);
-        exit_btn.add_style(Part::Main, Box::leak(button_style()));
-        exit_btn.set_align(Align::TopRight, -50, 130);
+        exit_btn.add_style(Part::Main, theme::style(button_style));
+        exit_btn.set_align(Align::TopRight, layout::pick(-50, -20), 130);
         btn_label = Label::create(&mut exit_btn).unwrap();
         btn_label.set_align(Align::Center, 0, 0);
-        text = CString::new("Exit").unwrap();
+        text = CString::new(i18n::text().exit).unwrap();
         btn_label.set_text(text.as_c_str()).unwrap();
 
         // Create keyboard
         let mut keyboard = Keyboard::create(&mut pane).unwrap();
-        keyboard.set_size(740, 200);
-        keyboard.set_align(Align::Center, 0, 100);
-        keyboard.add_style(Part::Main, Box::leak(keyboard_style()));
+        keyboard.set_size(layout::screen_width() - 60, 200);
+        if layout::is_portrait() {
+            keyboard.set_align(Align::BottomMid, 0, 0);
+        } else {
+            keyboard.set_align(Align::Center, 0, 100);
+        }
+        keyboard.add_style(Part::Main, theme::style(keyboard_style));
+
+        // Textarea events - the virtual keyboard is assigned to the textarea clicked
+        for (city, city_widgets) in cities_widgets.iter_mut().enumerate() {
+            for (ta, text_field) in [
+                (&mut city_widgets.city_name, TextField::CityName(city)),
+                (&mut city_widgets.zipcode, TextField::Zipcode(city)),
+            ] {
+                let queue = events.clone();
+                ta.on_event(move |_ta, event| {
+                    if let lvgl::Event::Clicked = event {
+                        queue.push(UiEvent::TextFieldClicked(text_field));
+                    }
+                })
+                .unwrap();
+            }
+        }
+
+        // Button clicked events
+        for (btn, button) in [
+            (&mut edit_btn, Button::Edit),
+            (&mut save_btn, Button::Save),
+            (&mut exit_btn, Button::Exit),
+        ] {
+            let queue = events.clone();
+            btn.on_event(move |_btn, event| {
+                if let lvgl::Event::Clicked = event {
+                    queue.push(UiEvent::ButtonClicked(PaneId::CitiesSettings, button));
+                }
+            })
+            .unwrap();
+        }
+
+        lvgl_misc::hide_obj(&mut pane);
 
         Self {
             pane,
             keyboard,
             cities_widgets,
-            edit_btn,
             save_btn,
-            exit_btn,
             cities_info,
             error_message,
+            tx: tx.clone(),
         }
     }
 
-    pub fn show(&mut self) {
+    fn id(&self) -> PaneId {
+        PaneId::CitiesSettings
+    }
+
+    fn nav_title(&self) -> Option<&'static str> {
+        Some(i18n::text().nav_cities)
+    }
+
+    fn show(&mut self) {
         self.clear_error_message();
         self.prepare_cities_settings();
         self.disable_editing();
         lvgl_misc::show_obj(&mut self.pane);
     }
 
-    pub fn hide(&mut self) {
+    fn hide(&mut self) {
         lvgl_misc::hide_obj(&mut self.pane);
     }
 
+    fn handle_request(&mut self, request: &UiRequest) {
+        if let UiRequest::SetCitiesSettings(cities_info) = request {
+            self.set_cities_settings(cities_info.clone());
+        }
+    }
+
+    fn handle_event(&mut self, event: &UiEvent) -> Option<Navigation> {
+        match event {
+            UiEvent::TextFieldClicked(TextField::CityName(city)) => {
+                let city_widgets = self.cities_widgets.get_mut(*city)?;
+                self.keyboard.set_textarea(&mut city_widgets.city_name);
+            }
+            UiEvent::TextFieldClicked(TextField::Zipcode(city)) => {
+                let city_widgets = self.cities_widgets.get_mut(*city)?;
+                self.keyboard.set_textarea(&mut city_widgets.zipcode);
+            }
+            UiEvent::ButtonClicked(PaneId::CitiesSettings, Button::Edit) => self.enable_editing(),
+            // The settings stay on the pane with the error message when they are not valid
+            UiEvent::ButtonClicked(PaneId::CitiesSettings, Button::Save) => {
+                let user_cities_info = self.get_user_cities_settings()?;
+                self.tx
+                    .send(ModelRequest::UpdateCitiesInfo(user_cities_info))
+                    .unwrap();
+            }
+            _ => {}
+        }
+
+        pane_navigation(PaneId::CitiesSettings, event)
+    }
+}
+
+impl<'a> CitiesSettingsPane<'a> {
     pub fn disable_editing(&mut self) {
         lvgl_misc::hide_button(&mut self.save_btn);
         lvgl_misc::hide_keyboard(&mut self.keyboard);
@@ -229,7 +323,7 @@ impl<'a> CitiesSettingsPane<'a> {
             });
         }
 
-        match check_for_errors(cities_info.clone()) {
+        match cities::check_cities_info(&cities_info, i18n::text()) {
             Ok(()) => {
                 self.cities_info = cities_info.clone();
                 self.clear_error_message();
@@ -283,35 +377,6 @@ impl<'a> CitiesSettingsPane<'a> {
     }
 }
 
-fn check_for_errors(cities_info: Vec<CityInfo>) -> Result<(), String> {
-    for (i, city) in cities_info.iter().enumerate() {
-        let zipcode = city.zipcode.as_str();
-        is_zipcode_valid(zipcode).map_err(|e| format!("City {} - {}", i + 1, e))?;
-    }
-
-    core::result::Result::Ok(())
-}
-
-fn is_zipcode_valid(city_zipcode: &str) -> Result<(), String> {
-    // 1st check if zipcode is a number
-    // 2nd check if zipcode has 5 digits
-    // 3rd check if zipcode is within zipcode numbers assigned by USPS
-    let zipcode_vec: Vec<char> = city_zipcode.chars().collect();
-    if let Ok(zipcode) = city_zipcode.to_string().parse::<u32>() {
-        if zipcode_vec.len() == 5 {
-            if let 501..=99950 = zipcode {
-                Ok(())
-            } else {
-                Err("Zipcode must be from 00501 to 99950".to_string())
-            }
-        } else {
-            Err("Zipcode must be 5 digits".to_string())
-        }
-    } else {
-        Err("Zipcode is not a number".to_string())
-    }
-}
-
 fn find_state_position(states: &str, state: &str) -> u16 {
     // Finds the position of the state is in states string, if state is not found return position 0
     // Then divide position by 3 because every state word is 3 bytes long (two bytes for state, one byte for \n)
@@ -333,80 +398,51 @@ fn find_timezone_position(timezones: &str, timezone: &str) -> u16 {
 //                Sytles for Cities Settings Pane
 //*****************************************************************************
 
-fn settings_pane_style() -> Box<Style> {
-    let mut style = Style::default();
-    style.set_bg_color(Color::from_rgb((0, 0, 0)));
+fn settings_pane_style(style: &mut Style, palette: &Palette) {
+    style.set_bg_color(theme::color(palette.background));
     style.set_radius(0);
     style.set_border_width(0);
-    //style.set_border_color(Color::from_rgb((0, 255, 0)));
-    style.set_pad_top(4);
-    style.set_pad_bottom(4);
-    style.set_pad_left(12);
-    style.set_pad_right(12);
-
-    Box::new(style)
+    theme::set_pad(style, theme::PANE_PAD_TOP, theme::PANE_PAD);
 }
 
-fn text_area_style() -> Box<Style> {
-    let mut style = Style::default();
-    //style.set_bg_color(Color::from_rgb((0, 0, 0)));
+fn text_area_style(style: &mut Style, _palette: &Palette) {
     style.set_radius(0);
     style.set_border_width(0);
-    style.set_pad_top(4);
-    style.set_pad_bottom(4);
-    style.set_pad_left(4);
-    style.set_pad_right(4);
-
-    Box::new(style)
+    theme::set_pad(style, theme::WIDGET_PAD, theme::WIDGET_PAD);
 }
 
-fn keyboard_style() -> Box<Style> {
-    let mut style = Style::default();
-    style.set_text_color(Color::from_rgb((0, 0, 0))); // black
-    style.set_bg_color(Color::from_rgb((0, 0, 139))); // navy blue
+fn keyboard_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.on_accent));
+    style.set_bg_color(theme::color(palette.keyboard));
     style.set_text_align(TextAlign::Right);
-    style.set_text_font(unsafe { Font::new_raw(lv_font_montserrat_16) });
-
-    Box::new(style)
+    style.set_text_font(theme::font(FontSize::Medium));
 }
 
-fn font_12_color_white_style() -> Box<Style> {
-    let mut style = Style::default();
-    style.set_text_color(Color::from_rgb((255, 255, 255))); // white
+fn column_title_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.text));
     style.set_text_align(TextAlign::Center);
-    style.set_text_font(unsafe { Font::new_raw(lv_font_montserrat_12) });
-
-    Box::new(style)
+    style.set_text_font(theme::font(FontSize::Small));
 }
 
-fn font_12_color_yellow_style() -> Box<Style> {
-    let mut style = Style::default();
-    style.set_text_color(Color::from_rgb((255, 215, 0))); // gold
+fn row_title_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.highlight));
     style.set_text_align(TextAlign::Center);
-    style.set_text_font(unsafe { Font::new_raw(lv_font_montserrat_12) });
-
-    Box::new(style)
+    style.set_text_font(theme::font(FontSize::Small));
 }
 
-fn button_style() -> Box<Style> {
-    let mut style = Style::default();
-    //style.set_text_color(Color::from_rgb((255, 255, 255))); // white
-    style.set_text_color(Color::from_rgb((0, 0, 0))); // black
+fn button_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.on_accent));
     style.set_text_align(TextAlign::Center);
-    style.set_bg_color(Color::from_rgb((30, 144, 255))); // dodger blue 1
-    style.set_radius(6);
-    style.set_border_width(0);
+    style.set_bg_color(theme::color(palette.accent));
+    style.set_radius(theme::BUTTON_RADIUS);
+    style.set_border_color(theme::color(palette.outline));
+    style.set_border_width(palette.outline_width);
     style.set_shadow_width(0);
-    style.set_text_font(unsafe { Font::new_raw(lv_font_montserrat_12) });
-
-    Box::new(style)
+    style.set_text_font(theme::font(FontSize::Small));
 }
 
-fn error_message_style() -> Box<Style> {
-    let mut style = Style::default();
-    style.set_text_color(Color::from_rgb((255, 0, 0))); // red
+fn error_message_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.error));
     style.set_text_align(TextAlign::Left);
-    style.set_text_font(unsafe { Font::new_raw(lv_font_montserrat_14) });
-
-    Box::new(style)
+    style.set_text_font(theme::font(FontSize::Body));
 }
@@ -0,0 +1,285 @@
+//============================================================================
+//                               Clock Pane
+//============================================================================
+use crate::cities::CityInfo;
+use crate::i18n;
+use crate::layout;
+use crate::lvgl_misc::{self, gotham_bold_80};
+use crate::model::{CityForecast, ModelRequest};
+use crate::pane::{pane_navigation, Navigation, Pane, PaneId};
+use crate::settings::CITIES_COUNT;
+use crate::theme::{self, FontSize, Palette};
+use crate::ui::UiRequest;
+use crate::ui_event::{UiEvent, UiEventQueue};
+use crate::weather_icon_widget::WeatherIconWidget;
+use cstr_core::CString;
+use lvgl::font::Font;
+use lvgl::style::Style;
+use lvgl::widgets::Label;
+use lvgl::{Align, Obj, Part, Screen, TextAlign, Widget};
+use std::sync::mpsc::Sender;
+
+/// A large clock of the home city that fills the screen, shown from the nav bar or after a while
+/// without a touch.  A tap goes back to the pane that was shown.
+pub struct ClockPane<'a> {
+    pane: Obj<'a>,
+    city_label: Label<'a>,
+    time_label: Label<'a>,
+    meridiem_label: Label<'a>,
+    date_label: Label<'a>,
+    temp_label: Label<'a>,
+    condition_label: Label<'a>,
+    weather_icon: WeatherIconWidget<'a>,
+    home_city: usize,
+    city_names: Vec<String>,
+    forecasts: Vec<Option<CityForecast>>,
+}
+
+impl<'a> Pane<'a> for ClockPane<'a> {
+    fn create(
+        clock_pane_parent: &'a mut Screen,
+        _tx: &Sender<ModelRequest>,
+        events: &UiEventQueue,
+    ) -> Self {
+        let mut pane = Obj::create(clock_pane_parent).unwrap();
+        pane.set_size(layout::screen_width(), layout::screen_height());
+        pane.set_align(Align::TopLeft, 0, 0);
+        pane.add_style(Part::Main, theme::style(clock_pane_style));
+
+        let mut city_label = Label::create(&mut pane).unwrap();
+        city_label.set_align(Align::TopMid, 0, 40);
+        city_label.add_style(Part::Main, theme::style(city_label_style));
+
+        // The time digits, the am or pm is on their right
+        let mut time_label = Label::create(&mut pane).unwrap();
+        time_label.set_align(Align::Center, -30, -50);
+        time_label.add_style(Part::Main, theme::style(time_label_style));
+
+        let mut meridiem_label = Label::create(&mut pane).unwrap();
+        meridiem_label.add_style(Part::Main, theme::style(meridiem_label_style));
+
+        let mut date_label = Label::create(&mut pane).unwrap();
+        date_label.set_align(Align::Center, 0, 40);
+        date_label.add_style(Part::Main, theme::style(date_label_style));
+
+        // Current conditions at the bottom of the pane, in portrait the condition is below the
+        // icon and the temperature
+        let mut weather_icon = WeatherIconWidget::new(&mut pane);
+        weather_icon.set_align(
+            Align::BottomMid,
+            layout::pick(-160, -60),
+            layout::pick(-50, -120),
+        );
+
+        let mut temp_label = Label::create(&mut pane).unwrap();
+        temp_label.set_align(
+            Align::BottomMid,
+            layout::pick(-60, 40),
+            layout::pick(-50, -120),
+        );
+        temp_label.add_style(Part::Main, theme::style(temp_label_style));
+
+        let mut condition_label = Label::create(&mut pane).unwrap();
+        condition_label.set_width(300);
+        if layout::is_portrait() {
+            condition_label.set_align(Align::BottomMid, 0, -60);
+        } else {
+            condition_label.set_align(Align::BottomLeft, 440, -56);
+        }
+        condition_label.add_style(Part::Main, theme::style(condition_label_style));
+
+        // A tap anywhere leaves the clock
+        let queue = events.clone();
+        pane.on_event(move |_pane, event| {
+            if let lvgl::Event::Clicked = event {
+                queue.push(UiEvent::ClockClicked);
+            }
+        })
+        .unwrap();
+
+        let mut clock_pane = Self {
+            pane,
+            city_label,
+            time_label,
+            meridiem_label,
+            date_label,
+            temp_label,
+            condition_label,
+            weather_icon,
+            home_city: 0,
+            city_names: vec![String::new(); CITIES_COUNT],
+            forecasts: vec![None; CITIES_COUNT],
+        };
+        clock_pane.set_time_date("", "");
+        clock_pane.hide();
+        clock_pane
+    }
+
+    fn id(&self) -> PaneId {
+        PaneId::Clock
+    }
+
+    fn nav_title(&self) -> Option<&'static str> {
+        Some(i18n::text().nav_clock)
+    }
+
+    // The clock fills the screen
+    fn shows_nav_bar(&self) -> bool {
+        false
+    }
+
+    fn show(&mut self) {
+        lvgl_misc::show_obj(&mut self.pane);
+    }
+
+    fn hide(&mut self) {
+        lvgl_misc::hide_obj(&mut self.pane);
+    }
+
+    fn handle_request(&mut self, request: &UiRequest) {
+        match request {
+            UiRequest::SetCityTime(city, time, date) if *city == self.home_city => {
+                self.set_time_date(time, date)
+            }
+            UiRequest::SetCitiesTitles(cities_info) => self.set_cities(cities_info),
+            UiRequest::SetCityForecast(city, city_forecast) => {
+                self.set_city_forecast(*city, city_forecast)
+            }
+            UiRequest::SetSettings(settings) => self.set_home_city(settings.home_city),
+            UiRequest::SetTheme(..) => self.weather_icon.apply_theme(),
+            _ => {}
+        }
+    }
+
+    fn handle_event(&mut self, event: &UiEvent) -> Option<Navigation> {
+        pane_navigation(PaneId::Clock, event)
+    }
+}
+
+impl<'a> ClockPane<'a> {
+    /// The time as formatted by the model, the digits are drawn large and what follows them,
+    /// the am or pm, is drawn on their right
+    pub fn set_time_date(&mut self, time: &str, date: &str) {
+        let (digits, meridiem) = split_time(time);
+
+        self.time_label
+            .set_text(CString::new(digits).unwrap().as_c_str())
+            .unwrap();
+        self.meridiem_label
+            .set_text(CString::new(meridiem.to_uppercase()).unwrap().as_c_str())
+            .unwrap();
+        lvgl_misc::label_align_to_right_bottom(&mut self.meridiem_label, &self.time_label, 12, -12);
+
+        self.date_label
+            .set_text(CString::new(date).unwrap().as_c_str())
+            .unwrap();
+    }
+
+    pub fn set_cities(&mut self, cities_info: &[CityInfo]) {
+        for (city, city_info) in cities_info.iter().enumerate().take(CITIES_COUNT) {
+            self.city_names[city] = city_info.city_name.clone();
+            // The forecast was for the city that was replaced
+            self.forecasts[city] = None;
+        }
+        self.show_home_city();
+    }
+
+    pub fn set_city_forecast(&mut self, city: usize, city_forecast: &CityForecast) {
+        if let Some(forecast) = self.forecasts.get_mut(city) {
+            *forecast = Some(city_forecast.clone());
+        }
+
+        if city == self.home_city {
+            self.show_home_city();
+        }
+    }
+
+    fn set_home_city(&mut self, home_city: usize) {
+        if self.home_city != home_city {
+            self.home_city = home_city;
+            self.show_home_city();
+        }
+    }
+
+    // Shows the name and the current conditions of the home city, the time is shown with the
+    // next time update
+    fn show_home_city(&mut self) {
+        let city_name = self.city_names.get(self.home_city).cloned();
+        self.city_label
+            .set_text(
+                CString::new(city_name.unwrap_or_default())
+                    .unwrap()
+                    .as_c_str(),
+            )
+            .unwrap();
+
+        let forecast = self.forecasts.get(self.home_city).cloned().flatten();
+        let (temp, condition, weather_icon) = match forecast {
+            Some(forecast) => (forecast.temp, forecast.weather_descr, forecast.weather_icon),
+            None => (String::new(), String::new(), None),
+        };
+
+        self.temp_label
+            .set_text(CString::new(temp).unwrap().as_c_str())
+            .unwrap();
+        self.condition_label
+            .set_text(CString::new(condition).unwrap().as_c_str())
+            .unwrap();
+        self.weather_icon.set_icon(weather_icon);
+    }
+}
+
+// Splits "09:41am" into the digits "09:41" and the "am" that follows them
+fn split_time(time: &str) -> (&str, &str) {
+    let digits_end = time
+        .find(|c: char| !c.is_ascii_digit() && c != ':')
+        .unwrap_or(time.len());
+    let (digits, meridiem) = time.split_at(digits_end);
+    (digits, meridiem.trim())
+}
+
+//*****************************************************************************
+//                      Styles for Clock Pane
+//*****************************************************************************
+
+fn clock_pane_style(style: &mut Style, palette: &Palette) {
+    style.set_bg_color(theme::color(palette.background));
+    style.set_radius(0);
+    style.set_border_width(0);
+}
+
+fn city_label_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.highlight));
+    style.set_text_align(TextAlign::Center);
+    style.set_text_font(theme::font(FontSize::Large));
+}
+
+fn time_label_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.text));
+    style.set_text_align(TextAlign::Center);
+    style.set_text_font(unsafe { Font::new_raw(gotham_bold_80) });
+}
+
+fn meridiem_label_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.text));
+    style.set_text_align(TextAlign::Left);
+    style.set_text_font(theme::font(FontSize::Huge));
+}
+
+fn date_label_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.text));
+    style.set_text_align(TextAlign::Center);
+    style.set_text_font(theme::font(FontSize::Large));
+}
+
+fn temp_label_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.highlight));
+    style.set_text_align(TextAlign::Center);
+    style.set_text_font(theme::font(FontSize::Huge));
+}
+
+fn condition_label_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.text));
+    style.set_text_align(TextAlign::Left);
+    style.set_text_font(theme::font(FontSize::Medium));
+}
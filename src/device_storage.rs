@@ -0,0 +1,267 @@
+//! The storages of `ConfigStorage` that need the ESP-IDF, see config_storage.rs
+use crate::config_storage::{ConfigStorage, MediaChange};
+use crate::file_store::{FileStore, MAX_ATOMIC_FILE_SIZE};
+use crate::sd_card::SDcard;
+
+use anyhow::{bail, Result};
+
+use std::ffi::CString;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+use log::{info, warn};
+
+//============================================================================
+//                          Removable SDCard
+//============================================================================
+/// The SDCard while a card is inserted, the fallback storage otherwise
+pub struct RemovableStorage {
+    file_store: FileStore<SDcard>,
+    fallback: Box<dyn ConfigStorage>,
+    // Why the card is not used, None while it is used
+    card_error: Option<String>,
+}
+
+impl RemovableStorage {
+    pub fn new(mut file_store: FileStore<SDcard>, fallback: Box<dyn ConfigStorage>) -> Self {
+        let card_error = file_store.probe_card().err().map(|e| e.to_string());
+        if let Some(e) = &card_error {
+            warn!("No SDCard, {} = {}", fallback.name(), e);
+        }
+
+        Self {
+            file_store,
+            fallback,
+            card_error,
+        }
+    }
+
+    fn active(&mut self) -> &mut dyn ConfigStorage {
+        if self.card_error.is_none() {
+            &mut self.file_store
+        } else {
+            self.fallback.as_mut()
+        }
+    }
+}
+
+impl ConfigStorage for RemovableStorage {
+    fn name(&self) -> &'static str {
+        if self.card_error.is_none() {
+            self.file_store.name()
+        } else {
+            self.fallback.name()
+        }
+    }
+
+    fn read_lines(&mut self, file_name: &str) -> Result<Vec<String>> {
+        self.active().read_lines(file_name)
+    }
+
+    fn read_to_string(&mut self, file_name: &str, limit: usize) -> Result<String> {
+        self.active().read_to_string(file_name, limit)
+    }
+
+    fn write_atomic(&mut self, file_name: &str, contents: &[u8]) -> Result<()> {
+        self.active().write_atomic(file_name, contents)
+    }
+
+    fn recover(&mut self, file_name: &str) -> Result<()> {
+        self.active().recover(file_name)
+    }
+
+    fn set_clock_synced(&mut self) {
+        ConfigStorage::set_clock_synced(&mut self.file_store);
+        self.fallback.set_clock_synced();
+    }
+
+    fn append_in_dir(
+        &mut self,
+        dir_name: &str,
+        file_name: &str,
+        header: &[u8],
+        contents: &[u8],
+    ) -> Result<()> {
+        self.active()
+            .append_in_dir(dir_name, file_name, header, contents)
+    }
+
+    fn list_dir(&mut self, dir_name: &str) -> Result<Vec<String>> {
+        self.active().list_dir(dir_name)
+    }
+
+    fn delete_in_dir(&mut self, dir_name: &str, file_name: &str) -> Result<()> {
+        self.active().delete_in_dir(dir_name, file_name)
+    }
+
+    fn file_size_in_dir(&mut self, dir_name: &str, file_name: &str) -> Result<Option<u32>> {
+        self.active().file_size_in_dir(dir_name, file_name)
+    }
+
+    fn for_each_line_in_dir(
+        &mut self,
+        dir_name: &str,
+        file_name: &str,
+        f: &mut dyn FnMut(&str),
+    ) -> Result<()> {
+        self.active().for_each_line_in_dir(dir_name, file_name, f)
+    }
+
+    fn check_media(&mut self) -> Option<MediaChange> {
+        if self.card_error.is_none() {
+            if self.file_store.is_card_present() {
+                return None;
+            }
+
+            // Tell why the card can't be used, a card that is in but can't be read shows the error
+            let error = match self.file_store.probe_card() {
+                Ok(()) => return None,
+                Err(e) => e.to_string(),
+            };
+
+            warn!("SDCard removed = {}", error);
+            self.card_error = Some(error.clone());
+            Some(MediaChange::Removed(error))
+        } else {
+            match self.file_store.probe_card() {
+                Ok(()) => {
+                    info!("SDCard inserted");
+                    self.card_error = None;
+                    Some(MediaChange::Inserted)
+                }
+                Err(e) => {
+                    self.card_error = Some(e.to_string());
+                    None
+                }
+            }
+        }
+    }
+
+    fn notice(&self) -> Option<String> {
+        self.card_error.as_ref().map(|e| {
+            format!(
+                "No SD card - {}\nSettings are kept in {}",
+                e,
+                self.fallback.name().to_lowercase()
+            )
+        })
+    }
+}
+
+//============================================================================
+//                          Internal Flash
+//============================================================================
+/// The FAT partition in the internal flash, mounted on the ESP-IDF VFS with wear levelling.  The
+/// VFS can rename files, so a write goes to a .TMP file that replaces the file once it is synced,
+/// the old file is renamed to .BAK on the way.
+pub struct FlashStorage {
+    base_path: PathBuf,
+}
+
+impl FlashStorage {
+    /// Mounts the partition, it is formatted the first time
+    pub fn mount(partition_label: &str, base_path: &str) -> Result<Self> {
+        let c_base_path = CString::new(base_path)?;
+        let c_partition_label = CString::new(partition_label)?;
+
+        let mount_config = esp_idf_svc::sys::esp_vfs_fat_mount_config_t {
+            format_if_mount_failed: true,
+            max_files: 4,
+            allocation_unit_size: 4096,
+            ..Default::default()
+        };
+
+        let mut wl_handle: esp_idf_svc::sys::wl_handle_t = 0;
+
+        esp_idf_svc::sys::esp!(unsafe {
+            esp_idf_svc::sys::esp_vfs_fat_spiflash_mount_rw_wl(
+                c_base_path.as_ptr(),
+                c_partition_label.as_ptr(),
+                &mount_config,
+                &mut wl_handle,
+            )
+        })?;
+
+        info!("Mounted {} partition on {}", partition_label, base_path);
+
+        Ok(Self {
+            base_path: PathBuf::from(base_path),
+        })
+    }
+
+    fn path(&self, file_name: &str) -> PathBuf {
+        self.base_path.join(file_name.to_uppercase())
+    }
+
+    fn sibling_path(&self, file_name: &str, extension: &str) -> PathBuf {
+        self.path(file_name).with_extension(extension)
+    }
+}
+
+impl ConfigStorage for FlashStorage {
+    fn name(&self) -> &'static str {
+        "Internal flash"
+    }
+
+    fn read_lines(&mut self, file_name: &str) -> Result<Vec<String>> {
+        let text = self.read_to_string(file_name, MAX_ATOMIC_FILE_SIZE)?;
+        Ok(text.lines().map(String::from).collect())
+    }
+
+    fn read_to_string(&mut self, file_name: &str, limit: usize) -> Result<String> {
+        let path = self.path(file_name);
+        let length = fs::metadata(&path)?.len() as usize;
+        if length > limit {
+            bail!(
+                "{} is {} bytes, the limit is {} bytes",
+                file_name,
+                length,
+                limit
+            );
+        }
+
+        Ok(fs::read_to_string(path)?)
+    }
+
+    fn write_atomic(&mut self, file_name: &str, contents: &[u8]) -> Result<()> {
+        let path = self.path(file_name);
+        let tmp_path = self.sibling_path(file_name, "TMP");
+        let bak_path = self.sibling_path(file_name, "BAK");
+
+        let mut tmp_file = fs::File::create(&tmp_path)?;
+        tmp_file.write_all(contents)?;
+        tmp_file.sync_all()?;
+        drop(tmp_file);
+
+        // FAT can't rename over an existing file
+        if bak_path.exists() {
+            fs::remove_file(&bak_path)?;
+        }
+        if path.exists() {
+            fs::rename(&path, &bak_path)?;
+        }
+
+        fs::rename(&tmp_path, &path)?;
+        Ok(())
+    }
+
+    fn recover(&mut self, file_name: &str) -> Result<()> {
+        let path = self.path(file_name);
+        let tmp_path = self.sibling_path(file_name, "TMP");
+        let bak_path = self.sibling_path(file_name, "BAK");
+
+        // A power cut between the two renames leaves the old contents in the .BAK file
+        if !path.exists() && bak_path.exists() {
+            info!("Restoring {} from backup", file_name);
+            fs::rename(&bak_path, &path)?;
+        }
+
+        // Left by a write that did not get to replace the file
+        if tmp_path.exists() {
+            fs::remove_file(&tmp_path)?;
+        }
+
+        Ok(())
+    }
+}
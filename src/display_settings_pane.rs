@@ -0,0 +1,646 @@
+//============================================================================
+//                          Display Settings Pane
+//============================================================================
+use crate::cities::CityInfo;
+use crate::date_format::DateOrder;
+use crate::i18n;
+use crate::layout;
+use crate::lvgl_misc::{self, BTNMATRIX_BTN_NONE};
+use crate::model::ModelRequest;
+use crate::orientation::Orientation;
+use crate::pane::{pane_navigation, Navigation, Pane, PaneId};
+use crate::settings::{DisplayPrefs, Settings, ThemeName, MIN_BRIGHTNESS};
+use crate::theme::{self, FontSize, Palette};
+use crate::ui::UiRequest;
+use crate::ui_event::{Button, Choice, LevelSlider, UiEvent, UiEventQueue};
+use crate::units::{TempUnit, WindUnit};
+use cstr_core::CString;
+use lvgl::style::Style;
+use lvgl::widgets::{Btn, Btnmatrix, Label, Slider};
+use lvgl::{Align, Obj, Part, Screen, TextAlign, Widget};
+use std::sync::mpsc::Sender;
+
+// Minutes of the dimming and of the refresh buttons, 0 never dims
+const DIM_AFTER_MIN: [u32; 5] = [0, 1, 5, 10, 30];
+const REFRESH_MIN: [u32; 5] = [5, 10, 15, 30, 60];
+
+// Rows of the settings, in landscape the rows from COLUMN_ROWS on are in a second column.  The
+// rows are closer in portrait so they fit in a single column.
+const FIRST_ROW_Y: i32 = 20;
+const COLUMN_ROWS: i32 = 6;
+const COLUMN_SPACING: i32 = 390;
+// The controls are on the right of the row titles
+const CONTROL_X: i32 = 100;
+const CONTROL_WIDTH: i32 = 270;
+
+pub struct DisplaySettingsPane<'a> {
+    pane: Obj<'a>,
+    brightness_slider: Slider<'a>,
+    brightness_value: Label<'a>,
+    dim_slider: Slider<'a>,
+    dim_value: Label<'a>,
+    choices: Vec<(Choice, Btnmatrix<'a>)>,
+    // Shown while the rotation chosen is not the one the panes are laid out for
+    restart_note: Label<'a>,
+    // Settings saved in SETTINGS.TXT and the settings shown on the pane
+    saved: DisplayPrefs,
+    draft: DisplayPrefs,
+    // The draft is previewed by the model until it is saved or dropped
+    previewing: bool,
+    tx: Sender<ModelRequest>,
+}
+
+impl<'a> Pane<'a> for DisplaySettingsPane<'a> {
+    fn create(
+        display_settings_pane_parent: &'a mut Screen,
+        tx: &Sender<ModelRequest>,
+        events: &UiEventQueue,
+    ) -> Self {
+        let mut pane = Obj::create(display_settings_pane_parent).unwrap();
+        pane.set_size(layout::screen_width(), layout::pane_height());
+        pane.set_align(Align::TopLeft, 0, layout::nav_bar_height());
+        pane.add_style(Part::Main, theme::style(settings_pane_style));
+
+        let text = i18n::text();
+        let dim_after_titles: Vec<String> = DIM_AFTER_MIN.into_iter().map(minutes_title).collect();
+        let refresh_titles: Vec<String> = REFRESH_MIN.into_iter().map(minutes_title).collect();
+        let rotation_titles: Vec<String> = Orientation::ALL
+            .iter()
+            .map(|orientation| format!("{}°", orientation.degrees()))
+            .collect();
+
+        // Backlight
+        let (mut brightness_slider, brightness_value) =
+            level_slider(&mut pane, text.brightness, 0, MIN_BRIGHTNESS);
+        let (mut dim_slider, dim_value) = level_slider(&mut pane, text.dim_level, 1, 0);
+        let mut choices = vec![
+            (
+                Choice::DimAfter,
+                choice_btn_matrix(
+                    &mut pane,
+                    text.dimming,
+                    2,
+                    dim_after_titles.iter().map(String::as_str).collect(),
+                ),
+            ),
+            (
+                Choice::Theme,
+                choice_btn_matrix(
+                    &mut pane,
+                    text.theme,
+                    3,
+                    vec![text.theme_light, text.theme_dark, text.theme_contrast],
+                ),
+            ),
+            (
+                Choice::AutoDark,
+                choice_btn_matrix(&mut pane, text.auto_dark, 4, vec![text.off, text.on]),
+            ),
+            (
+                Choice::Refresh,
+                choice_btn_matrix(
+                    &mut pane,
+                    text.refresh,
+                    5,
+                    refresh_titles.iter().map(String::as_str).collect(),
+                ),
+            ),
+            // Clock, dates and units
+            (
+                Choice::ClockFormat,
+                choice_btn_matrix(
+                    &mut pane,
+                    text.clock_format,
+                    6,
+                    vec![text.hours_12, text.hours_24],
+                ),
+            ),
+            (
+                Choice::Seconds,
+                choice_btn_matrix(&mut pane, text.seconds, 7, vec![text.off, text.on]),
+            ),
+            (
+                Choice::DateOrder,
+                choice_btn_matrix(
+                    &mut pane,
+                    text.date_order,
+                    8,
+                    DateOrder::ALL.iter().map(|order| order.title()).collect(),
+                ),
+            ),
+            (
+                Choice::TempUnit,
+                choice_btn_matrix(
+                    &mut pane,
+                    text.temperature,
+                    9,
+                    TempUnit::ALL.iter().map(|unit| unit.symbol()).collect(),
+                ),
+            ),
+            (
+                Choice::WindUnit,
+                choice_btn_matrix(
+                    &mut pane,
+                    text.wind,
+                    10,
+                    WindUnit::ALL.iter().map(|unit| unit.symbol()).collect(),
+                ),
+            ),
+            (
+                Choice::Orientation,
+                choice_btn_matrix(
+                    &mut pane,
+                    text.rotation,
+                    11,
+                    rotation_titles.iter().map(String::as_str).collect(),
+                ),
+            ),
+        ];
+
+        // Home city, one button per city, the names are set with the cities titles.  The row
+        // is below the others and in portrait the buttons are below the title.
+        let home_city_y = FIRST_ROW_Y + layout::pick(COLUMN_ROWS, 12) * row_spacing();
+        let mut label = Label::create(&mut pane).unwrap();
+        label.set_align(Align::TopLeft, 10, home_city_y);
+        label.add_style(Part::Main, theme::style(row_title_style));
+        label
+            .set_text(CString::new(text.home_city).unwrap().as_c_str())
+            .unwrap();
+
+        let mut home_city_btn_matrix = Btnmatrix::create(&mut pane).unwrap();
+        home_city_btn_matrix.set_size(layout::pick(480, 440), 40);
+        home_city_btn_matrix.set_align(
+            Align::TopLeft,
+            layout::pick(CONTROL_X + 10, 0),
+            home_city_y + layout::pick(-12, 26),
+        );
+        home_city_btn_matrix.add_style(Part::Main, theme::style(btn_matrix_style));
+        lvgl_misc::btnmatrix_set_one_checked_map(
+            &mut home_city_btn_matrix,
+            vec!["City 1", "City 2", "City 3", "City 4"],
+        );
+        choices.push((Choice::HomeCity, home_city_btn_matrix));
+
+        // Save, Exit and Defaults buttons
+        let buttons_y = home_city_y + layout::pick(50, 80);
+        let mut buttons = Vec::new();
+        for (i, (title, button)) in [
+            (text.save, Button::Save),
+            (text.exit, Button::Exit),
+            (text.defaults, Button::Defaults),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let mut btn = Btn::create(&mut pane).unwrap();
+            btn.set_size(100, 40);
+            btn.add_style(Part::Main, theme::style(button_style));
+            btn.set_align(Align::TopLeft, CONTROL_X + 10 + i as i32 * 110, buttons_y);
+            let mut btn_label = Label::create(&mut btn).unwrap();
+            btn_label.set_align(Align::Center, 0, 0);
+            btn_label
+                .set_text(CString::new(title).unwrap().as_c_str())
+                .unwrap();
+            buttons.push((btn, button));
+        }
+
+        // Right of the buttons in landscape, below them in portrait
+        let mut restart_note = Label::create(&mut pane).unwrap();
+        restart_note.set_align(
+            Align::TopLeft,
+            layout::pick(CONTROL_X + 10 + 3 * 110, 10),
+            buttons_y + layout::pick(10, 48),
+        );
+        restart_note.add_style(Part::Main, theme::style(note_style));
+        restart_note
+            .set_text(CString::new(text.applies_after_restart).unwrap().as_c_str())
+            .unwrap();
+
+        // Slider events - the value is previewed while the slider is moved
+        for (slider, which) in [
+            (&mut brightness_slider, LevelSlider::Brightness),
+            (&mut dim_slider, LevelSlider::DimLevel),
+        ] {
+            let queue = events.clone();
+            slider
+                .on_event(move |slider, event| match event {
+                    lvgl::Event::Pressing => queue.push(UiEvent::SliderMoved(
+                        which,
+                        lvgl_misc::slider_get_value(&slider) as u8,
+                    )),
+                    lvgl::Event::Released | lvgl::Event::PressLost => {
+                        queue.push(UiEvent::SliderReleased(which))
+                    }
+                    _ => {}
+                })
+                .unwrap();
+        }
+
+        for (choice, btn_matrix) in choices.iter_mut() {
+            let choice = *choice;
+            let queue = events.clone();
+            btn_matrix
+                .on_event(move |btnm, event| {
+                    if let lvgl::Event::Pressed = event {
+                        queue.push(UiEvent::ChoicePressed(
+                            choice,
+                            lvgl_misc::btnmatrix_get_selected_btn(&btnm),
+                        ));
+                    }
+                })
+                .unwrap();
+        }
+
+        for (btn, button) in buttons.iter_mut() {
+            let button = *button;
+            let queue = events.clone();
+            btn.on_event(move |_btn, event| {
+                if let lvgl::Event::Clicked = event {
+                    queue.push(UiEvent::ButtonClicked(PaneId::DisplaySettings, button));
+                }
+            })
+            .unwrap();
+        }
+
+        let mut ds_pane = Self {
+            pane,
+            brightness_slider,
+            brightness_value,
+            dim_slider,
+            dim_value,
+            choices,
+            restart_note,
+            saved: DisplayPrefs::default(),
+            draft: DisplayPrefs::default(),
+            previewing: false,
+            tx: tx.clone(),
+        };
+        ds_pane.show_draft();
+        ds_pane.hide();
+        ds_pane
+    }
+
+    fn id(&self) -> PaneId {
+        PaneId::DisplaySettings
+    }
+
+    fn nav_title(&self) -> Option<&'static str> {
+        Some(i18n::text().nav_display)
+    }
+
+    fn show(&mut self) {
+        lvgl_misc::show_obj(&mut self.pane);
+    }
+
+    // Leaving the pane without saving drops the preview
+    fn hide(&mut self) {
+        self.drop_preview();
+        lvgl_misc::hide_obj(&mut self.pane);
+    }
+
+    fn handle_request(&mut self, request: &UiRequest) {
+        match request {
+            UiRequest::SetSettings(settings) => self.set_settings(settings),
+            UiRequest::SetCitiesTitles(cities_info) => self.set_cities(cities_info),
+            _ => {}
+        }
+    }
+
+    fn handle_event(&mut self, event: &UiEvent) -> Option<Navigation> {
+        match event {
+            UiEvent::SliderMoved(LevelSlider::Brightness, brightness) => {
+                if self.draft.brightness != *brightness {
+                    self.draft.brightness = *brightness;
+                    self.show_draft();
+                    self.preview();
+                }
+            }
+            // The backlight is at the dim level while its slider is moved
+            UiEvent::SliderMoved(LevelSlider::DimLevel, dim_brightness) => {
+                if self.draft.dim_brightness != *dim_brightness {
+                    self.draft.dim_brightness = *dim_brightness;
+                    self.show_draft();
+                    self.preview();
+                    self.tx
+                        .send(ModelRequest::PreviewBrightness(Some(*dim_brightness)))
+                        .unwrap();
+                }
+            }
+            UiEvent::SliderReleased(LevelSlider::DimLevel) => {
+                self.tx.send(ModelRequest::PreviewBrightness(None)).unwrap();
+            }
+            UiEvent::ChoicePressed(choice, btn_id) if *btn_id != BTNMATRIX_BTN_NONE => {
+                if set_choice(&mut self.draft, *choice, *btn_id as usize) {
+                    self.show_draft();
+                    self.preview();
+                }
+            }
+            UiEvent::ButtonClicked(PaneId::DisplaySettings, Button::Save) => {
+                if self.previewing {
+                    self.previewing = false;
+                    self.saved = self.draft.clone();
+                    self.tx
+                        .send(ModelRequest::SaveSettings(self.draft.clone()))
+                        .unwrap();
+                }
+            }
+            UiEvent::ButtonClicked(PaneId::DisplaySettings, Button::Exit) => self.drop_preview(),
+            // The defaults are previewed like the other changes, they are kept once saved.  The
+            // rotation is kept, it is the way the device is mounted.
+            UiEvent::ButtonClicked(PaneId::DisplaySettings, Button::Defaults) => {
+                self.draft = DisplayPrefs {
+                    orientation: self.draft.orientation,
+                    ..DisplayPrefs::default()
+                };
+                self.show_draft();
+                self.preview();
+            }
+            _ => {}
+        }
+
+        pane_navigation(PaneId::DisplaySettings, event)
+    }
+}
+
+impl<'a> DisplaySettingsPane<'a> {
+    /// The settings come back from the model once they are saved or previewed, the pane keeps
+    /// its draft while it is previewed
+    pub fn set_settings(&mut self, settings: &Settings) {
+        if self.previewing {
+            return;
+        }
+
+        self.saved = settings.display_prefs();
+        self.draft = settings.display_prefs();
+        self.show_draft();
+    }
+
+    pub fn set_cities(&mut self, cities_info: &[CityInfo]) {
+        let city_names: Vec<&str> = cities_info
+            .iter()
+            .map(|city| city.city_name.as_str())
+            .collect();
+        if let Some((_, btn_matrix)) = self
+            .choices
+            .iter_mut()
+            .find(|(choice, _)| *choice == Choice::HomeCity)
+        {
+            lvgl_misc::btnmatrix_set_one_checked_map(btn_matrix, city_names);
+        }
+        self.show_draft();
+    }
+
+    fn preview(&mut self) {
+        self.previewing = true;
+        self.tx
+            .send(ModelRequest::PreviewSettings(Some(self.draft.clone())))
+            .unwrap();
+    }
+
+    // Back to the saved settings
+    fn drop_preview(&mut self) {
+        if !self.previewing {
+            return;
+        }
+
+        self.previewing = false;
+        self.draft = self.saved.clone();
+        self.show_draft();
+        self.tx.send(ModelRequest::PreviewSettings(None)).unwrap();
+    }
+
+    fn show_draft(&mut self) {
+        lvgl_misc::slider_set_value(&mut self.brightness_slider, self.draft.brightness as i32);
+        show_percent(&mut self.brightness_value, self.draft.brightness);
+        lvgl_misc::slider_set_value(&mut self.dim_slider, self.draft.dim_brightness as i32);
+        show_percent(&mut self.dim_value, self.draft.dim_brightness);
+
+        // A value set in SETTINGS.TXT that has no button leaves the row unchecked
+        for (choice, btn_matrix) in self.choices.iter_mut() {
+            lvgl_misc::btnmatrix_clear_checked_all(btn_matrix);
+            if let Some(btn_id) = choice_index(&self.draft, *choice) {
+                lvgl_misc::btnmatrix_set_btn_ctrl(btn_matrix, btn_id as u16);
+            }
+        }
+
+        if self.draft.orientation == layout::orientation() {
+            lvgl_misc::hide_label(&mut self.restart_note);
+        } else {
+            lvgl_misc::show_label(&mut self.restart_note);
+        }
+    }
+}
+
+// Index of the checked button of a row
+fn choice_index(settings: &DisplayPrefs, choice: Choice) -> Option<usize> {
+    match choice {
+        Choice::DimAfter => DIM_AFTER_MIN
+            .iter()
+            .position(|min| *min == settings.dim_after_min),
+        Choice::Theme => ThemeName::ALL
+            .iter()
+            .position(|theme| *theme == settings.theme),
+        Choice::AutoDark => Some(settings.auto_dark as usize),
+        Choice::HomeCity => Some(settings.home_city),
+        Choice::ClockFormat => Some(settings.time_24h as usize),
+        Choice::Seconds => Some(settings.show_seconds as usize),
+        Choice::DateOrder => DateOrder::ALL
+            .iter()
+            .position(|order| *order == settings.date_order),
+        Choice::TempUnit => TempUnit::ALL
+            .iter()
+            .position(|unit| *unit == settings.temp_unit),
+        Choice::WindUnit => WindUnit::ALL
+            .iter()
+            .position(|unit| *unit == settings.wind_unit),
+        Choice::Refresh => REFRESH_MIN
+            .iter()
+            .position(|min| *min == settings.refresh_min),
+        Choice::Orientation => Orientation::ALL
+            .iter()
+            .position(|orientation| *orientation == settings.orientation),
+    }
+}
+
+// Sets the setting of a button, returns true when the settings were changed
+fn set_choice(settings: &mut DisplayPrefs, choice: Choice, index: usize) -> bool {
+    let before = settings.clone();
+    match choice {
+        Choice::DimAfter => {
+            if let Some(min) = DIM_AFTER_MIN.get(index) {
+                settings.dim_after_min = *min;
+            }
+        }
+        Choice::Theme => {
+            if let Some(theme) = ThemeName::ALL.get(index) {
+                settings.theme = *theme;
+            }
+        }
+        Choice::AutoDark => settings.auto_dark = index == 1,
+        Choice::HomeCity => settings.home_city = index,
+        Choice::ClockFormat => settings.time_24h = index == 1,
+        Choice::Seconds => settings.show_seconds = index == 1,
+        Choice::DateOrder => {
+            if let Some(order) = DateOrder::ALL.get(index) {
+                settings.date_order = *order;
+            }
+        }
+        Choice::TempUnit => {
+            if let Some(unit) = TempUnit::ALL.get(index) {
+                settings.temp_unit = *unit;
+            }
+        }
+        Choice::WindUnit => {
+            if let Some(unit) = WindUnit::ALL.get(index) {
+                settings.wind_unit = *unit;
+            }
+        }
+        Choice::Refresh => {
+            if let Some(min) = REFRESH_MIN.get(index) {
+                settings.refresh_min = *min;
+            }
+        }
+        Choice::Orientation => {
+            if let Some(orientation) = Orientation::ALL.get(index) {
+                settings.orientation = *orientation;
+            }
+        }
+    }
+    *settings != before
+}
+
+fn minutes_title(minutes: u32) -> String {
+    match minutes {
+        0 => i18n::text().off.to_string(),
+        minutes => format!("{}m", minutes),
+    }
+}
+
+fn row_spacing() -> i32 {
+    layout::pick(50, 42)
+}
+
+// Position of the title of a row
+fn row_position(row: i32) -> (i32, i32) {
+    if layout::is_portrait() {
+        (10, FIRST_ROW_Y + row * row_spacing())
+    } else {
+        (
+            10 + (row / COLUMN_ROWS) * COLUMN_SPACING,
+            FIRST_ROW_Y + (row % COLUMN_ROWS) * row_spacing(),
+        )
+    }
+}
+
+fn row_title(pane: &mut Obj, title: &str, row: i32) -> (i32, i32) {
+    let (x, y) = row_position(row);
+    let mut label = Label::create(pane).unwrap();
+    label.set_align(Align::TopLeft, x, y);
+    label.add_style(Part::Main, theme::style(row_title_style));
+    label
+        .set_text(CString::new(title).unwrap().as_c_str())
+        .unwrap();
+    (x, y)
+}
+
+// A row title, its slider in percent and the value
+fn level_slider<'a>(pane: &mut Obj<'a>, title: &str, row: i32, min: u8) -> (Slider<'a>, Label<'a>) {
+    let (x, y) = row_title(pane, title, row);
+
+    let mut slider = Slider::create(pane).unwrap();
+    slider.set_size(CONTROL_WIDTH - 80, 12);
+    slider.set_align(Align::TopLeft, x + CONTROL_X + 10, y + 4);
+    slider.add_style(Part::Main, theme::style(slider_style));
+    slider.add_style(Part::Indicator, theme::style(slider_indicator_style));
+    slider.add_style(Part::Knob, theme::style(slider_knob_style));
+    lvgl_misc::slider_set_range(&mut slider, min as i32, 100);
+
+    let mut value = Label::create(pane).unwrap();
+    value.set_width(60);
+    value.set_align(Align::TopLeft, x + CONTROL_X + CONTROL_WIDTH - 60, y);
+    value.add_style(Part::Main, theme::style(value_style));
+    (slider, value)
+}
+
+// A row title and its buttons, one of them is checked
+fn choice_btn_matrix<'a>(
+    pane: &mut Obj<'a>,
+    title: &str,
+    row: i32,
+    names: Vec<&str>,
+) -> Btnmatrix<'a> {
+    let (x, y) = row_title(pane, title, row);
+
+    let mut btn_matrix = Btnmatrix::create(pane).unwrap();
+    btn_matrix.set_size(CONTROL_WIDTH, layout::pick(40, 36));
+    btn_matrix.set_align(Align::TopLeft, x + CONTROL_X, y - layout::pick(12, 10));
+    btn_matrix.add_style(Part::Main, theme::style(btn_matrix_style));
+    lvgl_misc::btnmatrix_set_one_checked_map(&mut btn_matrix, names);
+    btn_matrix
+}
+
+fn show_percent(label: &mut Label, percent: u8) {
+    let text = CString::new(format!("{}%", percent)).unwrap();
+    label.set_text(text.as_c_str()).unwrap();
+}
+
+//*****************************************************************************
+//                      Styles for Display Settings Pane
+//*****************************************************************************
+
+fn settings_pane_style(style: &mut Style, palette: &Palette) {
+    style.set_bg_color(theme::color(palette.background));
+    style.set_radius(0);
+    style.set_border_width(0);
+    theme::set_pad(style, theme::PANE_PAD_TOP, theme::PANE_PAD);
+}
+
+fn row_title_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.highlight));
+    style.set_text_align(TextAlign::Center);
+    style.set_text_font(theme::font(FontSize::Small));
+}
+
+fn note_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.highlight));
+    style.set_text_font(theme::font(FontSize::Small));
+}
+
+fn value_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.text));
+    style.set_text_align(TextAlign::Right);
+    style.set_text_font(theme::font(FontSize::Small));
+}
+
+fn btn_matrix_style(style: &mut Style, palette: &Palette) {
+    style.set_border_width(0);
+    style.set_shadow_width(0);
+    style.set_bg_color(theme::color(palette.background));
+    theme::set_pad(style, theme::BUTTONS_PAD, theme::BUTTONS_PAD);
+    style.set_text_font(theme::font(FontSize::Small));
+}
+
+fn button_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.on_accent));
+    style.set_text_align(TextAlign::Center);
+    style.set_bg_color(theme::color(palette.accent));
+    style.set_radius(theme::BUTTON_RADIUS);
+    style.set_border_color(theme::color(palette.outline));
+    style.set_border_width(palette.outline_width);
+    style.set_shadow_width(0);
+    style.set_text_font(theme::font(FontSize::Small));
+}
+
+fn slider_style(style: &mut Style, palette: &Palette) {
+    style.set_bg_color(theme::color(palette.surface));
+    style.set_border_color(theme::color(palette.outline));
+    style.set_border_width(palette.outline_width);
+}
+
+fn slider_indicator_style(style: &mut Style, palette: &Palette) {
+    style.set_bg_color(theme::color(palette.accent));
+}
+
+fn slider_knob_style(style: &mut Style, palette: &Palette) {
+    style.set_bg_color(theme::color(palette.highlight));
+}
@@ -1,6 +1,8 @@
 /// A minimal implementation of the GT911 to work with Lvgl since Lvgl only uses a single touch point
 /// The default orientation and size are based on the aliexpress ESP 7 inch capactive touch development
 /// board model ESP-8048S070C
+use crate::orientation::{Dimension, Orientation};
+
 use embedded_hal::i2c::{I2c, SevenBitAddress};
 
 const DEFAULT_GT911_ADDRESS: u8 = 0x5d;
@@ -15,22 +17,6 @@ enum Reg {
     Point1 = 0x814F,
 }
 
-/// Represents the orientation of the device
-#[derive(Copy, Clone, Debug)]
-pub enum Orientation {
-    Portrait, // Do Not use
-    Landscape,
-    InvertedPortrait, // Do Not use
-    InvertedLandscape,
-}
-
-/// Represents the dimensions of the device
-#[derive(Copy, Clone, Debug)]
-pub struct Dimension {
-    pub height: u16,
-    pub width: u16,
-}
-
 /// Current state of the driver
 #[derive(Copy, Clone, Debug)]
 pub enum TouchState {
@@ -49,7 +35,8 @@ pub struct TouchPoint {
 /// - The I2C Slave address of the GT911
 /// - The I2C Bus used to communicate with the GT911
 /// - The screen/panel orientation
-/// - The scree/panel dimesions
+/// - The panel dimensions in its own landscape orientation, the touch points are reported in
+///   the screen coordinates of the orientation
 
 pub struct GT911<I2C>
 where
@@ -165,25 +152,30 @@ where
 
         //println!("========== x = {:?}    y = {:?} ==========", x, y);
 
+        // The panel x goes from 0 to width - 1 and the panel y from 0 to height - 1 whatever the
+        // orientation, the portrait screens are height wide and width high
+        let last_x = self.size.width - 1;
+        let last_y = self.size.height - 1;
+        x = x.min(last_x);
+        y = y.min(last_y);
+
         match self.orientation {
             Orientation::Landscape => {
-                //x = x;
-                //y = y;
                 // x = x, y = y
             }
             Orientation::Portrait => {
                 let temp: u16 = x;
-                x = y;
-                y = self.size.height - temp;
+                x = last_y - y;
+                y = temp;
             }
             Orientation::InvertedLandscape => {
-                x = self.size.width - x;
-                y = self.size.height - y;
+                x = last_x - x;
+                y = last_y - y;
             }
             Orientation::InvertedPortrait => {
                 let temp: u16 = x;
-                x = self.size.width - y;
-                y = temp;
+                x = y;
+                y = last_x - temp;
             }
         }
 
@@ -0,0 +1,425 @@
+//============================================================================
+//                              History Pane
+//============================================================================
+use crate::cities::CityInfo;
+use crate::date_format::DateFormat;
+use crate::history::{BucketStats, CityHistory, HistoryPeriod};
+use crate::i18n;
+use crate::layout;
+use crate::lvgl_misc::*;
+use crate::model::ModelRequest;
+use crate::pane::{Navigation, Pane, PaneId};
+use crate::theme::{self, FontSize, Palette};
+use crate::ui::UiRequest;
+use crate::ui_event::{UiEvent, UiEventQueue};
+use crate::units::TempUnit;
+use cstr_core::CString;
+use lvgl::style::Style;
+use lvgl::widgets::{Btnmatrix, Chart, Label};
+use lvgl::{Align, Color, Obj, Part, Screen, TextAlign, Widget};
+use lvgl_sys::*;
+use std::sync::mpsc::Sender;
+
+struct HistoryChart<'a> {
+    title: Label<'a>,
+    chart: Chart<'a>,
+    max_series: *mut lv_chart_series_t,
+    avg_series: *mut lv_chart_series_t,
+    min_series: *mut lv_chart_series_t,
+}
+
+pub struct HistoryPane<'a> {
+    pane: Obj<'a>,
+    city_btn_matrix: Btnmatrix<'a>,
+    period_btn_matrix: Btnmatrix<'a>,
+    info_label: Label<'a>,
+    charts: Vec<HistoryChart<'a>>,
+    city: usize,
+    period: HistoryPeriod,
+    date_format: DateFormat,
+    temp_unit: TempUnit,
+    tx: Sender<ModelRequest>,
+}
+
+impl<'a> Pane<'a> for HistoryPane<'a> {
+    fn create(
+        history_pane_parent: &'a mut Screen,
+        tx: &Sender<ModelRequest>,
+        events: &UiEventQueue,
+    ) -> Self {
+        let mut pane = Obj::create(history_pane_parent).unwrap();
+        pane.set_size(layout::screen_width(), layout::pane_height());
+        pane.set_align(Align::TopLeft, 0, layout::nav_bar_height());
+
+        // In portrait the periods are below the cities and the charts are taller
+        let top = layout::pick(0, 46);
+        pane.add_style(Part::Main, theme::style(history_pane_style));
+
+        // One button per city, the names are set with the cities titles
+        let mut city_btn_matrix = Btnmatrix::create(&mut pane).unwrap();
+        city_btn_matrix.set_size(480, 40);
+        city_btn_matrix.set_align(Align::TopLeft, 0, 0);
+        city_btn_matrix.add_style(Part::Main, theme::style(btn_matrix_style));
+        btnmatrix_set_one_checked_map(
+            &mut city_btn_matrix,
+            vec!["City 1", "City 2", "City 3", "City 4"],
+        );
+
+        let mut period_btn_matrix = Btnmatrix::create(&mut pane).unwrap();
+        period_btn_matrix.set_size(270, 40);
+        if layout::is_portrait() {
+            period_btn_matrix.set_align(Align::TopLeft, 0, top);
+        } else {
+            period_btn_matrix.set_align(Align::TopRight, 0, 0);
+        }
+        period_btn_matrix.add_style(Part::Main, theme::style(btn_matrix_style));
+        btnmatrix_set_one_checked_map(
+            &mut period_btn_matrix,
+            HistoryPeriod::ALL
+                .iter()
+                .map(|period| period.title())
+                .collect(),
+        );
+
+        // Time span of the charts or why there is nothing to show
+        let mut info_label = Label::create(&mut pane).unwrap();
+        info_label.add_style(Part::Main, theme::style(info_label_style));
+        info_label.set_width(layout::screen_width() - 40);
+        info_label.set_align(Align::TopLeft, 0, top + 46);
+        info_label
+            .set_text(CString::new("").unwrap().as_c_str())
+            .unwrap();
+
+        let chart_height = layout::pick(90, 150);
+        let chart_spacing = chart_height + 26;
+
+        let text = i18n::text();
+        let chart_titles = [
+            text.chart_temperature,
+            text.chart_pressure,
+            text.chart_humidity,
+        ];
+
+        let mut charts: Vec<HistoryChart> = Vec::new();
+        for (i, title) in chart_titles.iter().enumerate() {
+            let y = top + 66 + (i as i32 * chart_spacing);
+
+            let mut title_label = Label::create(&mut pane).unwrap();
+            title_label.add_style(Part::Main, theme::style(chart_title_style));
+            title_label.set_align(Align::TopLeft, 0, y);
+            title_label
+                .set_text(CString::new(*title).unwrap().as_c_str())
+                .unwrap();
+
+            // The tick labels are drawn on the left of the chart
+            let mut chart = Chart::create(&mut pane).unwrap();
+            chart.set_size(layout::screen_width() - 100, chart_height);
+            chart.set_align(Align::TopLeft, 56, y + 18);
+            chart.add_style(Part::Main, theme::style(chart_style));
+            chart_set_line_type(&mut chart, HistoryPeriod::Day.bucket_count() as u16, 3);
+            chart_set_y_axis_labels(&mut chart, 3, 50);
+
+            let max_series = chart_add_series(&mut chart, Color::from_rgb((255, 69, 0))); // orange red
+            let avg_series =
+                chart_add_series(&mut chart, theme::color(theme::palette().chart_line));
+            let min_series = chart_add_series(&mut chart, Color::from_rgb((30, 144, 255))); // dodger blue 1
+
+            charts.push(HistoryChart {
+                title: title_label,
+                chart,
+                max_series,
+                avg_series,
+                min_series,
+            });
+        }
+
+        let queue = events.clone();
+        city_btn_matrix
+            .on_event(move |btnm, event| {
+                if let lvgl::Event::Pressed = event {
+                    queue.push(UiEvent::HistoryCityPressed(btnmatrix_get_selected_btn(
+                        &btnm,
+                    )));
+                }
+            })
+            .unwrap();
+
+        let queue = events.clone();
+        period_btn_matrix
+            .on_event(move |btnm, event| {
+                if let lvgl::Event::Pressed = event {
+                    queue.push(UiEvent::HistoryPeriodPressed(btnmatrix_get_selected_btn(
+                        &btnm,
+                    )));
+                }
+            })
+            .unwrap();
+
+        hide_obj(&mut pane);
+
+        let mut history_pane = Self {
+            pane,
+            city_btn_matrix,
+            period_btn_matrix,
+            info_label,
+            charts,
+            city: 0,
+            period: HistoryPeriod::Day,
+            date_format: DateFormat::default(),
+            temp_unit: TempUnit::default(),
+            tx: tx.clone(),
+        };
+        history_pane.show_temp_unit();
+        history_pane
+    }
+
+    fn id(&self) -> PaneId {
+        PaneId::History
+    }
+
+    fn nav_title(&self) -> Option<&'static str> {
+        Some(i18n::text().nav_history)
+    }
+
+    // The history is read again every time the pane is shown
+    fn show(&mut self) {
+        self.request_history();
+        show_obj(&mut self.pane);
+    }
+
+    fn hide(&mut self) {
+        hide_obj(&mut self.pane);
+    }
+
+    fn handle_request(&mut self, request: &UiRequest) {
+        match request {
+            UiRequest::SetCitiesTitles(cities_info) => self.set_cities(cities_info.clone()),
+            UiRequest::SetHistory(city_history) => self.set_history(city_history.clone()),
+            UiRequest::SetHistoryMessage(msg) => self.set_message(msg.clone()),
+            UiRequest::SetTheme(..) => self.apply_theme(),
+            UiRequest::SetSettings(settings) => {
+                self.date_format = settings.date_format();
+                if self.temp_unit != settings.temp_unit {
+                    self.temp_unit = settings.temp_unit;
+                    self.show_temp_unit();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_event(&mut self, event: &UiEvent) -> Option<Navigation> {
+        let changed = match event {
+            UiEvent::HistoryCityPressed(btn_id) => self.select_city(*btn_id),
+            UiEvent::HistoryPeriodPressed(btn_id) => self.select_period(*btn_id),
+            _ => false,
+        };
+
+        if changed {
+            self.request_history();
+        }
+
+        None
+    }
+}
+
+impl<'a> HistoryPane<'a> {
+    pub fn set_cities(&mut self, cities_info: Vec<CityInfo>) {
+        let city_names: Vec<&str> = cities_info
+            .iter()
+            .map(|city| city.city_name.as_str())
+            .collect();
+        btnmatrix_set_one_checked_map(&mut self.city_btn_matrix, city_names);
+
+        // Keep the city that was shown
+        btnmatrix_set_btn_ctrl(&mut self.city_btn_matrix, self.city as u16);
+    }
+
+    fn request_history(&mut self) {
+        self.set_message(i18n::text().reading_history.to_string());
+        self.tx
+            .send(ModelRequest::GetHistory(self.city, self.period))
+            .unwrap();
+    }
+
+    // Returns true when another city was selected
+    fn select_city(&mut self, btn_id: u16) -> bool {
+        match btn_id {
+            BTNMATRIX_BTN_NONE => false,
+            btn_id if btn_id as usize == self.city => false,
+            btn_id => {
+                self.city = btn_id as usize;
+                true
+            }
+        }
+    }
+
+    // Returns true when another period was selected
+    fn select_period(&mut self, btn_id: u16) -> bool {
+        match HistoryPeriod::ALL.get(btn_id as usize) {
+            Some(period) if *period != self.period => {
+                self.period = *period;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// The average line is drawn with the color of the theme
+    pub fn apply_theme(&mut self) {
+        let color = theme::color(theme::palette().chart_line);
+        for history_chart in self.charts.iter_mut() {
+            chart_set_series_color(&mut history_chart.chart, history_chart.avg_series, color);
+        }
+    }
+
+    /// Empties the charts and shows the message instead
+    pub fn set_message(&mut self, message: String) {
+        self.set_info(&message);
+
+        for history_chart in self.charts.iter_mut() {
+            let empty = vec![None; HistoryPeriod::Day.bucket_count()];
+            chart_set_line_type(&mut history_chart.chart, empty.len() as u16, 3);
+            for series in [
+                history_chart.max_series,
+                history_chart.avg_series,
+                history_chart.min_series,
+            ] {
+                chart_set_series_values(&mut history_chart.chart, series, &empty);
+            }
+        }
+    }
+
+    pub fn set_history(&mut self, city_history: CityHistory) {
+        if city_history.is_empty() {
+            self.set_message(i18n::fill(
+                i18n::text().no_history,
+                &[&city_history.city_name, &city_history.period.title()],
+            ));
+            return;
+        }
+
+        let end = city_history.start + city_history.period.duration();
+        let date_time_format = self.date_format.date_time();
+        self.set_info(&i18n::fill(
+            i18n::text().history_span,
+            &[
+                &city_history.city_name,
+                &city_history.period.title(),
+                &city_history
+                    .start
+                    .format(&i18n::localize(&date_time_format, &city_history.start)),
+                &end.format(&i18n::localize(&date_time_format, &end)),
+            ],
+        ));
+
+        // The history files keep the temperatures in Fahrenheit
+        let temp_unit = self.temp_unit;
+        let temps: Vec<Option<BucketStats>> = city_history
+            .temp_f
+            .iter()
+            .map(|bucket| {
+                bucket.map(|stats| BucketStats {
+                    min: temp_unit.convert(stats.min),
+                    max: temp_unit.convert(stats.max),
+                    avg: temp_unit.convert(stats.avg),
+                })
+            })
+            .collect();
+
+        let all_buckets = [&temps, &city_history.pressure_mb, &city_history.humidity];
+
+        for (history_chart, buckets) in self.charts.iter_mut().zip(all_buckets) {
+            let chart = &mut history_chart.chart;
+            chart_set_line_type(chart, buckets.len() as u16, 3);
+
+            let (min, max) = chart_range(buckets);
+            chart_set_range(chart, min, max);
+
+            let values = |value: fn(&BucketStats) -> f64| -> Vec<Option<i16>> {
+                buckets
+                    .iter()
+                    .map(|bucket| bucket.as_ref().map(|stats| value(stats).round() as i16))
+                    .collect()
+            };
+
+            chart_set_series_values(chart, history_chart.max_series, &values(|s| s.max));
+            chart_set_series_values(chart, history_chart.avg_series, &values(|s| s.avg));
+            chart_set_series_values(chart, history_chart.min_series, &values(|s| s.min));
+        }
+    }
+
+    // The temperature chart title ends with the unit
+    fn show_temp_unit(&mut self) {
+        let title = i18n::fill(i18n::text().chart_temperature, &[&self.temp_unit.symbol()]);
+        self.charts[0]
+            .title
+            .set_text(CString::new(title).unwrap().as_c_str())
+            .unwrap();
+    }
+
+    fn set_info(&mut self, info: &str) {
+        self.info_label
+            .set_text(CString::new(info).unwrap().as_c_str())
+            .unwrap();
+    }
+}
+
+// Y axis range holding all the values with a little room above and below
+fn chart_range(buckets: &[Option<BucketStats>]) -> (i16, i16) {
+    let min = buckets
+        .iter()
+        .flatten()
+        .map(|stats| stats.min)
+        .fold(f64::INFINITY, f64::min);
+    let max = buckets
+        .iter()
+        .flatten()
+        .map(|stats| stats.max)
+        .fold(f64::NEG_INFINITY, f64::max);
+
+    if min > max {
+        return (0, 100);
+    }
+
+    (min.floor() as i16 - 2, max.ceil() as i16 + 2)
+}
+
+//*****************************************************************************
+//                      Sytles for History Pane
+//*****************************************************************************
+fn history_pane_style(style: &mut Style, palette: &Palette) {
+    style.set_bg_color(theme::color(palette.background));
+    style.set_radius(0);
+    style.set_border_width(0);
+    theme::set_pad(style, theme::PANE_PAD_TOP, theme::PANE_PAD);
+}
+
+fn btn_matrix_style(style: &mut Style, palette: &Palette) {
+    style.set_border_width(0);
+    style.set_shadow_width(0);
+    style.set_bg_color(theme::color(palette.background));
+    theme::set_pad(style, theme::BUTTONS_PAD, theme::BUTTONS_PAD);
+    style.set_text_font(theme::font(FontSize::Small));
+}
+
+fn info_label_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.text));
+    style.set_text_align(TextAlign::Left);
+    style.set_text_font(theme::font(FontSize::Body));
+}
+
+fn chart_title_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.highlight));
+    style.set_text_align(TextAlign::Left);
+    style.set_text_font(theme::font(FontSize::Small));
+}
+
+fn chart_style(style: &mut Style, palette: &Palette) {
+    style.set_bg_color(theme::color(palette.chart));
+    style.set_text_color(theme::color(palette.chart_text));
+    style.set_text_font(theme::font(FontSize::Tiny));
+    style.set_radius(0);
+    style.set_border_width(0);
+    theme::set_pad(style, theme::WIDGET_PAD, theme::WIDGET_PAD);
+}
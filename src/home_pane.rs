@@ -1,15 +1,22 @@
 //============================================================================
 //                              Home Pane
 //============================================================================
+use crate::cities::CityInfo;
+use crate::i18n;
+use crate::layout;
 use crate::lvgl_misc;
-use crate::model::{CityForecast, CityInfo};
+use crate::model::{CityForecast, ModelRequest};
+use crate::pane::{Pane, PaneId};
+use crate::theme::{self, FontSize, Palette};
+use crate::ui::UiRequest;
+use crate::ui_event::UiEventQueue;
+use crate::weather_icon_widget::WeatherIconWidget;
 use cstr_core::CString;
-use lvgl::font::Font;
 use lvgl::misc::area::pct;
 use lvgl::style::{FlexAlign, FlexFlow, Layout, Opacity, Style};
 use lvgl::widgets::Label;
 use lvgl::{Align, Color, Obj, Part, Screen, TextAlign, Widget};
-use lvgl_sys::*;
+use std::sync::mpsc::Sender;
 
 pub struct CityLabels<'a> {
     pub title: Label<'a>,
@@ -17,6 +24,7 @@ pub struct CityLabels<'a> {
     pub date: Label<'a>,
     pub temp: Label<'a>,
     pub weather_descr: Label<'a>,
+    pub weather_icon: WeatherIconWidget<'a>,
     pub uv: Label<'a>,
     pub feels_like: Label<'a>,
     pub aqi: Label<'a>,
@@ -25,10 +33,13 @@ pub struct CityLabels<'a> {
     pub wind_dir: Label<'a>,
     pub title_forecast_day_1: Label<'a>,
     pub forecast_day_1: Label<'a>,
+    pub icon_forecast_day_1: WeatherIconWidget<'a>,
     pub title_forecast_day_2: Label<'a>,
     pub forecast_day_2: Label<'a>,
+    pub icon_forecast_day_2: WeatherIconWidget<'a>,
     pub title_forecast_day_3: Label<'a>,
     pub forecast_day_3: Label<'a>,
+    pub icon_forecast_day_3: WeatherIconWidget<'a>,
     pub last_update: Label<'a>,
 }
 
@@ -37,21 +48,25 @@ pub struct HomePane<'a> {
     cities_labels: Vec<CityLabels<'a>>,
 }
 
-impl<'a> HomePane<'a> {
-    pub fn new(home_pane_parent: &'a mut Screen) -> Self {
+impl<'a> Pane<'a> for HomePane<'a> {
+    fn create(
+        home_pane_parent: &'a mut Screen,
+        _tx: &Sender<ModelRequest>,
+        _events: &UiEventQueue,
+    ) -> Self {
         let mut home_pane = Obj::create(home_pane_parent).unwrap();
-        home_pane.set_size(800, 429);
-        home_pane.set_align(Align::TopLeft, 0, 51);
-        home_pane.add_style(Part::Main, Box::leak(home_pane_style()));
+        home_pane.set_size(layout::screen_width(), layout::pane_height());
+        home_pane.set_align(Align::TopLeft, 0, layout::nav_bar_height());
+        home_pane.add_style(Part::Main, theme::style(home_pane_style));
 
         let mut cities_labels: Vec<CityLabels> = Vec::new();
 
-        // Create 4 city panes
+        // Create 4 city panes, in portrait the cities are on two rows and the pane scrolls
         for _i in 1..5 {
             // city pane
             let mut city_pane = Obj::create(&mut home_pane).unwrap();
-            city_pane.set_size(183, pct(100));
-            city_pane.add_style(Part::Main, Box::leak(city_pane_style()));
+            city_pane.set_size(183, layout::pick(pct(100), 405));
+            city_pane.add_style(Part::Main, theme::style(city_pane_style));
 
             // City title
             let mut title = Label::create(&mut city_pane).unwrap();
@@ -59,20 +74,21 @@ impl<'a> HomePane<'a> {
             title.set_align(Align::TopMid, 0, 5);
             let mut text = CString::new("").unwrap();
             title.set_text(text.as_c_str()).unwrap();
-            title.add_style(Part::Main, Box::leak(city_title_style()));
+            title.add_style(Part::Main, theme::style(city_title_style));
 
             // city local time title
             let mut label = Label::create(&mut city_pane).unwrap();
             label.set_align(Align::TopMid, 0, 53);
-            text = CString::new("Local Time").unwrap();
+            text = CString::new(i18n::text().local_time).unwrap();
             label.set_text(text.as_c_str()).unwrap();
+            label.add_style(Part::Main, theme::style(caption_style));
 
             // city local time
             let mut time = Label::create(&mut city_pane).unwrap();
             time.set_align(Align::TopMid, 0, 66);
             text = CString::new("").unwrap();
             time.set_text(text.as_c_str()).unwrap();
-            time.add_style(Part::Main, Box::leak(time_style()));
+            time.add_style(Part::Main, theme::style(time_style));
 
             // city local date
             let mut date = Label::create(&mut city_pane).unwrap();
@@ -83,15 +99,16 @@ impl<'a> HomePane<'a> {
             // city - local weather title label
             label = Label::create(&mut city_pane).unwrap();
             label.set_align(Align::TopMid, 0, 125);
-            text = CString::new("Local Weather").unwrap();
+            text = CString::new(i18n::text().local_weather).unwrap();
             label.set_text(text.as_c_str()).unwrap();
+            label.add_style(Part::Main, theme::style(caption_style));
 
-            // city - local temperature
+            // city - local temperature, the weather icon is on its right
             let mut temp = Label::create(&mut city_pane).unwrap();
-            temp.set_align(Align::TopMid, 0, 136);
+            temp.set_align(Align::TopMid, -22, 136);
             text = CString::new("").unwrap();
             temp.set_text(text.as_c_str()).unwrap();
-            temp.add_style(Part::Main, Box::leak(temperature_style()));
+            temp.add_style(Part::Main, theme::style(temperature_style));
 
             // city - weather description label, wrap text for long descriptions
             let mut weather_descr = Label::create(&mut city_pane).unwrap();
@@ -99,13 +116,18 @@ impl<'a> HomePane<'a> {
             weather_descr.set_align(Align::TopMid, 0, 170);
             let text = CString::new("").unwrap();
             weather_descr.set_text(text.as_c_str()).unwrap();
-            weather_descr.add_style(Part::Main, Box::leak(font_12_red_style()));
+            weather_descr.add_style(Part::Main, theme::style(small_title_style));
+
+            // city - weather icon
+            let mut weather_icon = WeatherIconWidget::new(&mut city_pane);
+            weather_icon.set_align(Align::TopMid, 48, 138);
 
             // city - uv title label
             label = Label::create(&mut city_pane).unwrap();
             label.set_align(Align::Center, -55, 25);
-            let text = CString::new("UV").unwrap();
+            let text = CString::new(i18n::text().uv).unwrap();
             label.set_text(text.as_c_str()).unwrap();
+            label.add_style(Part::Main, theme::style(caption_style));
 
             // city - uv
             let mut uv = Label::create(&mut city_pane).unwrap();
@@ -113,26 +135,28 @@ impl<'a> HomePane<'a> {
             uv.set_width(40);
             let text = CString::new("").unwrap();
             uv.set_text(text.as_c_str()).unwrap();
-            uv.add_style(Part::Main, Box::leak(color_scale_style()));
+            uv.add_style(Part::Main, theme::style(color_scale_style));
 
             // city - feels like title label
             label = Label::create(&mut city_pane).unwrap();
             label.set_align(Align::Center, 0, 25);
-            let text = CString::new("Feels Like").unwrap();
+            let text = CString::new(i18n::text().feels_like).unwrap();
             label.set_text(text.as_c_str()).unwrap();
+            label.add_style(Part::Main, theme::style(caption_style));
 
             // city - feels like temperature
             let mut feels_like = Label::create(&mut city_pane).unwrap();
             feels_like.set_align(Align::Center, 0, 43);
             let text = CString::new("").unwrap();
             feels_like.set_text(text.as_c_str()).unwrap();
-            feels_like.add_style(Part::Main, Box::leak(font_14_blu_style()));
+            feels_like.add_style(Part::Main, theme::style(value_style));
 
             // city - air quality index title label
             label = Label::create(&mut city_pane).unwrap();
             label.set_align(Align::Center, 55, 25);
-            let text = CString::new("AQI").unwrap();
+            let text = CString::new(i18n::text().aqi).unwrap();
             label.set_text(text.as_c_str()).unwrap();
+            label.add_style(Part::Main, theme::style(caption_style));
 
             // city - air quality index
             let mut aqi = Label::create(&mut city_pane).unwrap();
@@ -140,101 +164,117 @@ impl<'a> HomePane<'a> {
             aqi.set_width(40);
             let text = CString::new("").unwrap();
             aqi.set_text(text.as_c_str()).unwrap();
-            aqi.add_style(Part::Main, Box::leak(color_scale_style()));
+            aqi.add_style(Part::Main, theme::style(color_scale_style));
 
             // city - wind speed title label
             label = Label::create(&mut city_pane).unwrap();
             label.set_align(Align::Center, -55, 65);
-            let text = CString::new("Wind").unwrap();
+            let text = CString::new(i18n::text().wind).unwrap();
             label.set_text(text.as_c_str()).unwrap();
+            label.add_style(Part::Main, theme::style(caption_style));
 
             // city - wind speed
             let mut wind_speed = Label::create(&mut city_pane).unwrap();
             wind_speed.set_align(Align::Center, -55, 80);
             let text = CString::new("").unwrap();
             wind_speed.set_text(text.as_c_str()).unwrap();
-            wind_speed.add_style(Part::Main, Box::leak(font_14_blu_style()));
+            wind_speed.add_style(Part::Main, theme::style(value_style));
 
             // city - wind gust title label
             label = Label::create(&mut city_pane).unwrap();
             label.set_align(Align::Center, 0, 65);
-            let text = CString::new("Gust").unwrap();
+            let text = CString::new(i18n::text().gust).unwrap();
             label.set_text(text.as_c_str()).unwrap();
+            label.add_style(Part::Main, theme::style(caption_style));
 
             // city - wind gust
             let mut wind_gust = Label::create(&mut city_pane).unwrap();
             wind_gust.set_align(Align::Center, 0, 80);
             let text = CString::new("").unwrap();
             wind_gust.set_text(text.as_c_str()).unwrap();
-            wind_gust.add_style(Part::Main, Box::leak(font_14_blu_style()));
+            wind_gust.add_style(Part::Main, theme::style(value_style));
 
             // city - wind direction title label
             label = Label::create(&mut city_pane).unwrap();
             label.set_align(Align::Center, 55, 65);
-            let text = CString::new("Dir").unwrap();
+            let text = CString::new(i18n::text().wind_dir).unwrap();
             label.set_text(text.as_c_str()).unwrap();
+            label.add_style(Part::Main, theme::style(caption_style));
 
             // city - wind direction
             let mut wind_dir = Label::create(&mut city_pane).unwrap();
             wind_dir.set_align(Align::Center, 55, 80);
             let text = CString::new("").unwrap();
             wind_dir.set_text(text.as_c_str()).unwrap();
-            wind_dir.add_style(Part::Main, Box::leak(font_12_blu_style()));
+            wind_dir.add_style(Part::Main, theme::style(small_value_style));
 
             // city - local weather forecast title label
             label = Label::create(&mut city_pane).unwrap();
             label.set_align(Align::Center, 0, 105);
-            let text = CString::new("Local Weather Forecast").unwrap();
+            let text = CString::new(i18n::text().local_forecast).unwrap();
             label.set_text(text.as_c_str()).unwrap();
+            label.add_style(Part::Main, theme::style(caption_style));
 
             // city forecast day 1 name title label
             let mut title_forecast_day_1 = Label::create(&mut city_pane).unwrap();
             title_forecast_day_1.set_align(Align::Center, -60, 120);
             let text = CString::new("").unwrap();
             title_forecast_day_1.set_text(text.as_c_str()).unwrap();
-            title_forecast_day_1.add_style(Part::Main, Box::leak(font_12_red_style()));
+            title_forecast_day_1.add_style(Part::Main, theme::style(small_title_style));
 
             // city forecast day 1
             let mut forecast_day_1 = Label::create(&mut city_pane).unwrap();
-            forecast_day_1.set_align(Align::Center, -60, 145);
+            forecast_day_1.set_align(Align::Center, -60, 173);
             let text = CString::new("").unwrap();
             forecast_day_1.set_text(text.as_c_str()).unwrap();
-            forecast_day_1.add_style(Part::Main, Box::leak(font_12_blu_style()));
+            forecast_day_1.add_style(Part::Main, theme::style(small_value_style));
+
+            // city forecast day 1 weather icon
+            let mut icon_forecast_day_1 = WeatherIconWidget::new(&mut city_pane);
+            icon_forecast_day_1.set_align(Align::Center, -60, 141);
 
             // city forecast day 2 name title label
             let mut title_forecast_day_2 = Label::create(&mut city_pane).unwrap();
             title_forecast_day_2.set_align(Align::Center, 0, 120);
             let text = CString::new("").unwrap();
             title_forecast_day_2.set_text(text.as_c_str()).unwrap();
-            title_forecast_day_2.add_style(Part::Main, Box::leak(font_12_red_style()));
+            title_forecast_day_2.add_style(Part::Main, theme::style(small_title_style));
 
             // city forecast day 2
             let mut forecast_day_2 = Label::create(&mut city_pane).unwrap();
-            forecast_day_2.set_align(Align::Center, 0, 145);
+            forecast_day_2.set_align(Align::Center, 0, 173);
             let text = CString::new("").unwrap();
             forecast_day_2.set_text(text.as_c_str()).unwrap();
-            forecast_day_2.add_style(Part::Main, Box::leak(font_12_blu_style()));
+            forecast_day_2.add_style(Part::Main, theme::style(small_value_style));
+
+            // city forecast day 2 weather icon
+            let mut icon_forecast_day_2 = WeatherIconWidget::new(&mut city_pane);
+            icon_forecast_day_2.set_align(Align::Center, 0, 141);
 
             // city forecast day 3 name title label
             let mut title_forecast_day_3 = Label::create(&mut city_pane).unwrap();
             title_forecast_day_3.set_align(Align::Center, 60, 120);
             let text = CString::new("").unwrap();
             title_forecast_day_3.set_text(text.as_c_str()).unwrap();
-            title_forecast_day_3.add_style(Part::Main, Box::leak(font_12_red_style()));
+            title_forecast_day_3.add_style(Part::Main, theme::style(small_title_style));
 
             // city forecast day 3
             let mut forecast_day_3 = Label::create(&mut city_pane).unwrap();
-            forecast_day_3.set_align(Align::Center, 60, 145);
+            forecast_day_3.set_align(Align::Center, 60, 173);
             let text = CString::new("").unwrap();
             forecast_day_3.set_text(text.as_c_str()).unwrap();
-            forecast_day_3.add_style(Part::Main, Box::leak(font_12_blu_style()));
+            forecast_day_3.add_style(Part::Main, theme::style(small_value_style));
+
+            // city forecast day 3 weather icon
+            let mut icon_forecast_day_3 = WeatherIconWidget::new(&mut city_pane);
+            icon_forecast_day_3.set_align(Align::Center, 60, 141);
 
             // city last updated
             let mut last_update = Label::create(&mut city_pane).unwrap();
-            last_update.set_align(Align::Center, 0, 175);
+            last_update.set_align(Align::Center, 0, 194);
             let text = CString::new("").unwrap();
             last_update.set_text(text.as_c_str()).unwrap();
-            last_update.add_style(Part::Main, Box::leak(font_10_blk_style()));
+            last_update.add_style(Part::Main, theme::style(last_update_style));
 
             cities_labels.push(CityLabels {
                 title,
@@ -242,6 +282,7 @@ impl<'a> HomePane<'a> {
                 date,
                 temp,
                 weather_descr,
+                weather_icon,
                 uv,
                 feels_like,
                 aqi,
@@ -250,28 +291,67 @@ impl<'a> HomePane<'a> {
                 wind_dir,
                 title_forecast_day_1,
                 forecast_day_1,
+                icon_forecast_day_1,
                 title_forecast_day_2,
                 forecast_day_2,
+                icon_forecast_day_2,
                 title_forecast_day_3,
                 forecast_day_3,
+                icon_forecast_day_3,
                 last_update,
             })
         }
 
+        lvgl_misc::hide_obj(&mut home_pane);
+
         Self {
             home_pane,
             cities_labels,
         }
     }
 
-    pub fn show(&mut self) {
+    fn id(&self) -> PaneId {
+        PaneId::Home
+    }
+
+    fn nav_title(&self) -> Option<&'static str> {
+        Some(i18n::text().nav_home)
+    }
+
+    fn show(&mut self) {
         lvgl_misc::show_obj(&mut self.home_pane);
     }
 
-    pub fn hide(&mut self) {
+    fn hide(&mut self) {
         lvgl_misc::hide_obj(&mut self.home_pane);
     }
 
+    fn handle_request(&mut self, request: &UiRequest) {
+        match request {
+            UiRequest::SetCityTime(city_number, time, date) => {
+                self.set_city_time_date(*city_number, time.clone(), date.clone())
+            }
+            UiRequest::SetCitiesTitles(cities_info) => self.set_cities_title(cities_info.clone()),
+            UiRequest::SetCityForecast(city_number, city_forecast) => {
+                self.set_city_forecast(*city_number, city_forecast.clone())
+            }
+            UiRequest::SetTheme(..) => self.apply_theme(),
+            _ => {}
+        }
+    }
+}
+
+impl<'a> HomePane<'a> {
+    /// The colors of the weather icons are set with the icon, they are set again for the theme
+    pub fn apply_theme(&mut self) {
+        for city_labels in self.cities_labels.iter_mut() {
+            city_labels.weather_icon.apply_theme();
+            city_labels.icon_forecast_day_1.apply_theme();
+            city_labels.icon_forecast_day_2.apply_theme();
+            city_labels.icon_forecast_day_3.apply_theme();
+        }
+    }
+
     pub fn set_cities_title(&mut self, cities_info: Vec<CityInfo>) {
         for (i, city) in cities_info.iter().enumerate() {
             let city_title = format!("{}\n{} {}", city.city_name, city.state, city.zipcode);
@@ -316,6 +396,11 @@ impl<'a> HomePane<'a> {
             )
             .unwrap();
 
+        // Set city weather icon
+        self.cities_labels[city_number]
+            .weather_icon
+            .set_icon(city_forecast.weather_icon);
+
         // Set city feels like
         self.cities_labels[city_number]
             .feels_like
@@ -384,6 +469,11 @@ impl<'a> HomePane<'a> {
             )
             .unwrap();
 
+        // Set city day 1 weather icon
+        self.cities_labels[city_number]
+            .icon_forecast_day_1
+            .set_icon(city_forecast.icon_forecast_day_1);
+
         // Set city day 1 forecast
         self.cities_labels[city_number]
             .forecast_day_1
@@ -404,6 +494,11 @@ impl<'a> HomePane<'a> {
             )
             .unwrap();
 
+        // Set city day 2 weather icon
+        self.cities_labels[city_number]
+            .icon_forecast_day_2
+            .set_icon(city_forecast.icon_forecast_day_2);
+
         // Set city day 2 forecast
         self.cities_labels[city_number]
             .forecast_day_2
@@ -424,6 +519,11 @@ impl<'a> HomePane<'a> {
             )
             .unwrap();
 
+        // Set city day 3 weather icon
+        self.cities_labels[city_number]
+            .icon_forecast_day_3
+            .set_icon(city_forecast.icon_forecast_day_3);
+
         // Set city day 3 forecast
         self.cities_labels[city_number]
             .forecast_day_3
@@ -483,114 +583,77 @@ fn get_aqi_bg_color(aqi_value: u16) -> Color {
 //*****************************************************************************
 //                      Sytles for Home Pane
 //*****************************************************************************
-fn home_pane_style() -> Box<Style> {
-    let mut style = Style::default();
-    style.set_bg_color(Color::from_rgb((0, 0, 0)));
+fn home_pane_style(style: &mut Style, palette: &Palette) {
+    style.set_bg_color(theme::color(palette.background));
     style.set_radius(0);
     style.set_border_width(0);
-    style.set_pad_top(12);
-    style.set_pad_bottom(12);
-    style.set_pad_left(12);
-    style.set_pad_right(12);
+    theme::set_pad(style, theme::PANE_PAD, theme::PANE_PAD);
     style.set_layout(Layout::flex());
-    style.set_flex_flow(FlexFlow::ROW);
+    style.set_flex_flow(layout::pick(FlexFlow::ROW, FlexFlow::ROW_WRAP));
     style.set_flex_main_place(FlexAlign::CENTER);
-
-    Box::new(style)
 }
 
-fn city_pane_style() -> Box<Style> {
-    let mut style = Style::default();
-    // light steel blue 176.196,222 Antique White 250, 235, 215 yellow 2 238, 238, 0
-    style.set_bg_color(Color::from_rgb((255, 215, 0))); // gold
-    style.set_border_width(0);
-    style.set_pad_top(2);
-    style.set_pad_bottom(2);
-    style.set_pad_left(2);
-    style.set_pad_right(2);
+fn city_pane_style(style: &mut Style, palette: &Palette) {
+    style.set_bg_color(theme::color(palette.surface));
+    style.set_border_color(theme::color(palette.outline));
+    style.set_border_width(palette.outline_width);
+    theme::set_pad(style, theme::BUTTONS_PAD, theme::BUTTONS_PAD);
+}
 
-    Box::new(style)
+// Titles of the values
+fn caption_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.surface_text));
+    style.set_text_font(theme::font(FontSize::Small));
 }
 
-// Font 16 color red
-fn city_title_style() -> Box<Style> {
-    let mut style = Style::default();
-    style.set_text_color(Color::from_rgb((139, 0, 0))); // red4
+fn city_title_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.surface_title));
     style.set_text_align(TextAlign::Center);
-    style.set_text_font(unsafe { Font::new_raw(lv_font_montserrat_16) });
-
-    Box::new(style)
+    style.set_text_font(theme::font(FontSize::Medium));
 }
 
-// Font 24 color black
-fn time_style() -> Box<Style> {
-    let mut style = Style::default();
-    style.set_text_color(Color::from_rgb((0, 0, 0))); // black
+fn time_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.surface_text));
     style.set_text_align(TextAlign::Center);
-    style.set_text_font(unsafe { Font::new_raw(lv_font_montserrat_24) });
-
-    Box::new(style)
+    style.set_text_font(theme::font(FontSize::Large));
 }
 
-// Color scale, black font, green background
-fn color_scale_style() -> Box<Style> {
-    let mut style = Style::default();
+// Color scale, black font on the background color of the value, the same for every theme
+fn color_scale_style(style: &mut Style, _palette: &Palette) {
     style.set_text_color(Color::from_rgb((0, 0, 0))); // black
     style.set_text_align(TextAlign::Center);
-    style.set_text_font(unsafe { Font::new_raw(lv_font_montserrat_16) });
+    style.set_text_font(theme::font(FontSize::Medium));
     style.set_border_color(Color::from_rgb((0, 0, 0))); // black
     style.set_border_width(2);
     style.set_bg_opa(Opacity::OPA_COVER);
-
-    Box::new(style)
 }
 
-// Font 36 color blue
-fn temperature_style() -> Box<Style> {
-    let mut style = Style::default();
-    style.set_text_color(Color::from_rgb((0, 0, 139))); // navy blue
+fn temperature_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.surface_value));
     style.set_text_align(TextAlign::Center);
-    style.set_text_font(unsafe { Font::new_raw(lv_font_montserrat_36) });
-
-    Box::new(style)
+    style.set_text_font(theme::font(FontSize::Huge));
 }
 
-// Font 14 color blue
-fn font_14_blu_style() -> Box<Style> {
-    let mut style = Style::default();
-    style.set_text_color(Color::from_rgb((0, 0, 139))); // navy blue
+fn value_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.surface_value));
     style.set_text_align(TextAlign::Center);
-    style.set_text_font(unsafe { Font::new_raw(lv_font_montserrat_16) });
-
-    Box::new(style)
+    style.set_text_font(theme::font(FontSize::Medium));
 }
 
-// Font 12 color blue
-fn font_12_blu_style() -> Box<Style> {
-    let mut style = Style::default();
-    style.set_text_color(Color::from_rgb((0, 0, 139))); // navy blue
+fn small_value_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.surface_value));
     style.set_text_align(TextAlign::Center);
-    style.set_text_font(unsafe { Font::new_raw(lv_font_montserrat_12) });
-
-    Box::new(style)
+    style.set_text_font(theme::font(FontSize::Small));
 }
 
-// Font 12 color red
-fn font_12_red_style() -> Box<Style> {
-    let mut style = Style::default();
-    style.set_text_color(Color::from_rgb((139, 0, 0))); // red4
+fn small_title_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.surface_title));
     style.set_text_align(TextAlign::Center);
-    style.set_text_font(unsafe { Font::new_raw(lv_font_montserrat_12) });
-
-    Box::new(style)
+    style.set_text_font(theme::font(FontSize::Small));
 }
 
-// Font 10 color black
-fn font_10_blk_style() -> Box<Style> {
-    let mut style = Style::default();
-    style.set_text_color(Color::from_rgb((0, 0, 0))); // black
+fn last_update_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.surface_text));
     style.set_text_align(TextAlign::Center);
-    style.set_text_font(unsafe { Font::new_raw(lv_font_montserrat_10) });
-
-    Box::new(style)
+    style.set_text_font(theme::font(FontSize::Tiny));
 }
@@ -0,0 +1,96 @@
+//! The embedded HTTP server for the JSON API.
+//!
+//! The server only moves bytes, the routing and serialization is done by `web_api::handle_request`.
+use crate::model::ModelRequest;
+use crate::web_api::{self, ApiMethod, ApiSnapshot, MAX_BODY_SIZE};
+
+use anyhow::Result;
+use esp_idf_svc::http::server::{Configuration, EspHttpConnection, EspHttpServer, Request};
+use esp_idf_svc::http::Method;
+use esp_idf_svc::io::{Read, Write};
+
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+
+use log::{info, warn};
+
+pub struct HttpServer {
+    _server: EspHttpServer<'static>,
+}
+
+impl HttpServer {
+    pub fn start(snapshot: Arc<Mutex<ApiSnapshot>>, tx: Sender<ModelRequest>) -> Result<Self> {
+        let mut server = EspHttpServer::new(&Configuration {
+            stack_size: 10 * 1024,
+            uri_match_wildcard: true,
+            ..Default::default()
+        })?;
+
+        for (method, api_method) in [(Method::Get, ApiMethod::Get), (Method::Put, ApiMethod::Put)] {
+            let snapshot = snapshot.clone();
+            let tx = tx.clone();
+
+            server.fn_handler("/api/*", method, move |request| {
+                serve(request, api_method, &snapshot, &tx)
+            })?;
+        }
+
+        info!("HTTP server started");
+
+        Ok(Self { _server: server })
+    }
+}
+
+fn serve(
+    mut request: Request<&mut EspHttpConnection>,
+    method: ApiMethod,
+    snapshot: &Mutex<ApiSnapshot>,
+    tx: &Sender<ModelRequest>,
+) -> Result<()> {
+    let path = request.uri().to_string();
+    let authorization = request.header("Authorization").map(str::to_string);
+    let body = read_body(&mut request)?;
+
+    let (response, api_requests) = {
+        let snapshot = snapshot.lock().unwrap();
+        web_api::handle_request(
+            method,
+            path.as_str(),
+            authorization.as_deref(),
+            &body,
+            &snapshot,
+        )
+    };
+
+    for api_request in api_requests {
+        if let Err(e) = tx.send(api_request.into()) {
+            warn!("HTTP server model request error = {}", e);
+        }
+    }
+
+    let mut http_response = request.into_response(
+        response.status,
+        None,
+        &[("Content-Type", "application/json")],
+    )?;
+    http_response.write_all(response.body.as_bytes())?;
+
+    Ok(())
+}
+
+// Read the request body, one byte more than the limit is read so oversize bodies can be rejected
+fn read_body(request: &mut Request<&mut EspHttpConnection>) -> Result<Vec<u8>> {
+    let mut body: Vec<u8> = vec![0; MAX_BODY_SIZE + 1];
+    let mut bytes_read = 0;
+
+    while bytes_read < body.len() {
+        let n = request.read(&mut body[bytes_read..])?;
+        if n == 0 {
+            break;
+        }
+        bytes_read += n;
+    }
+
+    body.truncate(bytes_read);
+    Ok(body)
+}
@@ -0,0 +1,57 @@
+//! Screen layout of the panes.
+//!
+//! The panes are laid out for the orientation of the display, set once with `init` before the
+//! panes are created.  The landscape screen is 800 x 480 with the nav bar on a single row, the
+//! portrait screen is 480 x 800 with the nav buttons above the network status.
+use crate::orientation::{Dimension, Orientation};
+
+use std::sync::OnceLock;
+
+/// Size of the panel in landscape
+pub const PANEL_SIZE: Dimension = Dimension {
+    height: 480,
+    width: 800,
+};
+
+static ORIENTATION: OnceLock<Orientation> = OnceLock::new();
+
+/// Sets the orientation of the layout, later calls are ignored since the panes are not laid out
+/// again
+pub fn init(orientation: Orientation) {
+    let _ = ORIENTATION.set(orientation);
+}
+
+pub fn orientation() -> Orientation {
+    ORIENTATION.get().copied().unwrap_or_default()
+}
+
+pub fn is_portrait() -> bool {
+    orientation().is_portrait()
+}
+
+pub fn screen_width() -> i32 {
+    orientation().screen_size(PANEL_SIZE).width as i32
+}
+
+pub fn screen_height() -> i32 {
+    orientation().screen_size(PANEL_SIZE).height as i32
+}
+
+/// Height of the nav bar, the panes shown with the nav bar start below it
+pub fn nav_bar_height() -> i32 {
+    pick(51, 100)
+}
+
+/// Height of the panes shown with the nav bar
+pub fn pane_height() -> i32 {
+    screen_height() - nav_bar_height()
+}
+
+/// Picks the position or the size of a widget for the orientation
+pub fn pick<T>(landscape: T, portrait: T) -> T {
+    if is_portrait() {
+        portrait
+    } else {
+        landscape
+    }
+}
@@ -5,6 +5,8 @@
 //! Use Bounce Buffer since were are also using Wifi, otherwise wifi will cause disply to shift on screen.
 //! See https://www.esp32.com/viewtopic.php?f=12&p=94343
 //! Set CONFIG_FREERTOS_HZ=1000 in sdkconfig.defaults.
+use crate::orientation::Orientation;
+
 use std::ptr::null_mut;
 
 use core::cell::UnsafeCell;
@@ -15,7 +17,9 @@ use esp_idf_svc::sys::{
     esp_lcd_panel_draw_bitmap,
     esp_lcd_panel_handle_t,
     esp_lcd_panel_init,
+    esp_lcd_panel_mirror,
     esp_lcd_panel_reset,
+    esp_lcd_panel_swap_xy,
     esp_lcd_rgb_panel_config_t,
     esp_lcd_rgb_panel_config_t__bindgen_ty_1,
     //esp_lcd_rgb_panel_get_frame_buffer,
@@ -429,6 +433,25 @@ impl LcdPanel {
         Ok(Self { panel })
     }
 
+    /// Draws the bitmaps in the screen coordinates of the orientation, the portrait screens are
+    /// VER_RES wide and HOR_RES high.  The RGB panel driver mirrors the coordinates before it
+    /// swaps them.
+    pub fn set_orientation(&mut self, orientation: Orientation) -> Result<(), EspError> {
+        let (swap_xy, mirror_x, mirror_y) = match orientation {
+            Orientation::Landscape => (false, false, false),
+            Orientation::Portrait => (true, true, false),
+            Orientation::InvertedLandscape => (false, true, true),
+            Orientation::InvertedPortrait => (true, false, true),
+        };
+
+        unsafe {
+            esp_idf_svc::sys::esp!(esp_lcd_panel_swap_xy(self.panel, swap_xy))?;
+            esp_idf_svc::sys::esp!(esp_lcd_panel_mirror(self.panel, mirror_x, mirror_y))?;
+        };
+
+        Ok(())
+    }
+
     ///
     /// Sets pixel colors in a rectangular region.
     ///
@@ -0,0 +1,260 @@
+//! Persistent log files.
+//!
+//! The App logger sends every record to the ESP-IDF console logger as before and also queues the
+//! records at or above the log level of SETTINGS.TXT.  The model writes the queue to the log files
+//! in the LOGS directory of the SDCard every two seconds, the logger itself never touches the
+//! SDCard since records are logged from every thread.
+//!
+//! The files are LOGS/LOG0.TXT to LOGS/LOG{n-1}.TXT, n being `log_files` in SETTINGS.TXT.  Once
+//! the current file reaches `log_file_kb` the next file is emptied and the records go there, so
+//! the oldest records are the ones dropped.  FAT files can't be renamed with embedded-sdmmc, so
+//! the file holding the latest records is the first one that is not full.
+//!
+//! Each session starts with the boot count kept in NVS, the reason of the last reset and the
+//! firmware version, so a crash or a brownout can be told apart from a power cycle.
+use crate::config_storage::ConfigStorage;
+use crate::settings::{self, Settings};
+
+use anyhow::Result;
+use esp_idf_svc::hal::reset::ResetReason;
+use esp_idf_svc::log::EspLogger;
+use esp_idf_svc::nvs::{EspDefaultNvsPartition, EspNvs};
+use esp_idf_svc::sys::esp_timer_get_time;
+
+use chrono::{DateTime, Utc};
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use log::{info, warn, LevelFilter, Log, Metadata, Record};
+
+pub const LOG_DIR: &str = "LOGS";
+
+// Records waiting to be written, the oldest are dropped when the SDCard can't keep up or is missing
+const MAX_QUEUED_LINES: usize = 256;
+
+// Records logged before the clock is synced are dated with the time since boot, the clock starts
+// at 1970 until then
+const CLOCK_SYNCED_SECS: u64 = 1_704_067_200; // 2024-01-01
+
+static LOGGER: FileLogger = FileLogger {
+    esp_logger: EspLogger::new(),
+    queue: Mutex::new(LogQueue {
+        level: settings::DEFAULT_LOG_LEVEL,
+        lines: VecDeque::new(),
+        dropped: 0,
+    }),
+};
+
+struct LogQueue {
+    level: LevelFilter,
+    lines: VecDeque<String>,
+    dropped: u32,
+}
+
+struct FileLogger {
+    esp_logger: EspLogger,
+    queue: Mutex<LogQueue>,
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.esp_logger.enabled(metadata) || metadata.level() <= self.queue.lock().unwrap().level
+    }
+
+    fn log(&self, record: &Record) {
+        if self.esp_logger.enabled(record.metadata()) {
+            self.esp_logger.log(record);
+        }
+
+        let mut queue = self.queue.lock().unwrap();
+        if record.level() > queue.level {
+            return;
+        }
+
+        if queue.lines.len() >= MAX_QUEUED_LINES {
+            queue.lines.pop_front();
+            queue.dropped += 1;
+        }
+
+        queue.lines.push_back(format!(
+            "{} {:<5} {}: {}\n",
+            timestamp(),
+            record.level(),
+            record.target(),
+            record.args()
+        ));
+    }
+
+    fn flush(&self) {}
+}
+
+/// Installs the App logger, call it instead of `EspLogger::initialize_default`.  The records are
+/// queued at the default level until SETTINGS.TXT is read.
+pub fn init() {
+    log::set_logger(&LOGGER).unwrap();
+    set_file_level(settings::DEFAULT_LOG_LEVEL);
+}
+
+/// Level of the records written to the log files, the console level is not changed
+pub fn set_file_level(level: LevelFilter) {
+    LOGGER.queue.lock().unwrap().level = level;
+    log::set_max_level(level.max(LOGGER.esp_logger.get_max_level()));
+}
+
+/// Counts the boot in NVS and logs the first record of the session
+pub fn log_session_start(nvs_partition: EspDefaultNvsPartition) {
+    let boot_count = match EspNvs::new(nvs_partition, "app", true) {
+        Ok(mut nvs) => {
+            let boot_count = nvs.get_u32("boot_count").ok().flatten().unwrap_or(0) + 1;
+            if let Err(e) = nvs.set_u32("boot_count", boot_count) {
+                warn!("Boot count write error = {}", e);
+            }
+            boot_count
+        }
+        Err(e) => {
+            warn!("Boot count NVS error = {}", e);
+            0
+        }
+    };
+
+    info!(
+        "===== Boot {}, reset reason {:?}, firmware {} =====",
+        boot_count,
+        ResetReason::get(),
+        env!("CARGO_PKG_VERSION")
+    );
+}
+
+/// Name of a log file in the LOGS directory
+pub fn log_file_name(index: usize) -> String {
+    format!("LOG{}.TXT", index)
+}
+
+/// Index of the file to append to and whether it has to be emptied first.  `sizes` holds the size
+/// of each file, None when it is missing.  All the files are only full when the power was lost
+/// while the next one was being emptied.
+pub fn pick_log_file(sizes: &[Option<u32>], max_size: u32) -> (usize, bool) {
+    match sizes
+        .iter()
+        .position(|size| size.map_or(true, |size| size < max_size))
+    {
+        Some(index) => (index, false),
+        None => (0, true),
+    }
+}
+
+// Date of the records, UTC with milliseconds once the clock is synced
+fn timestamp() -> String {
+    match SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(since_epoch) if since_epoch.as_secs() >= CLOCK_SYNCED_SECS => {
+            DateTime::<Utc>::from(SystemTime::now())
+                .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+                .to_string()
+        }
+        _ => {
+            let uptime_ms = unsafe { esp_timer_get_time() } / 1000;
+            format!("+{}.{:03}", uptime_ms / 1000, uptime_ms % 1000)
+        }
+    }
+}
+
+/// Writes the queued records to the log files
+pub struct LogFiles {
+    files: usize,
+    max_size: u32,
+    // Index and size of the file being appended to, found again after a media change
+    current: Option<(usize, u32)>,
+    failed: bool,
+}
+
+impl LogFiles {
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            files: settings.log_files,
+            max_size: settings.log_file_kb * 1024,
+            current: None,
+            failed: false,
+        }
+    }
+
+    /// Forget the current file, the storage has changed
+    pub fn reset(&mut self) {
+        self.current = None;
+        self.failed = false;
+    }
+
+    /// Appends the queued records to the current file, the records are dropped when the storage
+    /// has no directories
+    pub fn flush(&mut self, storage: &mut dyn ConfigStorage) {
+        let (lines, dropped) = {
+            let mut queue = LOGGER.queue.lock().unwrap();
+            let dropped = queue.dropped;
+            queue.dropped = 0;
+            (queue.lines.drain(..).collect::<Vec<String>>(), dropped)
+        };
+
+        if lines.is_empty() && dropped == 0 {
+            return;
+        }
+
+        let mut contents = String::new();
+        if dropped > 0 {
+            contents.push_str(&format!("----- {} records dropped -----\n", dropped));
+        }
+        for line in lines {
+            contents.push_str(&line);
+        }
+
+        match self.append(storage, &contents) {
+            Ok(()) => self.failed = false,
+
+            // Only the first failure is logged, the warning would be queued again every time
+            Err(e) => {
+                self.current = None;
+                if !self.failed {
+                    self.failed = true;
+                    warn!("Log file error = {}", e);
+                }
+            }
+        }
+    }
+
+    fn append(&mut self, storage: &mut dyn ConfigStorage, contents: &str) -> Result<()> {
+        let (index, size) = match self.current {
+            Some(current) => current,
+            None => self.find_current_file(storage)?,
+        };
+
+        storage.append_in_dir(LOG_DIR, &log_file_name(index), &[], contents.as_bytes())?;
+        let size = size + contents.len() as u32;
+
+        // The next file is emptied right away, so after a reboot the file that is not full is
+        // still the one holding the latest records
+        if size >= self.max_size {
+            let next = (index + 1) % self.files;
+            storage.delete_in_dir(LOG_DIR, &log_file_name(next))?;
+            self.current = Some((next, 0));
+        } else {
+            self.current = Some((index, size));
+        }
+
+        Ok(())
+    }
+
+    fn find_current_file(&mut self, storage: &mut dyn ConfigStorage) -> Result<(usize, u32)> {
+        let mut sizes = Vec::with_capacity(self.files);
+        for index in 0..self.files {
+            sizes.push(storage.file_size_in_dir(LOG_DIR, &log_file_name(index))?);
+        }
+
+        let (index, empty_first) = pick_log_file(&sizes, self.max_size);
+        if empty_first {
+            storage.delete_in_dir(LOG_DIR, &log_file_name(index))?;
+            return Ok((index, 0));
+        }
+
+        Ok((index, sizes[index].unwrap_or(0)))
+    }
+}
@@ -3,11 +3,19 @@
 use core::mem;
 use cstr_core::CStr;
 use cstr_core::CString;
-use lvgl::widgets::{Btn, Btnmatrix, Dropdown, Keyboard, Label, Textarea};
+use lvgl::widgets::{Bar, Btn, Btnmatrix, Chart, Dropdown, Keyboard, Label, Slider, Textarea};
 use lvgl::NativeObject;
 use lvgl::{Color, Obj};
 use lvgl_sys::*;
 
+// ---------- Custom Fonts ----------
+// Compiled with lvgl from the custom-fonts folder, see LVGL_FONTS_DIR in config.toml
+extern "C" {
+    pub static cloud24: lv_font_t;
+    // Only has the digits, ':' and the space
+    pub static gotham_bold_80: lv_font_t;
+}
+
 // ---------- Object Functions ----------
 pub fn hide_obj(obj: &mut Obj) {
     unsafe {
@@ -27,6 +35,16 @@ pub fn show_obj(obj: &mut Obj) {
     }
 }
 
+pub fn set_obj_bg_color(obj: &mut Obj, color: Color) {
+    unsafe {
+        lv_obj_set_style_bg_color(
+            obj.raw().as_mut() as *mut lvgl_sys::lv_obj_t,
+            color.into(),
+            lvgl_sys::LV_PART_MAIN,
+        );
+    }
+}
+
 // ---------- Keyboard Functions ----------
 pub fn hide_keyboard(kybd: &mut Keyboard) {
     unsafe {
@@ -112,6 +130,174 @@ pub fn set_label_bg_color(label: &mut Label, color: Color) {
     }
 }
 
+pub fn set_label_text_color(label: &mut Label, color: Color) {
+    unsafe {
+        lv_obj_set_style_text_color(
+            label.raw().as_mut() as *mut lvgl_sys::lv_obj_t,
+            color.into(),
+            lvgl_sys::LV_PART_MAIN,
+        );
+    }
+}
+
+// Places the label on the right of the base label, their bottoms lined up
+pub fn label_align_to_right_bottom(label: &mut Label, base: &Label, x: i16, y: i16) {
+    unsafe {
+        lv_obj_align_to(
+            label.raw().as_mut() as *mut lvgl_sys::lv_obj_t,
+            base.raw().as_ptr() as *const lvgl_sys::lv_obj_t,
+            LV_ALIGN_OUT_RIGHT_BOTTOM.try_into().unwrap(),
+            x,
+            y,
+        );
+    }
+}
+
+pub fn hide_label(label: &mut Label) {
+    unsafe {
+        lv_obj_add_flag(
+            label.raw().as_mut() as *mut lvgl_sys::lv_obj_t,
+            lvgl_sys::LV_OBJ_FLAG_HIDDEN,
+        );
+    }
+}
+
+pub fn show_label(label: &mut Label) {
+    unsafe {
+        lv_obj_clear_flag(
+            label.raw().as_mut() as *mut lvgl_sys::lv_obj_t,
+            lvgl_sys::LV_OBJ_FLAG_HIDDEN,
+        );
+    }
+}
+
+pub fn set_label_clickable(label: &mut Label) {
+    unsafe {
+        lv_obj_add_flag(
+            label.raw().as_mut() as *mut lvgl_sys::lv_obj_t,
+            lvgl_sys::LV_OBJ_FLAG_CLICKABLE,
+        );
+    }
+}
+
+// ---------- Bar Functions ----------
+pub fn hide_bar(bar: &mut Bar) {
+    unsafe {
+        lv_obj_add_flag(
+            bar.raw().as_mut() as *mut lvgl_sys::lv_obj_t,
+            lvgl_sys::LV_OBJ_FLAG_HIDDEN,
+        );
+    }
+}
+
+pub fn show_bar(bar: &mut Bar) {
+    unsafe {
+        lv_obj_clear_flag(
+            bar.raw().as_mut() as *mut lvgl_sys::lv_obj_t,
+            lvgl_sys::LV_OBJ_FLAG_HIDDEN,
+        );
+    }
+}
+
+// ---------- Slider Functions ----------
+// The lv_slider functions are static inline, a slider is a bar so the lv_bar functions are used
+pub fn slider_set_range(slider: &mut Slider, min: i32, max: i32) {
+    unsafe {
+        lv_bar_set_range(slider.raw().as_mut() as *mut lvgl_sys::lv_obj_t, min, max);
+    }
+}
+
+pub fn slider_set_value(slider: &mut Slider, value: i32) {
+    unsafe {
+        lv_bar_set_value(
+            slider.raw().as_mut() as *mut lvgl_sys::lv_obj_t,
+            value,
+            lv_anim_enable_t_LV_ANIM_OFF,
+        );
+    }
+}
+
+pub fn slider_get_value(slider: &Slider) -> i32 {
+    unsafe { lv_bar_get_value(slider.raw().as_ptr() as *const lvgl_sys::lv_obj_t) }
+}
+
+// ---------- Chart Functions ----------
+// LV_CHART_POINT_NONE is LV_COORD_MAX, the bindings don't have it
+pub const CHART_POINT_NONE: lv_coord_t = (1 << 13) - 1;
+
+// Line chart without point markers
+pub fn chart_set_line_type(chart: &mut Chart, point_count: u16, horizontal_lines: u8) {
+    unsafe {
+        let obj = chart.raw().as_mut() as *mut lvgl_sys::lv_obj_t;
+        lv_chart_set_type(obj, lvgl_sys::LV_CHART_TYPE_LINE.try_into().unwrap());
+        lv_chart_set_point_count(obj, point_count);
+        lv_chart_set_div_line_count(obj, horizontal_lines, 0);
+        lv_obj_set_style_size(obj, 0, lvgl_sys::LV_PART_INDICATOR);
+    }
+}
+
+// Tick labels on the left of the chart, `draw_size` is the room kept for them
+pub fn chart_set_y_axis_labels(chart: &mut Chart, label_count: i16, draw_size: i16) {
+    unsafe {
+        lv_chart_set_axis_tick(
+            chart.raw().as_mut() as *mut lvgl_sys::lv_obj_t,
+            lvgl_sys::LV_CHART_AXIS_PRIMARY_Y.try_into().unwrap(),
+            4,
+            0,
+            label_count,
+            1,
+            true,
+            draw_size,
+        );
+    }
+}
+
+pub fn chart_set_range(chart: &mut Chart, min: i16, max: i16) {
+    unsafe {
+        lv_chart_set_range(
+            chart.raw().as_mut() as *mut lvgl_sys::lv_obj_t,
+            lvgl_sys::LV_CHART_AXIS_PRIMARY_Y.try_into().unwrap(),
+            min,
+            max,
+        );
+    }
+}
+
+pub fn chart_add_series(chart: &mut Chart, color: Color) -> *mut lv_chart_series_t {
+    unsafe {
+        lv_chart_add_series(
+            chart.raw().as_mut() as *mut lvgl_sys::lv_obj_t,
+            color.into(),
+            lvgl_sys::LV_CHART_AXIS_PRIMARY_Y.try_into().unwrap(),
+        )
+    }
+}
+
+pub fn chart_set_series_color(chart: &mut Chart, series: *mut lv_chart_series_t, color: Color) {
+    unsafe {
+        lv_chart_set_series_color(
+            chart.raw().as_mut() as *mut lvgl_sys::lv_obj_t,
+            series,
+            color.into(),
+        );
+    }
+}
+
+// Sets the points of the series, None leaves a gap in the line
+pub fn chart_set_series_values(
+    chart: &mut Chart,
+    series: *mut lv_chart_series_t,
+    values: &[Option<i16>],
+) {
+    unsafe {
+        let obj = chart.raw().as_mut() as *mut lvgl_sys::lv_obj_t;
+        for (id, value) in values.iter().enumerate() {
+            lv_chart_set_value_by_id(obj, series, id as u16, value.unwrap_or(CHART_POINT_NONE));
+        }
+        lv_chart_refresh(obj);
+    }
+}
+
 // ---------- Dropdown Functions ----------
 pub fn get_dropdown_selected_str(dd: &Dropdown) -> String {
     let mut buf: [u8; 32] = [0; 32];
@@ -154,6 +340,9 @@ pub fn disable_dropdown(dd: &mut Dropdown) {
 }
 
 // ---------- Button Matrix Functions ----------
+// Returned by lv_btnmatrix_get_selected_btn when no button is selected
+pub const BTNMATRIX_BTN_NONE: u16 = 0xFFFF;
+
 pub fn btnmatrix_set_map(btnm: &mut Btnmatrix, btn_matrix_vec: Vec<CString>) {
     // Turning each null-terminated string into a pointer.
     // `into_raw` takes ownershop, gives us the pointer and does NOT drop the data.
@@ -209,12 +398,35 @@ pub fn btnmatrix_clear_btn_ctrl(btnm: &mut Btnmatrix, btn_id: u16) {
     }
 }
 
+pub fn btnmatrix_clear_checked_all(btnm: &mut Btnmatrix) {
+    unsafe {
+        lv_btnmatrix_clear_btn_ctrl_all(
+            btnm.raw().as_mut() as *mut lvgl_sys::lv_obj_t,
+            lvgl_sys::LV_BTNMATRIX_CTRL_CHECKED.try_into().unwrap(),
+        );
+    }
+}
+
 pub fn btnmatrix_set_one_checked(btnm: &mut Btnmatrix) {
     unsafe {
         lv_btnmatrix_set_one_checked(btnm.raw().as_mut() as *mut lvgl_sys::lv_obj_t, true);
     }
 }
 
+// Buttons where only one at a time is checked, the first one starts checked
+pub fn btnmatrix_set_one_checked_map(btn_matrix: &mut Btnmatrix, names: Vec<&str>) {
+    let mut btnmatrix_vec: Vec<CString> = names
+        .iter()
+        .map(|name| CString::new(*name).unwrap())
+        .collect();
+    btnmatrix_vec.push(CString::new("").unwrap());
+
+    btnmatrix_set_map(btn_matrix, btnmatrix_vec);
+    btnmatrix_set_btn_ctrl_all(btn_matrix);
+    btnmatrix_set_one_checked(btn_matrix);
+    btnmatrix_set_btn_ctrl(btn_matrix, 0);
+}
+
 pub fn btnmatrix_get_selected_btn(btnm: &Btnmatrix) -> u16 {
     unsafe { lv_btnmatrix_get_selected_btn(btnm.raw().as_ptr() as *const lvgl_sys::lv_obj_t) }
 }
@@ -1,23 +1,52 @@
+pub mod backlight;
 pub mod cities_settings_pane;
-pub mod file_store;
+pub mod clock_pane;
+pub mod device_storage;
+pub mod display_settings_pane;
 pub mod forecast_weather_api;
 pub mod gt911;
+pub mod history_pane;
 pub mod home_pane;
+pub mod http_server;
+pub mod layout;
 pub mod lcd_panel;
+pub mod log_file;
 pub mod lvgl_misc;
 pub mod model;
+pub mod mqtt_client;
 pub mod navigation_pane;
+pub mod ota_update;
+pub mod pane;
+pub mod pane_router;
+pub mod sd_card;
 pub mod startup_pane;
+pub mod theme;
 pub mod ui;
+pub mod weather_icon_widget;
+pub mod wifi_manager;
 pub mod wifi_settings_pane;
 
-use crate::file_store::FileStore;
+// The modules that don't touch the hardware are in weather-core so they are tested on the host
+pub use weather_core::{
+    brightness, cities, config_storage, date_format, daylight, file_store, history, i18n,
+    key_value, mqtt, nav_stack, orientation, ota, provider, settings, ui_event, units,
+    weather_icons, web_api,
+};
+
+use crate::backlight::Backlight;
+use crate::config_storage::{ConfigStorage, MemoryStorage};
+use crate::device_storage::{FlashStorage, RemovableStorage};
 use crate::gt911::GT911;
+use crate::http_server::HttpServer;
 use crate::model::{Model, ModelRequest};
+use crate::settings::Settings;
 use crate::ui::{UiRequest, UserInterface};
+use crate::web_api::ApiSnapshot;
+use crate::wifi_manager::WifiManager;
 
 use core::time::Duration;
 use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
 
 use esp_idf_svc::hal::{
     delay::Ets,
@@ -27,26 +56,26 @@ use esp_idf_svc::hal::{
         config::TimerConfig,
         {LedcDriver, LedcTimerDriver},
     },
+    peripheral::Peripheral,
     peripherals::Peripherals,
+    spi::SpiAnyPins,
     units::FromValueType,
 };
 
 use esp_idf_svc::{
-    eventloop::EspSystemEventLoop,
-    nvs::EspDefaultNvsPartition,
-    timer::EspTaskTimerService,
-    wifi::{BlockingWifi, EspWifi},
+    eventloop::EspSystemEventLoop, nvs::EspDefaultNvsPartition, timer::EspTaskTimerService,
+    wifi::EspWifi,
 };
 
-use log::info;
+use log::{info, warn};
 
 fn main() {
     // It is necessary to call this function once. Otherwise some patches to the runtime
     // implemented by esp-idf-sys might not link properly. See https://github.com/esp-rs/esp-idf-template/issues/71
     esp_idf_svc::sys::link_patches();
 
-    // Bind the log crate to the ESP Logging facilities
-    esp_idf_svc::log::EspLogger::initialize_default();
+    // Bind the log crate to the ESP Logging facilities, the records are also kept for the log files
+    log_file::init();
 
     info!("========== Starting App ==========");
 
@@ -58,18 +87,20 @@ fn main() {
     let sys_loop = EspSystemEventLoop::take().unwrap();
     let nvs = EspDefaultNvsPartition::take().unwrap();
 
+    // Start the session in the log files with the boot count and the reason of the last reset
+    log_file::log_session_start(nvs.clone());
+
     #[allow(unused)]
     let pins = peripherals.pins;
 
-    // Create file store to read/write text files
-    let file_store = FileStore::init(
+    // Keep the settings on the SDCard, or in the internal flash when there is no SDCard
+    let mut storage = open_storage(
         peripherals.spi2,
         pins.gpio12.into(),
         pins.gpio11.into(),
         pins.gpio13.into(),
         pins.gpio10.into(),
-    )
-    .unwrap();
+    );
 
     // Create I2C0 for GT911
     let i2c = peripherals.i2c0;
@@ -79,7 +110,7 @@ fn main() {
     let i2c = I2cDriver::new(i2c, sda, scl, &config).unwrap();
 
     // Create LedcDriver for LCD panel backlight
-    let mut ledc_driver = LedcDriver::new(
+    let ledc_driver = LedcDriver::new(
         peripherals.ledc.channel0,
         LedcTimerDriver::new(
             peripherals.ledc.timer0,
@@ -90,19 +121,30 @@ fn main() {
     )
     .unwrap();
 
-    // Create Wifi service
-    let wifi = BlockingWifi::wrap(
-        EspWifi::new(peripherals.modem, sys_loop.clone(), Some(nvs)).unwrap(),
-        sys_loop.clone(),
-    )
-    .unwrap();
+    // Create Wifi service, the wifi manager connects in the background and reconnects when the connection drops
+    let wifi = EspWifi::new(peripherals.modem, sys_loop.clone(), Some(nvs)).unwrap();
+    let wifi_manager = WifiManager::new(wifi, &sys_loop).unwrap();
+
+    // Create the HTTP server for the JSON API, the model keeps the data served by the API up to date
+    let api_snapshot = Arc::new(Mutex::new(ApiSnapshot::default()));
+    let _http_server = match HttpServer::start(api_snapshot.clone(), tx2.clone()) {
+        Ok(http_server) => Some(http_server),
+        Err(e) => {
+            warn!("HTTP server error = {}", e);
+            None
+        }
+    };
 
     // Create GT911 touchscreen driver
-    let gt911 = GT911::new(i2c);
+    let mut gt911 = GT911::new(i2c);
     reset_gt911(pins.gpio38.into());
 
-    // Create the user interface
-    UserInterface::new(gt911, tx2, rx1).run();
+    // Create the user interface, the display and the touchscreen are set up for the orientation
+    // of the settings and the text is in their locale
+    let start_settings = read_start_settings(storage.as_mut());
+    i18n::init(start_settings.locale);
+    gt911.set_orientation(start_settings.orientation);
+    UserInterface::new(gt911, start_settings.orientation, tx2, rx1).run();
 
     // Create a periodic timer to call LVGL tick. LVGL needs a system tick to know elapsed time for animations and other tasks.
     //info!("---------- Creating Lvgl Tick Timer ----------");
@@ -116,14 +158,54 @@ fn main() {
     // Let it trigger every 10 milliseconds
     lvgl_tick_timer.every(Duration::from_millis(10)).unwrap();
 
-    // Turn On LCD backlight
-    ledc_driver.set_duty(ledc_driver.get_max_duty()).unwrap();
+    // Turn On LCD backlight, the model dims it from then on
+    let backlight = Backlight::new(ledc_driver);
 
     info!("Creating Model");
-    let mut model = Model::new(wifi, rx2, tx1, file_store);
+    let mut model = Model::new(wifi_manager, backlight, rx2, tx1, storage, api_snapshot);
     model.run();
 }
 
+// Open the settings storage, the SDCard if there is one otherwise the app_storage partition in the
+// internal flash.  If that fails too the settings are only kept in memory until the next restart.
+fn open_storage(
+    spi: impl Peripheral<P = impl SpiAnyPins> + 'static,
+    sclk: gpio::AnyOutputPin,
+    sdo: gpio::AnyOutputPin,
+    sdi: gpio::AnyInputPin,
+    cs: gpio::AnyOutputPin,
+) -> Box<dyn ConfigStorage> {
+    // Used while there is no SDCard
+    let fallback: Box<dyn ConfigStorage> = match FlashStorage::mount("app_storage", "/flash") {
+        Ok(flash_storage) => Box::new(flash_storage),
+        Err(e) => {
+            warn!("Internal flash storage error = {}", e);
+            Box::new(MemoryStorage::default())
+        }
+    };
+
+    let storage: Box<dyn ConfigStorage> = match sd_card::init(spi, sclk, sdo, sdi, cs) {
+        Ok(file_store) => Box::new(RemovableStorage::new(file_store, fallback)),
+        Err(e) => {
+            warn!("SDCard SPI error = {}", e);
+            fallback
+        }
+    };
+
+    info!("Settings storage: {}", storage.name());
+    storage
+}
+
+// The orientation and the locale are needed before the panes are created, a change of these
+// settings, from SETTINGS.TXT, the Display pane or the web API, is used from the next start
+fn read_start_settings(storage: &mut dyn ConfigStorage) -> Settings {
+    storage
+        .read_lines("settings.txt")
+        .ok()
+        .and_then(|lines| Settings::from_lines(&lines).ok())
+        .unwrap_or_default()
+}
+
 // Reset the GT911 chip
 fn reset_gt911(rst_pin: gpio::AnyOutputPin) {
     let mut rst = PinDriver::output(rst_pin).unwrap();
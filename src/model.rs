@@ -1,6 +1,23 @@
+use crate::backlight::Backlight;
+use crate::cities::{CityConditions, CityInfo};
+use crate::config_storage::{ConfigStorage, MediaChange};
+use crate::daylight::SunTimes;
 use crate::forecast_weather_api::ForecastWeather;
+use crate::history::{self, HistoryConfig, HistoryLog, HistoryPeriod, HistoryRead};
+use crate::i18n;
+use crate::log_file::{self, LogFiles};
+use crate::mqtt::{DeviceHealth, MqttConfig};
+use crate::mqtt_client::MqttPublisher;
+use crate::orientation::Orientation;
+use crate::ota::OtaConfig;
+use crate::ota_update;
+use crate::provider::ProviderConfig;
+use crate::settings::{DisplayPrefs, Settings, ThemeName, CITIES_COUNT};
+use crate::theme;
 use crate::ui::UiRequest;
-use crate::FileStore;
+use crate::weather_icons::{self, WeatherIcon};
+use crate::web_api::{ApiConfig, ApiForecast, ApiForecastDay, ApiRequest, ApiSnapshot, ApiStatus};
+use crate::wifi_manager::{WifiManager, WifiState};
 
 use anyhow::*;
 use esp_idf_svc::{
@@ -9,32 +26,75 @@ use esp_idf_svc::{
     io::EspIOError,
     sntp::{EspSntp, SyncStatus},
     timer::EspTaskTimerService,
-    wifi::{BlockingWifi, EspWifi},
+    tls::X509,
 };
 
-use embedded_svc::{
-    http::client::Client as HttpClient,
-    utils::io,
-    wifi::{AuthMethod, ClientConfiguration, Configuration},
-};
+use embedded_svc::{http::client::Client as HttpClient, utils::io};
 
 use core::sync::atomic::{AtomicBool, Ordering};
 use core::time::Duration;
 use std::result::Result::Ok;
 use std::str::Utf8Error;
 use std::sync::mpsc::{Receiver, Sender};
-use std::sync::Arc;
-use std::time::SystemTime;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Instant, SystemTime};
 
 use chrono::{DateTime, TimeZone, Utc};
 use chrono_tz::Tz;
 
-use log::{info, warn};
+use log::{info, warn, LevelFilter};
+
+// How long a newly installed firmware has to fetch the forecasts while the Wifi is connected
+// before it is rolled back
+const FIRMWARE_VERIFY_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+// Settings files written by the App, an interrupted write is recovered before they are read
+const SETTINGS_FILES: [&str; 3] = ["wifi.txt", "cities.txt", "settings.txt"];
+
+// Cities used when there are no valid cities settings in storage
+const DEFAULT_CITIES: [(&str, &str, &str, &str); CITIES_COUNT] = [
+    ("Los Angeles", "CA", "90079", "US/Pacific"),
+    ("Denver", "CO", "80264", "US/Mountain"),
+    ("Chicago", "IL", "60614", "US/Central"),
+    ("New York", "NY", "10001", "US/Eastern"),
+];
+
+// Largest PEM certificate file read from the SDCard
+const MAX_CERTIFICATE_SIZE: usize = 8 * 1024;
 
 #[derive(Debug)]
 pub enum ModelRequest {
     UpdateCitiesInfo(Vec<CityInfo>),
     UpdateWifiCreds(String, String),
+    UpdateFirmware,
+    GetHistory(usize, HistoryPeriod),
+    SetLogLevel(LevelFilter),
+    SetTheme(ThemeName, bool),
+    /// The screen was touched
+    UserActivity,
+    /// Brightness shown while a slider is moved, None when it is released
+    PreviewBrightness(Option<u8>),
+    SetHomeCity(usize),
+    /// Saved and used from the next start
+    SetOrientation(Orientation),
+    /// Display settings shown before they are saved, None goes back to the saved settings
+    PreviewSettings(Option<DisplayPrefs>),
+    SaveSettings(DisplayPrefs),
+}
+
+impl From<ApiRequest> for ModelRequest {
+    fn from(api_request: ApiRequest) -> Self {
+        match api_request {
+            ApiRequest::UpdateCitiesInfo(cities_info) => Self::UpdateCitiesInfo(cities_info),
+            ApiRequest::UpdateWifiCreds(ssid, pass) => Self::UpdateWifiCreds(ssid, pass),
+            ApiRequest::UpdateFirmware => Self::UpdateFirmware,
+            ApiRequest::SetLogLevel(log_level) => Self::SetLogLevel(log_level),
+            ApiRequest::SetTheme(theme_name, auto_dark) => Self::SetTheme(theme_name, auto_dark),
+            ApiRequest::SetHomeCity(city) => Self::SetHomeCity(city),
+            ApiRequest::SetOrientation(orientation) => Self::SetOrientation(orientation),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -47,17 +107,10 @@ enum HttpError {
 }
 
 #[derive(Debug, Clone)]
-pub struct CityInfo {
-    pub city_name: String,
-    pub state: String,
-    pub zipcode: String,
-    pub timezone: String,
-}
-
-#[derive(Debug)]
 pub struct CityForecast {
     pub temp: String,
     pub weather_descr: String,
+    pub weather_icon: Option<WeatherIcon>,
     pub feels_like: String,
     pub uv: String,
     pub aqi: String,
@@ -66,13 +119,26 @@ pub struct CityForecast {
     pub wind_dir: String,
     pub weekday_forecast_day_1: String,
     pub forecast_day_1: String,
+    pub icon_forecast_day_1: Option<WeatherIcon>,
     pub weekday_forecast_day_2: String,
     pub forecast_day_2: String,
+    pub icon_forecast_day_2: Option<WeatherIcon>,
     pub weekday_forecast_day_3: String,
     pub forecast_day_3: String,
+    pub icon_forecast_day_3: Option<WeatherIcon>,
     pub last_update: String,
 }
 
+#[derive(Debug, Clone)]
+pub struct NetworkStatus {
+    pub wifi_state: WifiState,
+    pub ssid: String,
+    pub rssi: Option<i8>,
+    pub ip_address: Option<String>,
+    pub time_synced: bool,
+    pub last_fetch_secs: Option<u64>,
+}
+
 #[derive(Debug)]
 pub struct WifiCredentials {
     pub ssid: String,
@@ -80,21 +146,56 @@ pub struct WifiCredentials {
 }
 
 pub struct Model {
-    wifi: BlockingWifi<EspWifi<'static>>,
+    wifi_manager: WifiManager,
+    backlight: Backlight,
+    screen_asleep: bool,
+    clock_shown: bool,
     rx: Receiver<ModelRequest>,
     tx: Sender<UiRequest>,
-    file_store: FileStore,
+    storage: Box<dyn ConfigStorage>,
     wifi_credentials: WifiCredentials,
     cities_info: Vec<CityInfo>,
     weather_api_key: String,
+    provider: ProviderConfig,
+    provider_certificate: Option<&'static [u8]>,
+    sntp: Option<EspSntp<'static>>,
+    time_synced: bool,
+    forecasts_pending: bool,
+    home_pane_shown: bool,
+    wifi_error_shown: bool,
+    last_fetch: Option<Instant>,
+    // When the forecasts were last sent, they are fetched again after the refresh interval
+    last_forecasts: Instant,
+    api_snapshot: Arc<Mutex<ApiSnapshot>>,
+    mqtt: Option<MqttPublisher>,
+    history: HistoryLog,
+    history_read: Option<HistoryRead>,
+    settings: Settings,
+    // Saved settings while other settings are previewed
+    saved_settings: Option<Settings>,
+    log_files: LogFiles,
+    // Sunrise and sunset of each city from the last forecast
+    sun_times: Vec<Option<SunTimes>>,
+    // Last forecast of each city, formatted again when the units or the date format change
+    forecasts: Vec<Option<ForecastWeather>>,
+    theme_shown: Option<(ThemeName, u8)>,
+    ota_config: Option<OtaConfig>,
+    firmware_pending_verify: bool,
+    // Time the Wifi was connected while the new firmware is pending verify
+    firmware_verify_connected: Duration,
+    firmware_update: Option<JoinHandle<()>>,
+    // Second of the time last sent to the panes
+    time_sent: i64,
 }
 
 impl Model {
     pub fn new(
-        wifi: BlockingWifi<EspWifi<'static>>,
+        wifi_manager: WifiManager,
+        backlight: Backlight,
         rx: Receiver<ModelRequest>,
         tx: Sender<UiRequest>,
-        file_store: FileStore,
+        storage: Box<dyn ConfigStorage>,
+        api_snapshot: Arc<Mutex<ApiSnapshot>>,
     ) -> Self {
         let cities_info: Vec<CityInfo> = Vec::with_capacity(4);
         let wifi_credentials: WifiCredentials = WifiCredentials {
@@ -102,36 +203,86 @@ impl Model {
             pass: String::new(),
         };
         Self {
-            wifi,
+            wifi_manager,
+            backlight,
+            screen_asleep: false,
+            clock_shown: false,
             rx,
             tx,
-            file_store,
+            storage,
             wifi_credentials,
             weather_api_key: String::new(),
             cities_info,
+            provider: ProviderConfig::default(),
+            provider_certificate: None,
+            sntp: None,
+            time_synced: false,
+            forecasts_pending: true,
+            home_pane_shown: false,
+            wifi_error_shown: false,
+            last_fetch: None,
+            last_forecasts: Instant::now(),
+            api_snapshot,
+            mqtt: None,
+            history: HistoryLog::new(HistoryConfig::default()),
+            history_read: None,
+            settings: Settings::default(),
+            saved_settings: None,
+            log_files: LogFiles::new(&Settings::default()),
+            sun_times: Vec::new(),
+            forecasts: Vec::new(),
+            theme_shown: None,
+            ota_config: None,
+            firmware_pending_verify: false,
+            firmware_verify_connected: Duration::ZERO,
+            firmware_update: None,
+            time_sent: 0,
         }
     }
 
     pub fn run(&mut self) {
-        // Read the text files from the SDCard
+        // Finish or undo any settings write cut short by a power loss
+        for file_name in SETTINGS_FILES {
+            if let Err(e) = self.storage.recover(file_name) {
+                warn!("{} recovery error = {}", file_name, e);
+            }
+        }
+
+        // The log level comes first, the records logged so far are written to the log files
+        self.read_settings_txt_file();
+        self.log_files.flush(self.storage.as_mut());
+
+        // Read the text files from storage
         self.read_wifi_txt_file();
         self.read_cities_txt_file();
         self.read_key_txt_file();
+        self.read_provider_txt_file();
+        self.read_ota_txt_file();
+        self.read_api_txt_file();
+        self.read_history_txt_file();
+
+        // A firmware installed over the air has to prove itself healthy or it is rolled back
+        self.firmware_pending_verify = ota_update::is_running_image_pending_verify();
+        if self.firmware_pending_verify {
+            info!("New firmware is pending verify");
+        }
 
         // Update GUI
         self.send_wifi_credentials();
         self.send_cities_titles();
         self.send_cities_settings();
 
-        // Connect to Wifi and establish connection with an SNTP server and send date time to GUI
-        self.connect_to_wifi();
-        let _sntp = self.create_sntp();
-        self.send_date_time();
+        // MQTT is optional, the client connects to the broker once the Wifi is connected
+        self.start_mqtt();
 
-        // Update GUI cities forecast and then show home pane
-        self.send_startup_pane_message("Waiting for cities forecasts".to_string());
-        self.send_cities_forecasts();
-        self.send_show_home_pane();
+        // Start connecting to Wifi, the connection is made in the background by the wifi manager.
+        // The cities forecasts are fetched and the home pane is shown once we are connected.
+        self.send_show_startup_pane();
+        self.send_startup_pane_message(i18n::text().connecting_wifi.to_string());
+        self.send_startup_pane_notice();
+        if let Err(e) = self.wifi_manager.start(&self.wifi_credentials) {
+            warn!("Wifi start error = {}", e);
+        }
 
         // Create Two second timer thread
         //info!("---------- Creating Two Second Timer ----------");
@@ -147,22 +298,6 @@ impl Model {
         // Let it trigger every 2 seconds
         two_second_timer.every(Duration::from_secs(2)).unwrap();
 
-        // Create Ten minute thread
-        //info!("---------- Creating Ten Minute Timer ----------");
-        let ten_minute_timer_expired = Arc::new(AtomicBool::new(false));
-        let ten_minute_timer_expired_clone = ten_minute_timer_expired.clone();
-        let timer_service_03 = EspTaskTimerService::new().unwrap();
-        let ten_minute_timer = timer_service_03
-            .timer(move || {
-                ten_minute_timer_expired_clone.store(true, Ordering::Relaxed);
-            })
-            .unwrap();
-
-        // Let it trigger every 10 minutes
-        ten_minute_timer
-            .every(Duration::from_secs(10 * 60))
-            .unwrap();
-
         loop {
             if let Ok(model_request) = self.rx.try_recv() {
                 match model_request {
@@ -175,140 +310,358 @@ impl Model {
                             self.wifi_credentials.ssid = new_ssid;
                             self.wifi_credentials.pass = new_pass;
 
-                            // Save to SDCard
+                            // Save to storage
                             self.write_wifi_txt_file();
+                            self.send_wifi_credentials();
 
-                            // Restart WIFI with new ssid and pass
-                            self.wifi.disconnect().unwrap();
-                            self.connect_to_wifi();
-
+                            // Restart WIFI with new ssid and pass, the home pane is shown again
+                            // once we are connected and the cities forecasts have been updated
+                            self.send_show_startup_pane();
                             self.send_startup_pane_message(
-                                "Waiting for cities forecasts".to_string(),
+                                i18n::text().connecting_wifi.to_string(),
                             );
-
-                            // Update GUI time, cities forecast and then show home pane
-                            self.send_date_time();
-                            self.send_cities_forecasts();
+                            self.home_pane_shown = false;
+                            self.wifi_error_shown = false;
+                            self.forecasts_pending = true;
+
+                            if let Err(e) =
+                                self.wifi_manager.set_credentials(&self.wifi_credentials)
+                            {
+                                warn!("Wifi configuration error = {}", e);
+                            }
+                        } else {
+                            self.send_show_home_pane();
                         }
-                        self.send_show_home_pane();
                     }
 
                     ModelRequest::UpdateCitiesInfo(cities_info) => {
                         // Save Locally
                         self.cities_info = cities_info;
+                        self.sun_times.clear();
+                        self.forecasts.clear();
 
-                        // Save to SDCard
+                        // Save to storage
                         self.write_cities_txt_file();
 
-                        // Show statup pane
-                        self.send_show_startup_pane();
-                        self.send_startup_pane_message("Waiting for cities forecasts".to_string());
-
                         // Update GUI
                         self.send_cities_titles();
+                        self.send_cities_settings();
                         self.send_date_time();
-                        self.send_cities_forecasts();
-                        self.send_show_home_pane();
+                        self.forecasts_pending = true;
+
+                        // The sensors names in Home Assistant include the city name
+                        if let Some(mqtt) = &mut self.mqtt {
+                            mqtt.publish_discovery(&self.cities_info);
+                        }
+
+                        // Wait for the new forecasts on the startup pane if we can fetch them now,
+                        // otherwise they will be fetched when the Wifi connection is restored
+                        if self.wifi_manager.is_connected() {
+                            self.send_show_startup_pane();
+                            self.send_startup_pane_message(
+                                i18n::text().waiting_forecasts.to_string(),
+                            );
+                            self.home_pane_shown = false;
+                        } else {
+                            self.send_show_home_pane();
+                        }
+                    }
+
+                    ModelRequest::UpdateFirmware => {
+                        self.update_firmware();
                     }
+
+                    ModelRequest::GetHistory(city, period) => {
+                        self.send_city_history(city, period);
+                    }
+
+                    ModelRequest::SetLogLevel(log_level) => {
+                        self.end_preview();
+                        self.settings.log_level = log_level;
+                        self.write_settings_txt_file();
+                        self.apply_settings();
+                        info!("Log level set to {}", log_level);
+                    }
+
+                    ModelRequest::SetTheme(theme_name, auto_dark) => {
+                        self.end_preview();
+                        self.settings.theme = theme_name;
+                        self.settings.auto_dark = auto_dark;
+                        self.write_settings_txt_file();
+                        self.apply_settings();
+                        info!(
+                            "Theme set to {}, auto dark {}",
+                            theme_name.as_str(),
+                            auto_dark
+                        );
+                    }
+
+                    ModelRequest::UserActivity => {
+                        self.backlight.touched();
+                    }
+
+                    ModelRequest::PreviewBrightness(brightness) => {
+                        self.backlight.preview(brightness);
+                    }
+
+                    ModelRequest::SetHomeCity(home_city) => {
+                        self.end_preview();
+                        self.settings.home_city = home_city;
+                        self.write_settings_txt_file();
+                        self.apply_settings();
+                        info!("Home city set to {}", home_city + 1);
+                    }
+
+                    // The panes are laid out once, the display is turned at the next start
+                    ModelRequest::SetOrientation(orientation) => {
+                        self.end_preview();
+                        self.settings.orientation = orientation;
+                        self.write_settings_txt_file();
+                        self.apply_settings();
+                        info!(
+                            "Orientation set to {}, applies after restart",
+                            orientation.as_str()
+                        );
+                    }
+
+                    ModelRequest::PreviewSettings(Some(prefs)) => {
+                        // The saved settings are kept until the previewed ones are saved or
+                        // dropped, they are not written to storage
+                        if self.saved_settings.is_none() {
+                            self.saved_settings = Some(self.settings.clone());
+                        }
+                        self.show_settings(self.settings.with_display_prefs(&prefs));
+                    }
+
+                    ModelRequest::PreviewSettings(None) => {
+                        if let Some(settings) = self.saved_settings.take() {
+                            self.show_settings(settings);
+                        }
+                    }
+
+                    ModelRequest::SaveSettings(prefs) => {
+                        self.end_preview();
+                        self.settings = self.settings.with_display_prefs(&prefs);
+                        self.write_settings_txt_file();
+                        self.apply_settings();
+                        self.send_date_time();
+                        self.resend_cities_forecasts();
+                        info!("Settings saved");
+                    }
+                }
+            }
+
+            // The seconds are shown as they go by, the time is otherwise sent every 2 seconds
+            if self.settings.show_seconds && Utc::now().timestamp() != self.time_sent {
+                self.send_date_time();
+            }
+
+            self.update_backlight();
+            self.check_firmware_update();
+            self.continue_history_read();
+
+            if let Some(wifi_state) = self.wifi_manager.poll() {
+                self.wifi_state_changed(wifi_state);
+            }
+
+            if let Some(mqtt) = &mut self.mqtt {
+                if mqtt.poll(&self.cities_info) {
+                    self.publish_device_health();
                 }
             }
 
             if two_second_timer_expired.load(Ordering::Relaxed) {
                 two_second_timer_expired.store(false, Ordering::Relaxed);
+                self.check_time_sync();
+                self.check_storage_media();
                 self.send_date_time();
+                self.send_network_status();
+                self.check_firmware_health();
+                self.update_theme();
+                self.check_clock();
+                self.log_files.flush(self.storage.as_mut());
             }
 
-            if ten_minute_timer_expired.load(Ordering::Relaxed) {
-                ten_minute_timer_expired.store(false, Ordering::Relaxed);
+            if self.last_forecasts.elapsed() >= self.settings.refresh_interval() {
+                self.forecasts_pending = true;
+            }
+
+            // Forecasts are only fetched while we are connected, a pending fetch waits for the
+            // connection to be restored.  The clock keeps running from the system time meanwhile.
+            // They are not fetched during a firmware update either.
+            if self.forecasts_pending
+                && self.wifi_manager.is_connected()
+                && self.firmware_update.is_none()
+            {
+                self.forecasts_pending = false;
+                self.last_forecasts = Instant::now();
                 self.send_cities_forecasts();
+                self.publish_device_health();
+
+                if !self.home_pane_shown {
+                    self.send_show_home_pane();
+                    self.home_pane_shown = true;
+                }
             }
 
             FreeRtos::delay_ms(200);
         }
     }
 
-    fn connect_to_wifi(&mut self) {
-        self.send_show_startup_pane();
-        self.send_startup_pane_message("Trying to connect to Wifi".to_string());
+    fn wifi_state_changed(&mut self, wifi_state: WifiState) {
+        self.send_wifi_state(wifi_state);
+        self.send_network_status();
 
-        // Sit in while loop forever until we can connect to the wifi network
-        while let Err(_e) = self.connect_wifi() {
-            warn!("---------- Error connecting to Wifi ---------");
+        match wifi_state {
+            WifiState::Connected => {
+                // Clear error message on Wifi Settings pane
+                if self.wifi_error_shown {
+                    self.wifi_error_shown = false;
+                    self.send_wifi_settings_error_message("".to_string());
+                    self.send_show_startup_pane();
+                }
 
-            // Switch display from Startup pane to Wifi Settings pane so user can update wifi credentials
-            self.send_show_wifi_settings_pane();
-            self.send_wifi_settings_error_message(
-                "Wifi connection failed check SSID and Password.".to_string(),
-            );
+                if self.sntp.is_none() {
+                    self.create_sntp();
+                }
 
-            // Wait for the user to update wifi SSID or PASS
-            loop {
-                if let Ok(ModelRequest::UpdateWifiCreds(new_ssid, new_pass)) = self.rx.try_recv() {
-                    self.wifi_credentials.ssid = new_ssid.clone();
-                    self.wifi_credentials.pass = new_pass.clone();
-                    self.write_wifi_txt_file();
-                    self.send_show_startup_pane();
-                    self.send_startup_pane_message("Trying to connect to Wifi".to_string());
+                if !self.home_pane_shown {
+                    self.send_startup_pane_message(i18n::text().waiting_forecasts.to_string());
+                }
+            }
 
-                    break;
-                } else {
-                    FreeRtos::delay_ms(100);
+            WifiState::BackingOff => {
+                // If we have never been able to connect with these credentials switch display from
+                // Startup pane to Wifi Settings pane so user can update wifi credentials.
+                if !self.wifi_manager.has_connected() && !self.wifi_error_shown {
+                    self.wifi_error_shown = true;
+                    self.send_show_wifi_settings_pane();
+                    self.send_wifi_settings_error_message(
+                        i18n::text().wifi_connection_failed.to_string(),
+                    );
                 }
             }
 
-            // Clear error message on Wifi Settings pane
-            self.send_wifi_settings_error_message("".to_string());
+            WifiState::Disconnected | WifiState::Connecting => {}
         }
     }
 
-    // Connect to wifi
-    fn connect_wifi(&mut self) -> anyhow::Result<()> {
-        let wifi_configuration: Configuration = Configuration::Client(ClientConfiguration {
-            ssid: self.wifi_credentials.ssid.as_str().try_into().unwrap(),
-            bssid: None,
-            auth_method: AuthMethod::WPA2Personal,
-            password: self.wifi_credentials.pass.as_str().try_into().unwrap(),
-            channel: None,
-            ..Default::default()
-        });
+    // Start the SNTP service, the time sync completes in the background
+    fn create_sntp(&mut self) {
+        match EspSntp::new_default() {
+            Ok(sntp) => self.sntp = Some(sntp),
+            Err(e) => warn!("SNTP error = {}", e),
+        }
+    }
+
+    fn check_time_sync(&mut self) {
+        if !self.time_synced {
+            if let Some(sntp) = &self.sntp {
+                if sntp.get_sync_status() == SyncStatus::Completed {
+                    info!("Time Sync Completed");
+                    self.time_synced = true;
+                    self.storage.set_clock_synced();
+
+                    if let Err(e) = self.history.append_undated(
+                        self.storage.as_mut(),
+                        &Utc::now(),
+                        Instant::now(),
+                    ) {
+                        warn!("History error = {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    // The SDCard can be inserted or removed at any time
+    fn check_storage_media(&mut self) {
+        match self.storage.check_media() {
+            Some(MediaChange::Inserted) => self.reload_settings(),
 
-        self.wifi.set_configuration(&wifi_configuration)?;
+            // Keep the settings in use, they are saved to the fallback storage from now on
+            Some(MediaChange::Removed(_)) => {
+                self.write_wifi_txt_file();
+                self.write_cities_txt_file();
+                self.write_settings_txt_file();
+            }
 
-        self.wifi.start()?;
+            None => return,
+        }
 
-        // If you are getting broken pipe on terminal after wifi.start then USB power is probably glitching,
-        // you can try and reduce wifi TX power with the following unsafe method.  Your best option is to get
-        // a better power source for the dev board; possibly a powered hub.
-        //unsafe { esp_idf_svc::sys::esp_wifi_set_max_tx_power(34) };
-        info!("Wifi started");
-        self.wifi.connect()?;
-        info!("Wifi connected");
-        self.wifi.wait_netif_up()?;
-        info!("Wifi netif up");
+        // The log files are on the SDCard, the current one is found again once it is back
+        self.log_files.reset();
 
-        Ok(())
+        info!("Settings storage: {}", self.storage.name());
+        self.send_startup_pane_notice();
     }
 
-    fn create_sntp(&self) -> anyhow::Result<Box<EspSntp<'static>>> {
-        self.send_startup_pane_message(
-            "Waiting for Network Time Protocol server connection".to_string(),
-        );
+    // Use the settings on a newly inserted SDCard, settings missing from the card are written to it
+    fn reload_settings(&mut self) {
+        for file_name in SETTINGS_FILES {
+            if let Err(e) = self.storage.recover(file_name) {
+                warn!("{} recovery error = {}", file_name, e);
+            }
+        }
 
-        let sntp = EspSntp::new_default()?;
-        while sntp.get_sync_status() != SyncStatus::Completed {
-            FreeRtos::delay_ms(300);
+        let old_ssid = self.wifi_credentials.ssid.clone();
+        let old_pass = self.wifi_credentials.pass.clone();
+        self.read_wifi_txt_file();
+        if self.wifi_credentials.ssid.is_empty() {
+            self.wifi_credentials.ssid = old_ssid.clone();
+            self.wifi_credentials.pass = old_pass.clone();
+            self.write_wifi_txt_file();
+        }
+
+        if self.storage.read_lines("cities.txt").is_ok() {
+            self.read_cities_txt_file();
+        } else {
+            self.write_cities_txt_file();
+        }
+
+        if self.storage.read_lines("key.txt").is_ok() {
+            self.read_key_txt_file();
+        }
+        self.read_history_txt_file();
+
+        if self.storage.read_lines("settings.txt").is_ok() {
+            self.read_settings_txt_file();
+        } else {
+            self.write_settings_txt_file();
+        }
+
+        self.send_wifi_credentials();
+        self.send_cities_titles();
+        self.send_cities_settings();
+        self.send_date_time();
+        self.forecasts_pending = true;
+
+        if let Some(mqtt) = &mut self.mqtt {
+            mqtt.publish_discovery(&self.cities_info);
         }
-        info!("Time Sync Completed");
 
-        Ok(Box::new(sntp))
+        if self.wifi_credentials.ssid != old_ssid || self.wifi_credentials.pass != old_pass {
+            self.home_pane_shown = false;
+            self.wifi_error_shown = false;
+            self.send_show_startup_pane();
+            self.send_startup_pane_message(i18n::text().connecting_wifi.to_string());
+
+            if let Err(e) = self.wifi_manager.set_credentials(&self.wifi_credentials) {
+                warn!("Wifi configuration error = {}", e);
+            }
+        }
     }
 
     fn read_wifi_txt_file(&mut self) {
-        let lines = self.file_store.read_lines_from_file("wifi.txt").unwrap();
+        // Without Wifi settings the connection fails and the Wifi Settings pane asks for them
+        let lines = self.storage.read_lines("wifi.txt").unwrap_or_else(|e| {
+            warn!("WIFI.TXT error = {}", e);
+            Vec::new()
+        });
 
         self.wifi_credentials = WifiCredentials {
-            ssid: lines[0].clone(),
-            pass: lines[1].clone(),
+            ssid: lines.first().cloned().unwrap_or_default(),
+            pass: lines.get(1).cloned().unwrap_or_default(),
         };
     }
 
@@ -319,16 +672,25 @@ impl Model {
             self.wifi_credentials.pass.clone()
         );
 
-        self.file_store
-            .write_lines_to_file("wifi.txt", lines.as_bytes())
-            .unwrap();
+        if let Err(e) = self.storage.write_atomic("wifi.txt", lines.as_bytes()) {
+            warn!("WIFI.TXT write error = {}", e);
+        }
     }
 
     fn read_cities_txt_file(&mut self) {
-        let lines = self.file_store.read_lines_from_file("cities.txt").unwrap();
+        self.cities_info.clear();
+
+        let lines = self.storage.read_lines("cities.txt").unwrap_or_else(|e| {
+            warn!("CITIES.TXT error = {}", e);
+            Vec::new()
+        });
 
         for line in lines {
             let city: Vec<&str> = line.split(',').collect();
+            if city.len() < 4 {
+                warn!("CITIES.TXT line '{}' is not valid", line);
+                continue;
+            }
 
             self.cities_info.push(CityInfo {
                 city_name: city[0].to_string(),
@@ -337,10 +699,24 @@ impl Model {
                 timezone: city[3].to_string(),
             });
         }
+
+        // The panes always show four cities
+        if self.cities_info.len() != DEFAULT_CITIES.len() {
+            warn!("Using the default cities");
+            self.cities_info = DEFAULT_CITIES
+                .iter()
+                .map(|(city_name, state, zipcode, timezone)| CityInfo {
+                    city_name: city_name.to_string(),
+                    state: state.to_string(),
+                    zipcode: zipcode.to_string(),
+                    timezone: timezone.to_string(),
+                })
+                .collect();
+        }
     }
 
     fn write_cities_txt_file(&mut self) {
-        // Create lines that will be written to SDCard
+        // Create lines that will be written to storage
         let mut lines = String::new();
         for city in 0..self.cities_info.len() {
             let line = format!(
@@ -354,21 +730,372 @@ impl Model {
             lines += line.as_str();
         }
 
-        // Save to SDCard
-        self.file_store
-            .write_lines_to_file("cities.txt", lines.as_bytes())
-            .unwrap();
+        // Save to storage
+        if let Err(e) = self.storage.write_atomic("cities.txt", lines.as_bytes()) {
+            warn!("CITIES.TXT write error = {}", e);
+        }
     }
 
     fn read_key_txt_file(&mut self) {
-        let lines = self.file_store.read_lines_from_file("key.txt").unwrap();
-        self.weather_api_key = lines[0].clone();
+        match self.storage.read_lines("key.txt") {
+            Ok(lines) => self.weather_api_key = lines.first().cloned().unwrap_or_default(),
+            Err(e) => warn!("KEY.TXT error = {}, the forecasts can't be fetched", e),
+        }
+    }
+
+    // Mark a new firmware valid once it has fetched the forecasts, roll it back if it can't.  Only
+    // the time the Wifi is connected counts, a network outage doesn't roll back a good firmware.
+    // Called every 2 seconds.
+    fn check_firmware_health(&mut self) {
+        if !self.firmware_pending_verify {
+            return;
+        }
+
+        if self.last_fetch.is_some() {
+            self.firmware_pending_verify = false;
+            if let Err(e) = ota_update::mark_running_image_valid() {
+                warn!("OTA mark valid error = {}", e);
+            }
+        } else if self.wifi_manager.is_connected() {
+            self.firmware_verify_connected += Duration::from_secs(2);
+            if self.firmware_verify_connected > FIRMWARE_VERIFY_TIMEOUT {
+                if let Err(e) = ota_update::rollback_running_image() {
+                    warn!("OTA rollback error = {}", e);
+                }
+            }
+        }
+    }
+
+    fn update_firmware(&mut self) {
+        if self.firmware_update.is_some() {
+            warn!("A firmware update is already running");
+            return;
+        }
+
+        let config = match &self.ota_config {
+            Some(config) => config.clone(),
+            None => {
+                warn!("No OTA.TXT file, firmware update is not possible");
+                return;
+            }
+        };
+
+        if !self.wifi_manager.is_connected() {
+            warn!("Wifi is not connected, firmware update is not possible");
+            return;
+        }
+
+        self.send_show_startup_pane();
+        self.send_startup_pane_message(i18n::text().updating_firmware.to_string());
+        self.send_startup_pane_progress(Some(0));
+
+        // The download runs on its own thread so the model loop keeps serving the panes, the web
+        // API and MQTT.  The thread restarts the App once the new image is installed.
+        let tx = self.tx.clone();
+        let result = thread::Builder::new().stack_size(16 * 1024).spawn(move || {
+            let result = ota_update::update_firmware(&config, |percent| {
+                tx.send(UiRequest::SetStartupProgress(Some(percent)))
+                    .unwrap();
+            });
+
+            match result {
+                Ok(()) => {
+                    tx.send(UiRequest::SetStartupMessage(
+                        i18n::text().firmware_updated.to_string(),
+                    ))
+                    .unwrap();
+                    FreeRtos::delay_ms(2000);
+                    unsafe { esp_idf_svc::sys::esp_restart() };
+                }
+
+                Err(e) => {
+                    warn!("OTA update error = {}", e);
+                    tx.send(UiRequest::SetStartupProgress(None)).unwrap();
+                    tx.send(UiRequest::SetStartupMessage(
+                        i18n::text().firmware_update_failed.to_string(),
+                    ))
+                    .unwrap();
+                    FreeRtos::delay_ms(3000);
+                }
+            }
+        });
+
+        match result {
+            Ok(firmware_update) => self.firmware_update = Some(firmware_update),
+            Err(e) => {
+                warn!("OTA thread error = {}", e);
+                self.send_startup_pane_progress(None);
+                self.firmware_update_done();
+            }
+        }
+    }
+
+    // The update thread only returns when the update failed
+    fn check_firmware_update(&mut self) {
+        if !self
+            .firmware_update
+            .as_ref()
+            .is_some_and(|firmware_update| firmware_update.is_finished())
+        {
+            return;
+        }
+
+        if let Some(firmware_update) = self.firmware_update.take() {
+            if firmware_update.join().is_err() {
+                warn!("OTA update thread panicked");
+            }
+        }
+        self.firmware_update_done();
+    }
+
+    fn firmware_update_done(&mut self) {
+        if self.home_pane_shown {
+            self.send_show_home_pane();
+        } else {
+            self.send_startup_pane_message(i18n::text().waiting_forecasts.to_string());
+        }
+    }
+
+    fn start_mqtt(&mut self) {
+        // Without a MQTT.TXT file on the SDCard there is nothing to publish to
+        let lines = match self.storage.read_lines("mqtt.txt") {
+            Ok(lines) => lines,
+            Err(_) => {
+                info!("No MQTT.TXT file, MQTT publishing is disabled");
+                return;
+            }
+        };
+
+        let config = match MqttConfig::from_lines(&lines) {
+            Ok(config) => config,
+            Err(e) => {
+                warn!("MQTT.TXT error = {}", e);
+                return;
+            }
+        };
+
+        let ca_certificate = match &config.ca_file {
+            Some(ca_file) => match self.storage.read_to_string(ca_file, MAX_CERTIFICATE_SIZE) {
+                Ok(pem) => Some(pem),
+                Err(e) => {
+                    warn!("MQTT CA certificate {} error = {}", ca_file, e);
+                    return;
+                }
+            },
+            None => None,
+        };
+
+        match MqttPublisher::start(config, ca_certificate) {
+            Ok(mqtt) => self.mqtt = Some(mqtt),
+            Err(e) => warn!("MQTT start error = {}", e),
+        }
+    }
+
+    fn publish_device_health(&mut self) {
+        if let Some(mqtt) = &mut self.mqtt {
+            let health =
+                DeviceHealth::read(self.wifi_manager.rssi(), self.wifi_manager.ip_address());
+            mqtt.publish_health(&health);
+        }
+    }
+
+    fn read_history_txt_file(&mut self) {
+        let config = match self.storage.read_lines("history.txt") {
+            Ok(lines) => HistoryConfig::from_lines(&lines).unwrap_or_else(|e| {
+                warn!("HISTORY.TXT error = {}", e);
+                HistoryConfig::default()
+            }),
+            Err(_) => HistoryConfig::default(),
+        };
+
+        self.history.set_config(config);
+    }
+
+    fn read_settings_txt_file(&mut self) {
+        self.settings = match self.storage.read_lines("settings.txt") {
+            Ok(lines) => Settings::from_lines(&lines).unwrap_or_else(|e| {
+                warn!("SETTINGS.TXT error = {}", e);
+                Settings::default()
+            }),
+            Err(_) => Settings::default(),
+        };
+
+        self.apply_settings();
+    }
+
+    fn write_settings_txt_file(&mut self) {
+        let lines = self.settings.to_lines();
+        if let Err(e) = self.storage.write_atomic("settings.txt", lines.as_bytes()) {
+            warn!("SETTINGS.TXT write error = {}", e);
+        }
+    }
+
+    // Drops the previewed settings before a setting is changed from elsewhere
+    fn end_preview(&mut self) {
+        if let Some(settings) = self.saved_settings.take() {
+            self.settings = settings;
+        }
+    }
+
+    // Shows settings on the panes without saving them
+    fn show_settings(&mut self, settings: Settings) {
+        self.settings = settings;
+        self.update_theme();
+        self.tx
+            .send(UiRequest::SetSettings(self.settings.clone()))
+            .unwrap();
+        self.send_date_time();
+        self.resend_cities_forecasts();
+    }
+
+    fn apply_settings(&mut self) {
+        log_file::set_file_level(self.settings.log_level);
+        self.log_files = LogFiles::new(&self.settings);
+        self.api_snapshot.lock().unwrap().settings = self.settings.clone();
+        self.update_theme();
+        self.tx
+            .send(UiRequest::SetSettings(self.settings.clone()))
+            .unwrap();
+    }
+
+    // Dims the backlight after a while without a touch and at night, the UI is told when the
+    // screen falls asleep so the touch that wakes it is not taken as a click
+    fn update_backlight(&mut self) {
+        let night_part = self.night_part();
+        self.backlight.update(&self.settings, night_part);
+
+        let screen_asleep = self.backlight.is_asleep(&self.settings, night_part);
+        if self.screen_asleep != screen_asleep {
+            self.screen_asleep = screen_asleep;
+            self.tx
+                .send(UiRequest::SetScreenAsleep(screen_asleep))
+                .unwrap();
+        }
+    }
+
+    // Shows the clock once after `clock_after_min` minutes without a touch, it is left with a tap
+    fn check_clock(&mut self) {
+        if self.settings.clock_after_min == 0 || !self.home_pane_shown {
+            return;
+        }
+
+        let clock_after = Duration::from_secs(self.settings.clock_after_min as u64 * 60);
+        if self.backlight.idle() < clock_after {
+            self.clock_shown = false;
+        } else if !self.clock_shown {
+            self.clock_shown = true;
+            self.tx.send(UiRequest::ShowClock).unwrap();
+        }
+    }
+
+    // How far into the night it is in the home city, from 0 by day to 1 by night.  The night goes
+    // from the sunset to the sunrise of the forecast, until there is one the fixed night window of
+    // the settings is used.  It is always day until the time is synced.
+    fn night_part(&self) -> f32 {
+        if !self.time_synced {
+            return 0.0;
+        }
+
+        let home_city = self.settings.home_city;
+        let sun_times = match self.sun_times.get(home_city).copied().flatten() {
+            Some(sun_times) if self.settings.night_from_sun => sun_times,
+            _ => {
+                let Some(timezone) = self
+                    .cities_info
+                    .get(home_city)
+                    .and_then(|city| city.timezone.parse::<Tz>().ok())
+                else {
+                    return 0.0;
+                };
+                SunTimes::fixed(self.settings.night_start, self.settings.night_end, timezone)
+            }
+        };
+
+        sun_times.night_part(&Utc::now())
+    }
+
+    // Blends the theme of the UI into the dark theme around sunset and back around sunrise in
+    // the home city, and switches it when the theme setting changes
+    fn update_theme(&mut self) {
+        let theme_shown = theme::effective_theme(
+            self.settings.theme,
+            self.settings.auto_dark,
+            self.night_part(),
+        );
+
+        if self.theme_shown != Some(theme_shown) {
+            self.theme_shown = Some(theme_shown);
+            let (theme_name, dusk) = theme_shown;
+            self.tx.send(UiRequest::SetTheme(theme_name, dusk)).unwrap();
+        }
+    }
+
+    fn read_provider_txt_file(&mut self) {
+        // Without a PROVIDER.TXT file weatherapi.com is used with the certificate bundle
+        if let Ok(lines) = self.storage.read_lines("provider.txt") {
+            match ProviderConfig::from_lines(&lines) {
+                Ok(provider) => self.provider = provider,
+                Err(e) => warn!("PROVIDER.TXT error = {}", e),
+            }
+        }
+
+        if !self.provider.uses_tls() {
+            warn!("Weather provider is not using https, the API key is sent in plaintext");
+        }
+
+        if let Some(cert_file) = self.provider.cert_file.clone() {
+            match self
+                .storage
+                .read_to_string(&cert_file, MAX_CERTIFICATE_SIZE)
+            {
+                Ok(pem) => {
+                    // The certificate is used by every fetch, so it is kept for good
+                    let mut pem = pem.into_bytes();
+                    pem.push(0);
+                    self.provider_certificate = Some(Box::leak(pem.into_boxed_slice()));
+                    info!("Weather provider certificate {} loaded", cert_file);
+                }
+                Err(e) => warn!("Weather provider certificate {} error = {}", cert_file, e),
+            }
+        }
+    }
+
+    fn read_ota_txt_file(&mut self) {
+        // Firmware updates are only possible with a OTA.TXT file on the SDCard
+        if let Ok(lines) = self.storage.read_lines("ota.txt") {
+            match OtaConfig::from_lines(&lines) {
+                Ok(config) => self.ota_config = Some(config),
+                Err(e) => warn!("OTA.TXT error = {}", e),
+            }
+        }
+    }
+
+    fn read_api_txt_file(&mut self) {
+        // The PUT requests of the web API are only served with a API.TXT file on the SDCard
+        if let Ok(lines) = self.storage.read_lines("api.txt") {
+            match ApiConfig::from_lines(&lines) {
+                Ok(config) => self.api_snapshot.lock().unwrap().config = Some(config),
+                Err(e) => warn!("API.TXT error = {}", e),
+            }
+        }
     }
 
     fn send_startup_pane_message(&self, message: String) {
         self.tx.send(UiRequest::SetStartupMessage(message)).unwrap();
     }
 
+    fn send_startup_pane_progress(&self, percent: Option<u8>) {
+        self.tx
+            .send(UiRequest::SetStartupProgress(percent))
+            .unwrap();
+    }
+
+    // Tells why the SDCard is not used, an empty notice clears it
+    fn send_startup_pane_notice(&self) {
+        let notice = self.storage.notice().unwrap_or_default();
+        self.tx.send(UiRequest::SetStartupNotice(notice)).unwrap();
+    }
+
     fn send_show_startup_pane(&self) {
         self.tx.send(UiRequest::ShowStartup).unwrap();
     }
@@ -387,6 +1114,34 @@ impl Model {
             .unwrap();
     }
 
+    fn send_wifi_state(&self, wifi_state: WifiState) {
+        self.tx.send(UiRequest::SetWifiState(wifi_state)).unwrap();
+    }
+
+    fn send_network_status(&self) {
+        let network_status = NetworkStatus {
+            wifi_state: self.wifi_manager.state(),
+            ssid: self.wifi_credentials.ssid.clone(),
+            rssi: self.wifi_manager.rssi(),
+            ip_address: self.wifi_manager.ip_address(),
+            time_synced: self.time_synced,
+            last_fetch_secs: self.last_fetch.map(|fetched| fetched.elapsed().as_secs()),
+        };
+
+        self.api_snapshot.lock().unwrap().status = Some(ApiStatus {
+            wifi_state: format!("{:?}", network_status.wifi_state),
+            ssid: network_status.ssid.clone(),
+            rssi: network_status.rssi,
+            ip_address: network_status.ip_address.clone(),
+            time_synced: network_status.time_synced,
+            last_fetch_secs: network_status.last_fetch_secs,
+        });
+
+        self.tx
+            .send(UiRequest::SetNetworkStatus(network_status))
+            .unwrap();
+    }
+
     fn send_wifi_credentials(&self) {
         self.tx
             .send(UiRequest::SetWifiCreds(
@@ -397,15 +1152,27 @@ impl Model {
     }
 
     fn send_date_time(&mut self) {
+        // Until the first time sync the system clock does not hold the current time
+        if !self.time_synced {
+            return;
+        }
+
         let st_now = SystemTime::now();
         let dt: DateTime<Utc> = st_now.into();
+        self.time_sent = dt.timestamp();
 
+        let date_format = self.settings.date_format();
+        let (time_format, date_format) = (date_format.time(), date_format.date());
         for city in 0..self.cities_info.len() {
             let timezone: String = self.cities_info[city].timezone.clone();
             let city_timezone: Tz = timezone.parse().unwrap();
             let city_datetime = dt.with_timezone(&city_timezone);
-            let city_time = format!("{}", city_datetime.format("%I:%M%P"));
-            let city_date = format!("{}", city_datetime.format("%a %b %d %Y"));
+            let city_time = city_datetime
+                .format(&i18n::localize(&time_format, &city_datetime))
+                .to_string();
+            let city_date = city_datetime
+                .format(&i18n::localize(&date_format, &city_datetime))
+                .to_string();
 
             self.tx
                 .send(UiRequest::SetCityTime(
@@ -424,90 +1191,280 @@ impl Model {
     }
 
     fn send_cities_settings(&mut self) {
+        // The API serves the same cities settings as the Cities Settings pane
+        let mut api_snapshot = self.api_snapshot.lock().unwrap();
+        api_snapshot.cities_info = self.cities_info.clone();
+        api_snapshot.forecasts = vec![None; self.cities_info.len()];
+        drop(api_snapshot);
+
         self.tx
             .send(UiRequest::SetCitiesSettings(self.cities_info.clone()))
             .unwrap();
     }
 
     fn send_cities_forecasts(&mut self) {
+        let fetched = Utc::now();
+        let mut history_rows = Vec::new();
+        self.forecasts.resize_with(self.cities_info.len(), || None);
+
         for city in 0..self.cities_info.len() {
             if let Some(cf) = self.fetch_city_forecast(city) {
-                let fw: ForecastWeather = serde_json::from_str(&cf).unwrap();
-                let temp = format!("{:.0}F", fw.current.temp_f);
-                let weather_descr = fw.current.condition.text;
-                let feels_like = format!("{:.0}F", fw.current.feelslike_f);
-                let uv = format!("{:.0}", fw.current.uv);
-                let aqi = format!("{}", fw.current.air_quality.us_epa_index);
-                let wind_speed = format!("{:.0}", fw.current.wind_mph);
-                let wind_gust = format!("{:.0}", fw.current.gust_mph);
-                let wind_dir = fw.current.wind_dir;
-
-                let mut date_time = Utc
-                    .timestamp_opt(fw.forecast.forecastday[0].date_epoch, 0)
-                    .unwrap();
-                let weekday_forecast_day_1 = format!("{}", date_time.format("%a %d"));
-                let mut day_hi_temp = fw.forecast.forecastday[0].day.maxtemp_f;
-                let mut day_lo_temp = fw.forecast.forecastday[0].day.mintemp_f;
-                let forecast_day_1 = format!("{:.0}F\n{:.0}F", day_hi_temp, day_lo_temp);
+                self.last_fetch = Some(Instant::now());
+
+                let mut fw: ForecastWeather = serde_json::from_str(&cf).unwrap();
+
+                // The night of the backlight and the auto dark theme follows the sun of the
+                // home city
+                let astro = &fw.forecast.forecastday[0].astro;
+                let sun_times =
+                    self.cities_info[city]
+                        .timezone
+                        .parse::<Tz>()
+                        .ok()
+                        .and_then(|timezone| {
+                            SunTimes::from_astro(&astro.sunrise, &astro.sunset, timezone)
+                        });
+                self.sun_times.resize(self.cities_info.len(), None);
+                self.sun_times[city] = sun_times;
+
+                let conditions = CityConditions {
+                    temp_f: fw.current.temp_f,
+                    feels_like_f: fw.current.feelslike_f,
+                    humidity: fw.current.humidity,
+                    pressure_mb: fw.current.pressure_mb,
+                    wind_mph: fw.current.wind_mph,
+                    wind_gust_mph: fw.current.gust_mph,
+                    wind_dir: fw.current.wind_dir.clone(),
+                    uv: fw.current.uv,
+                    aqi: fw.current.air_quality.us_epa_index,
+                    condition: fw.current.condition.text.clone(),
+                    last_updated_epoch: fw.current.last_updated_epoch,
+                };
+
+                if let Some(mqtt) = &mut self.mqtt {
+                    mqtt.publish_city_conditions(city, &conditions);
+                }
 
-                date_time = Utc
-                    .timestamp_opt(fw.forecast.forecastday[1].date_epoch, 0)
-                    .unwrap();
-                let weekday_forecast_day_2 = format!("{}", date_time.format("%a %d"));
-                day_hi_temp = fw.forecast.forecastday[1].day.maxtemp_f;
-                day_lo_temp = fw.forecast.forecastday[1].day.mintemp_f;
-                let forecast_day_2 = format!("{:.0}F\n{:.0}F", day_hi_temp, day_lo_temp);
+                history_rows.push((self.cities_info[city].city_name.clone(), conditions.clone()));
+
+                let api_forecast = ApiForecast {
+                    current: conditions,
+                    days: fw
+                        .forecast
+                        .forecastday
+                        .iter()
+                        .map(|forecast_day| ApiForecastDay {
+                            date_epoch: forecast_day.date_epoch,
+                            max_temp_f: forecast_day.day.maxtemp_f,
+                            min_temp_f: forecast_day.day.mintemp_f,
+                            condition: forecast_day.day.condition.text.clone(),
+                        })
+                        .collect(),
+                };
+                if let Some(forecast) = self.api_snapshot.lock().unwrap().forecasts.get_mut(city) {
+                    *forecast = Some(api_forecast);
+                }
 
-                date_time = Utc
-                    .timestamp_opt(fw.forecast.forecastday[2].date_epoch, 0)
-                    .unwrap();
-                let weekday_forecast_day_3 = format!("{}", date_time.format("%a %d"));
-                day_hi_temp = fw.forecast.forecastday[2].day.maxtemp_f;
-                day_lo_temp = fw.forecast.forecastday[2].day.mintemp_f;
-                let forecast_day_3 = format!("{:.0}F\n{:.0}F", day_hi_temp, day_lo_temp);
+                // The hours are not shown, they are not kept with the forecast
+                for forecast_day in fw.forecast.forecastday.iter_mut() {
+                    forecast_day.hour = Vec::new();
+                }
+                self.forecasts[city] = Some(fw);
+                self.send_city_forecast(city);
+            }
+        }
 
-                let dt_last = Utc.timestamp_opt(fw.current.last_updated_epoch, 0).unwrap();
-                let city_tz: Tz = self.cities_info[city].timezone.clone().parse().unwrap();
-                let dt_last_with_tz = dt_last.with_timezone(&city_tz);
-                let dt_last_str = dt_last_with_tz.format("%D %I:%M%P");
-                let last_update = format!("Last update: {}", dt_last_str);
+        // Until the first time sync the date of the rows is not known, they are kept until then
+        if !history_rows.is_empty() {
+            if !self.time_synced {
+                self.history.keep_undated(Instant::now(), history_rows);
+            } else if let Err(e) =
+                self.history
+                    .append(self.storage.as_mut(), &fetched, &history_rows)
+            {
+                warn!("History error = {}", e);
+            }
+        }
+    }
+
+    // The forecasts last fetched are sent again when the units or the date format change
+    fn resend_cities_forecasts(&self) {
+        for city in 0..self.forecasts.len() {
+            self.send_city_forecast(city);
+        }
+    }
+
+    // Formats the forecast last fetched for the city with the settings and sends it to the panes
+    fn send_city_forecast(&self, city: usize) {
+        let Some(Some(fw)) = self.forecasts.get(city) else {
+            return;
+        };
+        let date_format = self.settings.date_format();
+        let temp_unit = self.settings.temp_unit;
+        let wind_unit = self.settings.wind_unit;
+
+        let temp = temp_unit.format(fw.current.temp_f);
+        let weather_descr = i18n::fold_accents(&fw.current.condition.text);
+        let weather_icon =
+            weather_icons::icon_for_code(fw.current.condition.code, fw.current.is_day == 1);
+        let feels_like = temp_unit.format(fw.current.feelslike_f);
+        let uv = i18n::number(fw.current.uv, 1);
+        let aqi = format!("{}", fw.current.air_quality.us_epa_index);
+        let wind_speed = wind_unit.format(fw.current.wind_mph);
+        let wind_gust = wind_unit.format(fw.current.gust_mph);
+        let wind_dir = fw.current.wind_dir.clone();
+
+        let mut date_time = Utc
+            .timestamp_opt(fw.forecast.forecastday[0].date_epoch, 0)
+            .unwrap();
+        let weekday_forecast_day_1 = date_time
+            .format(&i18n::localize(&date_format.day(), &date_time))
+            .to_string();
+        let mut day_hi_temp = fw.forecast.forecastday[0].day.maxtemp_f;
+        let mut day_lo_temp = fw.forecast.forecastday[0].day.mintemp_f;
+        let forecast_day_1 = format!(
+            "{}\n{}",
+            temp_unit.format(day_hi_temp),
+            temp_unit.format(day_lo_temp)
+        );
+        let icon_forecast_day_1 =
+            weather_icons::icon_for_code(fw.forecast.forecastday[0].day.condition.code, true);
+
+        date_time = Utc
+            .timestamp_opt(fw.forecast.forecastday[1].date_epoch, 0)
+            .unwrap();
+        let weekday_forecast_day_2 = date_time
+            .format(&i18n::localize(&date_format.day(), &date_time))
+            .to_string();
+        day_hi_temp = fw.forecast.forecastday[1].day.maxtemp_f;
+        day_lo_temp = fw.forecast.forecastday[1].day.mintemp_f;
+        let forecast_day_2 = format!(
+            "{}\n{}",
+            temp_unit.format(day_hi_temp),
+            temp_unit.format(day_lo_temp)
+        );
+        let icon_forecast_day_2 =
+            weather_icons::icon_for_code(fw.forecast.forecastday[1].day.condition.code, true);
+
+        date_time = Utc
+            .timestamp_opt(fw.forecast.forecastday[2].date_epoch, 0)
+            .unwrap();
+        let weekday_forecast_day_3 = date_time
+            .format(&i18n::localize(&date_format.day(), &date_time))
+            .to_string();
+        day_hi_temp = fw.forecast.forecastday[2].day.maxtemp_f;
+        day_lo_temp = fw.forecast.forecastday[2].day.mintemp_f;
+        let forecast_day_3 = format!(
+            "{}\n{}",
+            temp_unit.format(day_hi_temp),
+            temp_unit.format(day_lo_temp)
+        );
+        let icon_forecast_day_3 =
+            weather_icons::icon_for_code(fw.forecast.forecastday[2].day.condition.code, true);
+
+        let dt_last = Utc.timestamp_opt(fw.current.last_updated_epoch, 0).unwrap();
+        let city_tz: Tz = self.cities_info[city].timezone.clone().parse().unwrap();
+        let dt_last_with_tz = dt_last.with_timezone(&city_tz);
+        let dt_last_str = dt_last_with_tz
+            .format(&i18n::localize(&date_format.stamp(), &dt_last_with_tz))
+            .to_string();
+        let last_update = i18n::fill(i18n::text().last_update, &[&dt_last_str]);
+
+        let city_forecast = CityForecast {
+            temp,
+            weather_descr,
+            weather_icon,
+            feels_like,
+            uv,
+            aqi,
+            wind_speed,
+            wind_gust,
+            wind_dir,
+            weekday_forecast_day_1,
+            forecast_day_1,
+            icon_forecast_day_1,
+            weekday_forecast_day_2,
+            forecast_day_2,
+            icon_forecast_day_2,
+            weekday_forecast_day_3,
+            forecast_day_3,
+            icon_forecast_day_3,
+            last_update,
+        };
 
+        self.tx
+            .send(UiRequest::SetCityForecast(city, city_forecast))
+            .unwrap();
+    }
+
+    // Starts reading the history files of the period, the charts data is sent once they are read
+    fn send_city_history(&mut self, city: usize, period: HistoryPeriod) {
+        let Some(city_info) = self.cities_info.get(city) else {
+            return;
+        };
+        let city_name = city_info.city_name.clone();
+
+        // The history rows are dated with the synced clock
+        if !self.time_synced {
+            self.send_history_message(i18n::text().waiting_time_sync.to_string());
+            return;
+        }
+
+        let existing_files = match self.storage.list_dir(history::HISTORY_DIR) {
+            Ok(file_names) => file_names,
+            Err(e) => {
+                warn!("History error = {}", e);
+                self.send_history_message(i18n::fill(i18n::text().history_error, &[&e]));
+                return;
+            }
+        };
+
+        // A new request replaces the one being read
+        self.history_read = Some(HistoryRead::new(
+            &city_name,
+            period,
+            &Utc::now(),
+            &existing_files,
+        ));
+    }
+
+    // Reads one more history file each time around the model loop
+    fn continue_history_read(&mut self) {
+        let Some(history_read) = &mut self.history_read else {
+            return;
+        };
+
+        if history_read.read_next(self.storage.as_mut()) {
+            if let Some(history_read) = self.history_read.take() {
                 self.tx
-                    .send(UiRequest::SetCityForecast(
-                        city,
-                        CityForecast {
-                            temp,
-                            weather_descr,
-                            feels_like,
-                            uv,
-                            aqi,
-                            wind_speed,
-                            wind_gust,
-                            wind_dir,
-                            weekday_forecast_day_1,
-                            forecast_day_1,
-                            weekday_forecast_day_2,
-                            forecast_day_2,
-                            weekday_forecast_day_3,
-                            forecast_day_3,
-                            last_update,
-                        },
-                    ))
+                    .send(UiRequest::SetHistory(history_read.into_history()))
                     .unwrap();
             }
         }
     }
 
+    fn send_history_message(&self, message: String) {
+        self.tx.send(UiRequest::SetHistoryMessage(message)).unwrap();
+    }
+
     fn fetch_city_forecast(&mut self, city_id: usize) -> Option<String> {
-        let url = format!(
-            "http://api.weatherapi.com/v1/forecast.json?key={}&q={}&days=3&aqi=yes&alerts=no",
-            self.weather_api_key,
-            self.cities_info[city_id].zipcode.clone()
+        let url = self.provider.forecast_url(
+            &self.weather_api_key,
+            &self.cities_info[city_id].zipcode,
+            i18n::catalog().api_lang,
         );
 
+        // A certificate from PROVIDER.TXT replaces the certificate bundle
+        let server_certificate = self.provider_certificate.map(X509::pem_until_nul);
+        let crt_bundle_attach = if self.provider.uses_tls() && server_certificate.is_none() {
+            Some(esp_idf_svc::sys::esp_crt_bundle_attach as _)
+        } else {
+            None
+        };
+
         let httpconnection = EspHttpConnection::new(&HttpConfig {
-            use_global_ca_store: true,
-            crt_bundle_attach: Some(esp_idf_svc::sys::esp_crt_bundle_attach),
+            server_certificate,
+            crt_bundle_attach,
             timeout: Some(Duration::from_secs(10)),
             ..Default::default()
         })
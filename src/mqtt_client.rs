@@ -0,0 +1,182 @@
+//! The MQTT client publishing to the broker of MQTT.TXT, see mqtt.rs for the settings, the
+//! topics and the discovery messages.
+//!
+//! The ESP-IDF MQTT client reconnects by itself, `MqttPublisher::poll` reports every new
+//! connection so the availability and the discovery messages can be published again; they are
+//! retained so Home Assistant picks them up whenever it restarts.
+use crate::cities::{CityConditions, CityInfo};
+use crate::mqtt::{self, DeviceHealth, MqttConfig, PAYLOAD_OFFLINE, PAYLOAD_ONLINE};
+
+use anyhow::Result;
+use esp_idf_svc::mqtt::client::{
+    EspMqttClient, EventPayload, LwtConfiguration, MqttClientConfiguration, QoS,
+};
+use esp_idf_svc::tls::X509;
+
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+use std::sync::Arc;
+
+use log::{info, warn};
+
+impl DeviceHealth {
+    /// Reads the uptime and the heap of the device
+    pub fn read(rssi: Option<i8>, ip_address: Option<String>) -> Self {
+        unsafe {
+            Self {
+                uptime_secs: (esp_idf_svc::sys::esp_timer_get_time() / 1_000_000) as u64,
+                free_heap: esp_idf_svc::sys::esp_get_free_heap_size(),
+                min_free_heap: esp_idf_svc::sys::esp_get_minimum_free_heap_size(),
+                rssi,
+                ip_address,
+            }
+        }
+    }
+}
+
+pub struct MqttPublisher {
+    client: EspMqttClient<'static>,
+    config: MqttConfig,
+    connected: Arc<AtomicBool>,
+    new_connection: Arc<AtomicBool>,
+    city_states: Vec<Option<String>>,
+}
+
+impl MqttPublisher {
+    /// Starts the MQTT client, `ca_certificate` is the PEM CA certificate read from the `ca=` file
+    pub fn start(config: MqttConfig, ca_certificate: Option<String>) -> Result<Self> {
+        let connected = Arc::new(AtomicBool::new(false));
+        let new_connection = Arc::new(AtomicBool::new(false));
+
+        // The MQTT client keeps a pointer to the certificate, so it has to live forever
+        let server_certificate = ca_certificate.map(|pem| {
+            let mut pem = pem.into_bytes();
+            pem.push(0);
+            X509::pem_until_nul(Box::leak(pem.into_boxed_slice()))
+        });
+
+        let crt_bundle_attach = if config.uses_tls() && server_certificate.is_none() {
+            Some(esp_idf_svc::sys::esp_crt_bundle_attach as _)
+        } else {
+            None
+        };
+
+        let availability_topic = config.availability_topic();
+
+        let mqtt_config = MqttClientConfiguration {
+            client_id: Some(config.base_topic.as_str()),
+            username: config.username.as_deref(),
+            password: config.password.as_deref(),
+            keep_alive_interval: Some(Duration::from_secs(60)),
+            reconnect_timeout: Some(Duration::from_secs(10)),
+            lwt: Some(LwtConfiguration {
+                topic: availability_topic.as_str(),
+                payload: PAYLOAD_OFFLINE.as_bytes(),
+                qos: QoS::AtLeastOnce,
+                retain: true,
+            }),
+            server_certificate,
+            crt_bundle_attach,
+            skip_cert_common_name_check: config.skip_cn_check,
+            ..Default::default()
+        };
+
+        let events_connected = connected.clone();
+        let events_new_connection = new_connection.clone();
+        let client = EspMqttClient::new_cb(&config.url, &mqtt_config, move |event| {
+            match event.payload() {
+                EventPayload::Connected(_) => {
+                    events_connected.store(true, Ordering::Relaxed);
+                    events_new_connection.store(true, Ordering::Relaxed);
+                }
+                EventPayload::Disconnected => {
+                    events_connected.store(false, Ordering::Relaxed);
+                }
+                _ => {}
+            }
+        })?;
+
+        info!("MQTT client started, broker {}", config.url);
+
+        Ok(Self {
+            client,
+            config,
+            connected,
+            new_connection,
+            city_states: Vec::new(),
+        })
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+
+    /// Publishes the availability, the discovery messages and the latest cities states when a
+    /// new broker connection was made, returns true if so.
+    pub fn poll(&mut self, cities_info: &[CityInfo]) -> bool {
+        if !self.new_connection.swap(false, Ordering::Relaxed) {
+            return false;
+        }
+
+        info!("MQTT connected");
+        let topic = self.config.availability_topic();
+        self.publish(&topic, PAYLOAD_ONLINE, true);
+        self.publish_discovery(cities_info);
+
+        for city in 0..self.city_states.len() {
+            if let Some(payload) = self.city_states[city].clone() {
+                let topic = self.config.city_state_topic(city);
+                self.publish(&topic, &payload, true);
+            }
+        }
+
+        true
+    }
+
+    /// Publishes the discovery messages, needed again whenever the cities names change
+    pub fn publish_discovery(&mut self, cities_info: &[CityInfo]) {
+        for (topic, payload) in mqtt::discovery_messages(&self.config, cities_info) {
+            self.publish(&topic, &payload, true);
+        }
+    }
+
+    pub fn publish_city_conditions(&mut self, city: usize, conditions: &CityConditions) {
+        let payload = match serde_json::to_string(conditions) {
+            Ok(payload) => payload,
+            Err(e) => {
+                warn!("MQTT json error = {}", e);
+                return;
+            }
+        };
+
+        let topic = self.config.city_state_topic(city);
+        self.publish(&topic, &payload, true);
+
+        if self.city_states.len() <= city {
+            self.city_states.resize(city + 1, None);
+        }
+        self.city_states[city] = Some(payload);
+    }
+
+    pub fn publish_health(&mut self, health: &DeviceHealth) {
+        let topic = self.config.health_topic();
+        match serde_json::to_string(health) {
+            Ok(payload) => self.publish(&topic, &payload, false),
+            Err(e) => warn!("MQTT json error = {}", e),
+        }
+    }
+
+    // Messages are only queued while connected, the retained ones are published again on reconnect
+    fn publish(&mut self, topic: &str, payload: &str, retain: bool) {
+        if !self.is_connected() {
+            return;
+        }
+
+        if let Err(e) = self
+            .client
+            .enqueue(topic, QoS::AtLeastOnce, retain, payload.as_bytes())
+        {
+            warn!("MQTT publish error = {}", e);
+        }
+    }
+}
@@ -1,70 +1,139 @@
 //============================================================================
 //                              Navigation Pane
 //============================================================================
+use crate::i18n;
+use crate::layout;
 use crate::lvgl_misc::*;
+use crate::model::NetworkStatus;
+use crate::theme::{self, FontSize, Palette};
+use crate::ui::UiRequest;
+use crate::ui_event::{UiEvent, UiEventQueue};
+use crate::wifi_manager::WifiState;
 use cstr_core::CString;
-use lvgl::font::Font;
 use lvgl::style::{Opacity, Style};
 use lvgl::widgets::{Btnmatrix, Label};
-use lvgl::{Align, Color, Obj, Part, Screen, TextAlign, Widget};
-use lvgl_sys::*;
+use lvgl::{Align, Obj, Part, Screen, TextAlign, Widget};
 
 //use log::info;
 
+const SIGNAL_BARS: usize = 4;
+
 pub struct NavigationPane<'a> {
     pane: Obj<'a>,
-    pub btn_matrix: Btnmatrix<'a>,
+    btn_matrix: Btnmatrix<'a>,
     prev_btn_selected: u16,
+    signal_bars: Vec<Obj<'a>>,
+    status_label: Label<'a>,
+    network_status: Option<NetworkStatus>,
+    show_ip_address: bool,
 }
 
 impl<'a> NavigationPane<'a> {
-    pub fn new(navigation_pane_parent: &'a mut Screen) -> Self {
+    /// Creates the nav bar with a button per title
+    pub fn new(
+        navigation_pane_parent: &'a mut Screen,
+        titles: &[&str],
+        events: &UiEventQueue,
+    ) -> Self {
         let mut pane = Obj::create(navigation_pane_parent).unwrap();
-        pane.set_size(800, 49);
+        pane.set_size(layout::screen_width(), layout::nav_bar_height() - 2);
         pane.set_align(Align::TopLeft, 0, 0);
-        pane.add_style(Part::Main, Box::leak(navigation_pane_style()));
+
+        // In portrait the app title and the network status are below the buttons
+        let info_x = layout::pick(420, 10);
+        let info_y = layout::pick(0, 49);
+        pane.add_style(Part::Main, theme::style(navigation_pane_style));
 
         // Create the btn matrix vector that holds the button's name,  last entry of "" signifys no more buttons
-        let btnmatrix_vec = vec![
-            CString::new("Home").unwrap(),
-            CString::new("Wifi Settings").unwrap(),
-            CString::new("Cities Settings").unwrap(),
-            CString::new("").unwrap(),
-        ];
+        let mut btnmatrix_vec: Vec<CString> = titles
+            .iter()
+            .map(|title| CString::new(*title).unwrap())
+            .collect();
+        btnmatrix_vec.push(CString::new("").unwrap());
 
         // Create button matrix
         let mut btn_matrix = Btnmatrix::create(&mut pane).unwrap();
-        btn_matrix.set_size(400, 40);
+        btn_matrix.set_size(layout::pick(400, 460), 40);
         btn_matrix.set_align(Align::TopLeft, 10, 6);
-        btn_matrix.add_style(Part::Main, Box::leak(btn_matrix_style()));
+        btn_matrix.add_style(Part::Main, theme::style(btn_matrix_style));
         btnmatrix_set_map(&mut btn_matrix, btnmatrix_vec);
         btnmatrix_set_btn_ctrl_all(&mut btn_matrix);
-        btnmatrix_clear_btn_ctrl(&mut btn_matrix, 1);
-        btnmatrix_clear_btn_ctrl(&mut btn_matrix, 2);
+        for btn_id in 1..titles.len() {
+            btnmatrix_clear_btn_ctrl(&mut btn_matrix, btn_id as u16);
+        }
         btnmatrix_set_one_checked(&mut btn_matrix);
         btnmatrix_set_btn_ctrl(&mut btn_matrix, 0);
 
         // App title
         let mut app_title = Label::create(&mut pane).unwrap();
-        app_title.add_style(Part::Main, Box::leak(app_title_style()));
+        app_title.add_style(Part::Main, theme::style(app_title_style));
         app_title.set_width(320);
-        app_title.set_align(Align::TopLeft, 420, 4);
+        app_title.set_align(Align::TopLeft, info_x + 50, info_y + 2);
         app_title
-            .set_text(CString::new("Weather Forecaster").unwrap().as_c_str())
+            .set_text(CString::new(i18n::text().app_title).unwrap().as_c_str())
             .unwrap();
 
         let mut app_source = Label::create(&mut pane).unwrap();
-        app_source.add_style(Part::Main, Box::leak(app_source_style()));
-        app_source.set_width(320);
-        app_source.set_align(Align::TopLeft, 420, 32);
+        app_source.add_style(Part::Main, theme::style(app_source_style));
+        app_source.set_align(Align::TopLeft, info_x, info_y + 8);
         app_source
-            .set_text(CString::new("uses weatherapi.com").unwrap().as_c_str())
+            .set_text(CString::new("weatherapi.com").unwrap().as_c_str())
+            .unwrap();
+
+        // Wifi signal strength bars, the bars get taller from left to right and share the same bottom
+        let mut signal_bars: Vec<Obj> = Vec::new();
+        for i in 0..SIGNAL_BARS as i32 {
+            let mut bar = Obj::create(&mut pane).unwrap();
+            bar.set_size(5, 4 + (i * 3));
+            bar.set_align(
+                Align::TopLeft,
+                info_x + (i * 7),
+                info_y + 45 - (4 + (i * 3)),
+            );
+            bar.add_style(Part::Main, theme::style(signal_bar_style));
+            signal_bars.push(bar);
+        }
+
+        // Network status, clicking on the status shows the IP address
+        let mut status_label = Label::create(&mut pane).unwrap();
+        status_label.add_style(Part::Main, theme::style(status_label_style));
+        status_label.set_width(340);
+        status_label.set_align(Align::TopLeft, info_x + 30, info_y + 32);
+        status_label
+            .set_text(
+                CString::new(i18n::text().wifi_disconnected)
+                    .unwrap()
+                    .as_c_str(),
+            )
+            .unwrap();
+        set_label_clickable(&mut status_label);
+
+        let queue = events.clone();
+        btn_matrix
+            .on_event(move |btnm, event| {
+                if let lvgl::Event::Pressed = event {
+                    queue.push(UiEvent::NavButtonPressed(btnmatrix_get_selected_btn(&btnm)));
+                }
+            })
+            .unwrap();
+
+        let queue = events.clone();
+        status_label
+            .on_event(move |_label, event| {
+                if let lvgl::Event::Clicked = event {
+                    queue.push(UiEvent::NetworkStatusClicked);
+                }
+            })
             .unwrap();
 
         Self {
             pane,
             btn_matrix,
             prev_btn_selected: 0u16,
+            signal_bars,
+            status_label,
+            network_status: None,
+            show_ip_address: false,
         }
     }
 
@@ -76,12 +145,14 @@ impl<'a> NavigationPane<'a> {
         hide_obj(&mut self.pane);
     }
 
-    pub fn get_btn_selected(&self) -> u16 {
-        btnmatrix_get_selected_btn(&self.btn_matrix)
-    }
-
-    pub fn set_home_btn_selected(&mut self) {
-        self.set_new_btn_selected(0u16);
+    pub fn handle_request(&mut self, request: &UiRequest) {
+        match request {
+            UiRequest::SetNetworkStatus(network_status) => {
+                self.set_network_status(network_status.clone())
+            }
+            UiRequest::SetTheme(..) => self.apply_theme(),
+            _ => {}
+        }
     }
 
     pub fn set_new_btn_selected(&mut self, new_btn_selected: u16) {
@@ -90,52 +161,140 @@ impl<'a> NavigationPane<'a> {
         self.prev_btn_selected = new_btn_selected;
         lvgl::task_handler();
     }
+
+    pub fn set_network_status(&mut self, network_status: NetworkStatus) {
+        self.network_status = Some(network_status);
+        self.update_signal_bars();
+        self.update_status_label();
+    }
+
+    /// The colors of the signal bars are set with the network status, they are set again for the
+    /// theme
+    pub fn apply_theme(&mut self) {
+        self.update_signal_bars();
+    }
+
+    fn update_signal_bars(&mut self) {
+        let bars = match &self.network_status {
+            Some(network_status) => get_signal_bars(network_status.rssi),
+            None => 0,
+        };
+
+        let palette = theme::palette();
+        for (i, bar) in self.signal_bars.iter_mut().enumerate() {
+            if i < bars {
+                set_obj_bg_color(bar, theme::color(palette.good));
+            } else {
+                set_obj_bg_color(bar, theme::color(palette.muted));
+            }
+        }
+    }
+
+    // Toggle between the network status and the IP address
+    pub fn toggle_ip_address(&mut self) {
+        self.show_ip_address = !self.show_ip_address;
+        self.update_status_label();
+    }
+
+    fn update_status_label(&mut self) {
+        let text = i18n::text();
+        let status = match &self.network_status {
+            Some(network_status) => {
+                if self.show_ip_address {
+                    match &network_status.ip_address {
+                        Some(ip_address) => i18n::fill(text.ip_address, &[ip_address]),
+                        None => text.no_ip_address.to_string(),
+                    }
+                } else {
+                    get_status_text(network_status)
+                }
+            }
+            None => text.wifi_disconnected.to_string(),
+        };
+
+        self.status_label
+            .set_text(CString::new(status.as_str()).unwrap().as_c_str())
+            .unwrap();
+    }
+}
+
+fn get_status_text(network_status: &NetworkStatus) -> String {
+    let text = i18n::text();
+    let wifi = match network_status.wifi_state {
+        WifiState::Connected => network_status.ssid.clone(),
+        WifiState::Connecting => text.wifi_connecting.to_string(),
+        WifiState::Disconnected | WifiState::BackingOff => text.wifi_down.to_string(),
+    };
+
+    let time_sync = if network_status.time_synced {
+        text.time_synced
+    } else {
+        text.time_not_synced
+    };
+
+    let last_fetch = match network_status.last_fetch_secs {
+        Some(secs) if secs < 60 => text.updated_just_now.to_string(),
+        Some(secs) if secs < 60 * 60 => i18n::fill(text.updated_minutes_ago, &[&(secs / 60)]),
+        Some(secs) => i18n::fill(
+            text.updated_hours_ago,
+            &[&(secs / 3600), &((secs % 3600) / 60)],
+        ),
+        None => text.not_updated.to_string(),
+    };
+
+    format!("{} | {} | {}", wifi, time_sync, last_fetch)
+}
+
+fn get_signal_bars(rssi: Option<i8>) -> usize {
+    match rssi {
+        Some(rssi) if rssi >= -55 => 4,
+        Some(rssi) if rssi >= -65 => 3,
+        Some(rssi) if rssi >= -75 => 2,
+        Some(rssi) if rssi >= -85 => 1,
+        _ => 0,
+    }
 }
 
 //*****************************************************************************
 //                      Sytles for Navigation Pane
 //*****************************************************************************
-fn navigation_pane_style() -> Box<Style> {
-    let mut style = Style::default();
-    style.set_bg_color(Color::from_rgb((0, 0, 0))); // black
+fn navigation_pane_style(style: &mut Style, palette: &Palette) {
+    style.set_bg_color(theme::color(palette.background));
     style.set_radius(0);
     style.set_border_width(0);
-    //style.set_border_color(Color::from_rgb((255, 0, 0)));
-    style.set_pad_top(0);
-    style.set_pad_bottom(0);
-    style.set_pad_left(0);
-    style.set_pad_right(0);
-
-    Box::new(style)
+    theme::set_pad(style, 0, 0);
 }
 
-fn btn_matrix_style() -> Box<Style> {
-    let mut style = Style::default();
+fn btn_matrix_style(style: &mut Style, _palette: &Palette) {
     style.set_border_width(0);
     style.set_shadow_width(0);
     style.set_bg_opa(Opacity::OPA_TRANSP);
-    style.set_pad_top(2);
-    style.set_pad_bottom(2);
-    style.set_pad_left(2);
-    style.set_pad_right(2);
-
-    Box::new(style)
+    theme::set_pad(style, theme::BUTTONS_PAD, theme::BUTTONS_PAD);
+    style.set_text_font(theme::font(FontSize::Small));
 }
 
-fn app_title_style() -> Box<Style> {
-    let mut style = Style::default();
-    style.set_text_color(Color::from_rgb((255, 215, 0))); // gold
+fn app_title_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.highlight));
     style.set_text_align(TextAlign::Right);
-    style.set_text_font(unsafe { Font::new_raw(lv_font_montserrat_24) });
+    style.set_text_font(theme::font(FontSize::Large));
+}
 
-    Box::new(style)
+fn app_source_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.highlight));
+    style.set_text_align(TextAlign::Left);
+    style.set_text_font(theme::font(FontSize::Tiny));
 }
 
-fn app_source_style() -> Box<Style> {
-    let mut style = Style::default();
-    style.set_text_color(Color::from_rgb((255, 215, 0))); // gold
-    style.set_text_align(TextAlign::Right);
-    style.set_text_font(unsafe { Font::new_raw(lv_font_montserrat_12) });
+fn signal_bar_style(style: &mut Style, palette: &Palette) {
+    style.set_bg_color(theme::color(palette.muted));
+    style.set_bg_opa(Opacity::OPA_COVER);
+    style.set_radius(0);
+    style.set_border_width(0);
+    theme::set_pad(style, 0, 0);
+}
 
-    Box::new(style)
+fn status_label_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.text));
+    style.set_text_align(TextAlign::Right);
+    style.set_text_font(theme::font(FontSize::Small));
 }
@@ -0,0 +1,158 @@
+//! Download and installation of the firmware images of ota.rs.
+//!
+//! A new image boots in the pending verify state.  It has to confirm itself healthy with
+//! `mark_running_image_valid` once it has fetched a forecast, if it reboots or calls
+//! `rollback_running_image` before that the bootloader goes back to the previous image.
+use crate::ota::{parse_sha256, progress_percent, ImageHash, OtaConfig};
+
+use anyhow::{anyhow, bail, Result};
+use esp_idf_svc::http::client::{Configuration as HttpConfig, EspHttpConnection};
+use esp_idf_svc::ota::{EspOta, SlotState};
+
+use embedded_svc::{
+    http::{client::Client as HttpClient, Headers},
+    io::{Read, Write},
+};
+
+use sha2::{Digest, Sha256};
+
+use core::time::Duration;
+
+use log::{info, warn};
+
+// Size of the chunks the image is downloaded and written in
+const CHUNK_SIZE: usize = 4096;
+
+/// True when the running image was just installed and has not confirmed itself healthy yet
+pub fn is_running_image_pending_verify() -> bool {
+    match EspOta::new().and_then(|ota| ota.get_running_slot()) {
+        Ok(slot) => slot.state == SlotState::Unverified,
+        Err(e) => {
+            warn!("OTA running slot error = {}", e);
+            false
+        }
+    }
+}
+
+/// Confirms the running image, cancels the rollback to the previous image
+pub fn mark_running_image_valid() -> Result<()> {
+    EspOta::new()?.mark_running_slot_valid()?;
+    info!("OTA running image marked valid");
+    Ok(())
+}
+
+/// Marks the running image invalid and restarts into the previous image
+pub fn rollback_running_image() -> Result<()> {
+    warn!("OTA running image is not healthy, rolling back");
+    let e = EspOta::new()?.mark_running_slot_invalid_and_reboot();
+    Err(e.into())
+}
+
+/// Downloads the image, checks its hash and installs it in the other OTA slot.  The progress
+/// callback gets the percent downloaded.  The caller restarts the App to boot the new image.
+pub fn update_firmware(config: &OtaConfig, mut progress: impl FnMut(u8)) -> Result<()> {
+    let expected_hash = match &config.hash {
+        ImageHash::Pinned(sha256) => *sha256,
+        ImageHash::Url(sha256_url) => fetch_sha256(sha256_url)?,
+    };
+
+    let mut client = HttpClient::wrap(http_connection()?);
+    let mut response = client.get(&config.url)?.submit()?;
+    if response.status() != 200 {
+        bail!("Bad HTTP status - {}", response.status());
+    }
+
+    let image_size = response
+        .content_len()
+        .map(|size| size as usize)
+        .filter(|size| *size > 0);
+
+    let mut ota = EspOta::new()?;
+    let mut update = ota.initiate_update()?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; CHUNK_SIZE];
+    let mut bytes_written: usize = 0;
+    let mut last_progress = None;
+
+    info!("OTA downloading {}", config.url);
+
+    loop {
+        let bytes_read = match response.read(&mut buffer) {
+            Ok(bytes_read) => bytes_read,
+            Err(e) => {
+                update.abort()?;
+                bail!("OTA download error = {:?}", e);
+            }
+        };
+
+        if bytes_read == 0 {
+            break;
+        }
+
+        hasher.update(&buffer[..bytes_read]);
+        if let Err(e) = update.write_all(&buffer[..bytes_read]) {
+            update.abort()?;
+            bail!("OTA write error = {:?}", e);
+        }
+        bytes_written += bytes_read;
+
+        let percent = progress_percent(bytes_written, image_size);
+        if percent != last_progress {
+            last_progress = percent;
+            if let Some(percent) = percent {
+                progress(percent);
+            }
+        }
+    }
+
+    if image_size.is_some_and(|size| size != bytes_written) {
+        update.abort()?;
+        bail!(
+            "OTA image is incomplete, {} bytes downloaded",
+            bytes_written
+        );
+    }
+
+    if hasher.finalize().as_slice() != expected_hash {
+        update.abort()?;
+        bail!("OTA image SHA-256 does not match");
+    }
+
+    // The image header and checksum are checked by ESP-IDF when the update is completed
+    update.complete()?;
+    info!("OTA update installed, {} bytes", bytes_written);
+
+    Ok(())
+}
+
+fn fetch_sha256(url: &str) -> Result<[u8; 32]> {
+    let mut client = HttpClient::wrap(http_connection()?);
+    let mut response = client.get(url)?.submit()?;
+    if response.status() != 200 {
+        bail!("Bad HTTP status - {} for {}", response.status(), url);
+    }
+
+    let mut buffer = [0u8; 256];
+    let mut bytes_read = 0;
+    while bytes_read < buffer.len() {
+        let n = response
+            .read(&mut buffer[bytes_read..])
+            .map_err(|e| anyhow!("OTA SHA-256 read error = {:?}", e))?;
+        if n == 0 {
+            break;
+        }
+        bytes_read += n;
+    }
+
+    let text = std::str::from_utf8(&buffer[..bytes_read])?;
+    parse_sha256(text).map_err(|e| anyhow!(e))
+}
+
+fn http_connection() -> Result<EspHttpConnection> {
+    Ok(EspHttpConnection::new(&HttpConfig {
+        buffer_size: Some(CHUNK_SIZE),
+        crt_bundle_attach: Some(esp_idf_svc::sys::esp_crt_bundle_attach),
+        timeout: Some(Duration::from_secs(30)),
+        ..Default::default()
+    })?)
+}
@@ -0,0 +1,46 @@
+//! The screens of the App.
+//!
+//! Every screen implements `Pane` and is handed to the `PaneRouter`, which shows one pane at a
+//! time, keeps the `NavStack` and builds the nav bar buttons from `nav_title`.  A pane
+//! registers its LVGL event handlers when it is created, the handlers only push a `UiEvent` and
+//! the work is done in `handle_event` from the UI loop.
+use crate::model::ModelRequest;
+pub use crate::nav_stack::{pane_navigation, Navigation, PaneId};
+use crate::ui::UiRequest;
+use crate::ui_event::{UiEvent, UiEventQueue};
+
+use lvgl::Screen;
+
+use std::sync::mpsc::Sender;
+
+pub trait Pane<'a> {
+    /// Creates the pane hidden, the model requests of the pane are sent with `tx` and the events
+    /// of its widgets are pushed on `events`
+    fn create(parent: &'a mut Screen, tx: &Sender<ModelRequest>, events: &UiEventQueue) -> Self
+    where
+        Self: Sized;
+
+    fn id(&self) -> PaneId;
+
+    /// Title of the nav bar button of the pane, None for a pane that is not on the nav bar
+    fn nav_title(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Whether the nav bar is shown above the pane
+    fn shows_nav_bar(&self) -> bool {
+        true
+    }
+
+    fn show(&mut self);
+
+    fn hide(&mut self);
+
+    /// Every UI request is offered to every pane, a pane ignores the requests of the others
+    fn handle_request(&mut self, request: &UiRequest);
+
+    /// Every UI event is offered to every pane, a pane ignores the events of the others
+    fn handle_event(&mut self, _event: &UiEvent) -> Option<Navigation> {
+        None
+    }
+}
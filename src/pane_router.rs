@@ -0,0 +1,119 @@
+//============================================================================
+//                               Pane Router
+//============================================================================
+use crate::nav_stack::{NavStack, PaneId};
+use crate::navigation_pane::NavigationPane;
+use crate::pane::Pane;
+use crate::theme;
+use crate::ui::UiRequest;
+use crate::ui_event::{UiEvent, UiEventQueue};
+
+use lvgl::Screen;
+
+/// Shows one pane at a time with the nav bar above it when the pane wants it.  The nav bar has a
+/// button per pane with a `nav_title`, in the order the panes were given.
+pub struct PaneRouter<'a> {
+    nav_pane: NavigationPane<'a>,
+    panes: Vec<Box<dyn Pane<'a> + 'a>>,
+    nav_stack: NavStack,
+    events: UiEventQueue,
+}
+
+impl<'a> PaneRouter<'a> {
+    pub fn new(
+        navigation_pane_parent: &'a mut Screen,
+        panes: Vec<Box<dyn Pane<'a> + 'a>>,
+        events: &UiEventQueue,
+    ) -> Self {
+        let nav_buttons: Vec<(PaneId, &str)> = panes
+            .iter()
+            .filter_map(|pane| pane.nav_title().map(|title| (pane.id(), title)))
+            .collect();
+
+        let titles: Vec<&str> = nav_buttons.iter().map(|(_, title)| *title).collect();
+        let mut nav_pane = NavigationPane::new(navigation_pane_parent, &titles, events);
+        nav_pane.hide();
+
+        Self {
+            nav_pane,
+            panes,
+            nav_stack: NavStack::new(nav_buttons.into_iter().map(|(id, _)| id).collect()),
+            events: events.clone(),
+        }
+    }
+
+    /// The requests that show a pane start a new navigation stack, but the clock is shown on top
+    /// of the stack.  Every request is then offered to the nav bar and to every pane
+    pub fn handle_request(&mut self, request: UiRequest) {
+        match request {
+            UiRequest::ShowStartup => self.reset(PaneId::Startup),
+            UiRequest::ShowHome => self.reset(PaneId::Home),
+            UiRequest::ShowWifiSettings => self.reset(PaneId::WifiSettings),
+            UiRequest::ShowClock => {
+                self.nav_stack.show_clock();
+                self.show_top();
+            }
+            UiRequest::SetTheme(theme_name, dusk) => theme::set_theme(theme_name, dusk),
+            _ => {}
+        }
+
+        self.nav_pane.handle_request(&request);
+        for pane in self.panes.iter_mut() {
+            pane.handle_request(&request);
+        }
+    }
+
+    /// Handles the events queued by the LVGL event handlers
+    pub fn dispatch_events(&mut self) {
+        while let Some(event) = self.events.pop() {
+            self.handle_event(event);
+        }
+    }
+
+    /// The nav bar events are handled here, the others are offered to every pane
+    pub fn handle_event(&mut self, event: UiEvent) {
+        if let UiEvent::NetworkStatusClicked = event {
+            self.nav_pane.toggle_ip_address();
+        } else if self.nav_stack.handle_event(&event) {
+            self.show_top();
+        } else {
+            for index in 0..self.panes.len() {
+                if let Some(navigation) = self.panes[index].handle_event(&event) {
+                    self.nav_stack.navigate(navigation);
+                    self.show_top();
+                }
+            }
+        }
+    }
+
+    fn reset(&mut self, id: PaneId) {
+        self.nav_stack.reset(id);
+        self.show_top();
+    }
+
+    // Hides every pane but the one on top of the stack
+    fn show_top(&mut self) {
+        let Some(top) = self.nav_stack.top() else {
+            return;
+        };
+
+        for pane in self.panes.iter_mut().filter(|pane| pane.id() != top) {
+            pane.hide();
+        }
+
+        let Some(pane) = self.panes.iter_mut().find(|pane| pane.id() == top) else {
+            return;
+        };
+
+        if pane.shows_nav_bar() {
+            if let Some(btn_id) = self.nav_stack.nav_button(top) {
+                self.nav_pane.set_new_btn_selected(btn_id as u16);
+            }
+            self.nav_pane.show();
+        } else {
+            self.nav_pane.hide();
+        }
+
+        pane.show();
+    }
+}
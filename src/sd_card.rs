@@ -0,0 +1,48 @@
+use anyhow::Result;
+
+use esp_idf_svc::hal::{
+    delay::Ets,
+    gpio::{self, AnyOutputPin, Output, PinDriver},
+    peripheral,
+    spi::{SpiAnyPins, SpiConfig, SpiDeviceDriver, SpiDriver, SpiDriverConfig},
+    units::FromValueType,
+};
+
+use embedded_sdmmc::SdCard;
+
+use crate::file_store::FileStore;
+
+pub type SDcard = SdCard<
+    SpiDeviceDriver<'static, SpiDriver<'static>>,
+    PinDriver<'static, AnyOutputPin, Output>,
+    Ets,
+>;
+
+/// The files of the SDCard on the SPI bus of the device
+pub fn init(
+    spi: impl peripheral::Peripheral<P = impl SpiAnyPins> + 'static,
+    sclk: gpio::AnyOutputPin,
+    sdo: gpio::AnyOutputPin,
+    sdi: gpio::AnyInputPin,
+    cs: gpio::AnyOutputPin,
+) -> Result<FileStore<SDcard>> {
+    let spi = SpiDeviceDriver::new_single(
+        spi,
+        sclk,
+        sdo,
+        Some(sdi),
+        Option::<gpio::AnyIOPin>::None, // don't use chip select here
+        &SpiDriverConfig::new(),
+        &SpiConfig::new().baudrate(24.MHz().into()),
+        //&SpiDriverConfig::new().dma(Dma::Auto(4096)),
+        //&SpiConfig::new()
+        //    .duplex(Duplex::Full)
+        //    .baudrate(24.MHz().into()),
+    )?;
+
+    let sdcard_cs = PinDriver::output(cs)?;
+    let sdcard = SdCard::new(spi, sdcard_cs, Ets);
+
+    // The card itself is only accessed by `probe_card`, it may not be inserted yet
+    Ok(FileStore::new(sdcard))
+}
@@ -1,75 +1,155 @@
 //============================================================================
 //                              Startup Pane
 //============================================================================
+use crate::layout;
 use crate::lvgl_misc;
+use crate::model::ModelRequest;
+use crate::pane::{Pane, PaneId};
+use crate::theme::{self, FontSize, Palette};
+use crate::ui::UiRequest;
+use crate::ui_event::UiEventQueue;
 use cstr_core::CString;
-use lvgl::font::Font;
 use lvgl::style::Style;
-use lvgl::widgets::Label;
-use lvgl::{Align, Color, Obj, Part, Screen, TextAlign, Widget};
-use lvgl_sys::*;
+use lvgl::widgets::{Bar, Label};
+use lvgl::{Align, AnimationState, Obj, Part, Screen, TextAlign, Widget};
+use std::sync::mpsc::Sender;
 
 pub struct StartupPane<'a> {
     pane: Obj<'a>,
     message_label: Label<'a>,
+    progress_bar: Bar<'a>,
+    notice_label: Label<'a>,
 }
 
-impl<'a> StartupPane<'a> {
-    pub fn new(startup_pane_parent: &'a mut Screen) -> Self {
+impl<'a> Pane<'a> for StartupPane<'a> {
+    fn create(
+        startup_pane_parent: &'a mut Screen,
+        _tx: &Sender<ModelRequest>,
+        _events: &UiEventQueue,
+    ) -> Self {
         let mut pane = Obj::create(startup_pane_parent).unwrap();
-        pane.set_size(800, 480);
+        pane.set_size(layout::screen_width(), layout::screen_height());
         pane.set_align(Align::TopLeft, 0, 0);
-        pane.add_style(Part::Main, Box::leak(startup_pane_style()));
+        pane.add_style(Part::Main, theme::style(startup_pane_style));
 
         let mut message_label = Label::create(&mut pane).unwrap();
-        message_label.add_style(Part::Main, Box::leak(message_label_style()));
+        message_label.add_style(Part::Main, theme::style(message_label_style));
         message_label.set_align(Align::Center, 0, 0);
         message_label
             .set_text(CString::new("").unwrap().as_c_str())
             .unwrap();
 
+        // Progress bar, only shown while a firmware update is downloaded
+        let mut progress_bar = Bar::create(&mut pane).unwrap();
+        progress_bar.set_size(400, 20);
+        progress_bar.set_align(Align::Center, 0, 50);
+        progress_bar.add_style(Part::Main, theme::style(progress_bar_style));
+        progress_bar.add_style(Part::Indicator, theme::style(progress_indicator_style));
+        progress_bar.set_range(0, 100).unwrap();
+        lvgl_misc::hide_bar(&mut progress_bar);
+
+        // Notice at the bottom of the pane, tells why the SDCard is not used
+        let mut notice_label = Label::create(&mut pane).unwrap();
+        notice_label.add_style(Part::Main, theme::style(notice_label_style));
+        notice_label.set_align(Align::BottomMid, 0, -30);
+        notice_label
+            .set_text(CString::new("").unwrap().as_c_str())
+            .unwrap();
+
+        lvgl_misc::hide_obj(&mut pane);
+
         Self {
             pane,
             message_label,
+            progress_bar,
+            notice_label,
         }
     }
 
+    fn id(&self) -> PaneId {
+        PaneId::Startup
+    }
+
+    // The startup pane fills the screen
+    fn shows_nav_bar(&self) -> bool {
+        false
+    }
+
+    fn show(&mut self) {
+        lvgl_misc::show_obj(&mut self.pane);
+    }
+
+    fn hide(&mut self) {
+        lvgl_misc::hide_obj(&mut self.pane);
+    }
+
+    fn handle_request(&mut self, request: &UiRequest) {
+        match request {
+            UiRequest::SetStartupMessage(msg) => self.set_message(msg.clone()),
+            UiRequest::SetStartupProgress(percent) => self.set_progress(*percent),
+            UiRequest::SetStartupNotice(notice) => self.set_notice(notice.clone()),
+            _ => {}
+        }
+    }
+}
+
+impl<'a> StartupPane<'a> {
     pub fn set_message(&mut self, msg: String) {
         self.message_label
             .set_text(CString::new(msg.as_str()).unwrap().as_c_str())
             .unwrap();
     }
 
-    pub fn show(&mut self) {
-        lvgl_misc::show_obj(&mut self.pane);
+    /// An empty notice clears it
+    pub fn set_notice(&mut self, notice: String) {
+        self.notice_label
+            .set_text(CString::new(notice.as_str()).unwrap().as_c_str())
+            .unwrap();
     }
 
-    pub fn hide(&mut self) {
-        lvgl_misc::hide_obj(&mut self.pane);
+    /// Shows the progress bar with the percent done, None hides it
+    pub fn set_progress(&mut self, percent: Option<u8>) {
+        match percent {
+            Some(percent) => {
+                self.progress_bar
+                    .set_value(percent as i32, AnimationState::OFF)
+                    .unwrap();
+                lvgl_misc::show_bar(&mut self.progress_bar);
+            }
+            None => lvgl_misc::hide_bar(&mut self.progress_bar),
+        }
     }
 }
 
 //*****************************************************************************
 //                      Sytles for Startup Pane
 //*****************************************************************************
-pub fn startup_pane_style() -> Box<Style> {
-    let mut style = Style::default();
-    style.set_bg_color(Color::from_rgb((0, 0, 139))); // blue
+fn startup_pane_style(style: &mut Style, palette: &Palette) {
+    style.set_bg_color(theme::color(palette.splash));
     style.set_radius(0);
     style.set_border_width(0);
-    style.set_pad_top(0);
-    style.set_pad_bottom(0);
-    style.set_pad_left(0);
-    style.set_pad_right(0);
+    theme::set_pad(style, 0, 0);
+}
 
-    Box::new(style)
+fn message_label_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.text));
+    style.set_text_align(TextAlign::Center);
+    style.set_text_font(theme::font(FontSize::Large));
 }
 
-pub fn message_label_style() -> Box<Style> {
-    let mut style = Style::default();
-    style.set_text_color(Color::from_rgb((255, 255, 255))); // black
+fn notice_label_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.highlight));
     style.set_text_align(TextAlign::Center);
-    style.set_text_font(unsafe { Font::new_raw(lv_font_montserrat_24) });
+    style.set_text_font(theme::font(FontSize::Medium));
+}
+
+fn progress_bar_style(style: &mut Style, palette: &Palette) {
+    style.set_bg_color(theme::color(palette.track));
+    style.set_radius(theme::BUTTON_RADIUS);
+    style.set_border_width(0);
+}
 
-    Box::new(style)
+fn progress_indicator_style(style: &mut Style, palette: &Palette) {
+    style.set_bg_color(theme::color(palette.accent));
+    style.set_radius(theme::BUTTON_RADIUS);
 }
@@ -0,0 +1,334 @@
+//! Themes of the App.
+//!
+//! The colors, fonts and spacing of the panes are defined here once.  A pane keeps its own style
+//! functions but they take their colors from the `Palette` of the theme, their fonts from `font`
+//! and their spacing from the constants below.  The styles are created with `style`, which keeps
+//! the style function so the style can be built again when the theme is switched at runtime.
+//!
+//! The theme is set with `theme=` in SETTINGS.TXT, `dark` is the original look of the App.  With
+//! `auto_dark=true` the theme is blended into the dark theme in `DUSK_STEPS` steps around sunset
+//! and the dark theme is used until sunrise whatever the theme.
+use crate::settings::ThemeName;
+
+use lvgl::font::Font;
+use lvgl::style::Style;
+use lvgl::Color;
+use lvgl_sys::*;
+
+use std::sync::Mutex;
+
+pub type Rgb = (u8, u8, u8);
+
+/// Steps of the blend from the theme into the dark theme during the twilight
+pub const DUSK_STEPS: u8 = 8;
+
+/// Theme used at the moment and how many `DUSK_STEPS` it is blended into the dark theme, with
+/// `auto_dark` it follows `night_part`, from 0 by day to 1 by night
+pub fn effective_theme(theme: ThemeName, auto_dark: bool, night_part: f32) -> (ThemeName, u8) {
+    if auto_dark {
+        let dusk = (night_part.clamp(0.0, 1.0) * DUSK_STEPS as f32).round() as u8;
+        (theme, dusk)
+    } else {
+        (theme, 0)
+    }
+}
+
+/// The colors of a theme by the role they play
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    /// Background of the panes
+    pub background: Rgb,
+    /// Background of the startup pane
+    pub splash: Rgb,
+    /// Text on the background
+    pub text: Rgb,
+    /// Titles and notices on the background
+    pub highlight: Rgb,
+    /// Background of the city panes
+    pub surface: Rgb,
+    /// Border of the city panes and the buttons
+    pub outline: Rgb,
+    pub outline_width: lv_coord_t,
+    /// Text on the city panes
+    pub surface_text: Rgb,
+    /// Titles on the city panes
+    pub surface_title: Rgb,
+    /// Values on the city panes
+    pub surface_value: Rgb,
+    /// Clouds and snow of the weather icons
+    pub cloud: Rgb,
+    pub dark_cloud: Rgb,
+    /// Buttons and progress
+    pub accent: Rgb,
+    /// Text on the buttons
+    pub on_accent: Rgb,
+    /// Background of the progress bar
+    pub track: Rgb,
+    /// Background of the keyboards
+    pub keyboard: Rgb,
+    pub error: Rgb,
+    /// Signal bars that are off
+    pub muted: Rgb,
+    /// Signal bars that are on
+    pub good: Rgb,
+    /// Background, text and average line of the charts
+    pub chart: Rgb,
+    pub chart_text: Rgb,
+    pub chart_line: Rgb,
+}
+
+pub const DARK: Palette = Palette {
+    background: (0, 0, 0),    // black
+    splash: (0, 0, 139),      // navy blue
+    text: (255, 255, 255),    // white
+    highlight: (255, 215, 0), // gold
+    surface: (255, 215, 0),   // gold
+    outline: (0, 0, 0),       // black
+    outline_width: 0,
+    surface_text: (0, 0, 0),     // black
+    surface_title: (139, 0, 0),  // red4
+    surface_value: (0, 0, 139),  // navy blue
+    cloud: (255, 255, 255),      // white
+    dark_cloud: (112, 128, 144), // slate gray
+    accent: (30, 144, 255),      // dodger blue 1
+    on_accent: (0, 0, 0),        // black
+    track: (255, 255, 255),      // white
+    keyboard: (0, 0, 139),       // navy blue
+    error: (255, 0, 0),          // red
+    muted: (105, 105, 105),      // dim gray
+    good: (0, 205, 0),           // green 3
+    chart: (25, 25, 25),         // very dark gray
+    chart_text: (192, 192, 192), // silver
+    chart_line: (255, 255, 255), // white
+};
+
+pub const LIGHT: Palette = Palette {
+    background: (240, 240, 240), // light gray
+    splash: (176, 196, 222),     // light steel blue
+    text: (0, 0, 0),             // black
+    highlight: (0, 0, 139),      // navy blue
+    surface: (250, 235, 215),    // antique white
+    outline: (176, 196, 222),    // light steel blue
+    outline_width: 1,
+    surface_text: (0, 0, 0),    // black
+    surface_title: (139, 0, 0), // red4
+    surface_value: (0, 0, 139), // navy blue
+    cloud: (119, 136, 153),     // light slate gray
+    dark_cloud: (47, 79, 79),   // dark slate gray
+    accent: (30, 144, 255),     // dodger blue 1
+    on_accent: (255, 255, 255), // white
+    track: (211, 211, 211),     // light gray
+    keyboard: (176, 196, 222),  // light steel blue
+    error: (178, 34, 34),       // firebrick
+    muted: (169, 169, 169),     // dark gray
+    good: (0, 139, 0),          // green 4
+    chart: (255, 255, 255),     // white
+    chart_text: (64, 64, 64),   // dark gray
+    chart_line: (0, 0, 0),      // black
+};
+
+pub const HIGH_CONTRAST: Palette = Palette {
+    background: (0, 0, 0),    // black
+    splash: (0, 0, 0),        // black
+    text: (255, 255, 255),    // white
+    highlight: (255, 255, 0), // yellow
+    surface: (0, 0, 0),       // black
+    outline: (255, 255, 255), // white
+    outline_width: 2,
+    surface_text: (255, 255, 255), // white
+    surface_title: (255, 255, 0),  // yellow
+    surface_value: (0, 255, 255),  // cyan
+    cloud: (255, 255, 255),        // white
+    dark_cloud: (160, 160, 160),   // gray
+    accent: (255, 255, 0),         // yellow
+    on_accent: (0, 0, 0),          // black
+    track: (255, 255, 255),        // white
+    keyboard: (0, 0, 0),           // black
+    error: (255, 64, 64),          // light red
+    muted: (128, 128, 128),        // gray
+    good: (0, 255, 0),             // green
+    chart: (0, 0, 0),              // black
+    chart_text: (255, 255, 255),   // white
+    chart_line: (255, 255, 255),   // white
+};
+
+fn theme_palette(name: ThemeName) -> &'static Palette {
+    match name {
+        ThemeName::Light => &LIGHT,
+        ThemeName::Dark => &DARK,
+        ThemeName::HighContrast => &HIGH_CONTRAST,
+    }
+}
+
+pub fn color(rgb: Rgb) -> Color {
+    Color::from_rgb(rgb)
+}
+
+/// The palette `dusk` of `DUSK_STEPS` of the way from `day` to `night`
+pub fn blend(day: &Palette, night: &Palette, dusk: u8) -> Palette {
+    let dusk = dusk.min(DUSK_STEPS) as u32;
+    let mix = |day: Rgb, night: Rgb| {
+        let channel = |day: u8, night: u8| {
+            ((day as u32 * (DUSK_STEPS as u32 - dusk) + night as u32 * dusk) / DUSK_STEPS as u32)
+                as u8
+        };
+        (
+            channel(day.0, night.0),
+            channel(day.1, night.1),
+            channel(day.2, night.2),
+        )
+    };
+
+    Palette {
+        background: mix(day.background, night.background),
+        splash: mix(day.splash, night.splash),
+        text: mix(day.text, night.text),
+        highlight: mix(day.highlight, night.highlight),
+        surface: mix(day.surface, night.surface),
+        outline: mix(day.outline, night.outline),
+        outline_width: if dusk * 2 < DUSK_STEPS as u32 {
+            day.outline_width
+        } else {
+            night.outline_width
+        },
+        surface_text: mix(day.surface_text, night.surface_text),
+        surface_title: mix(day.surface_title, night.surface_title),
+        surface_value: mix(day.surface_value, night.surface_value),
+        cloud: mix(day.cloud, night.cloud),
+        dark_cloud: mix(day.dark_cloud, night.dark_cloud),
+        accent: mix(day.accent, night.accent),
+        on_accent: mix(day.on_accent, night.on_accent),
+        track: mix(day.track, night.track),
+        keyboard: mix(day.keyboard, night.keyboard),
+        error: mix(day.error, night.error),
+        muted: mix(day.muted, night.muted),
+        good: mix(day.good, night.good),
+        chart: mix(day.chart, night.chart),
+        chart_text: mix(day.chart_text, night.chart_text),
+        chart_line: mix(day.chart_line, night.chart_line),
+    }
+}
+
+//============================================================================
+//                                  Fonts
+//============================================================================
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FontSize {
+    /// montserrat 10
+    Tiny,
+    /// montserrat 12, the LVGL default font
+    Small,
+    /// montserrat 14
+    Body,
+    /// montserrat 16
+    Medium,
+    /// montserrat 24
+    Large,
+    /// montserrat 36
+    Huge,
+}
+
+pub fn font(size: FontSize) -> Font {
+    unsafe {
+        Font::new_raw(match size {
+            FontSize::Tiny => lv_font_montserrat_10,
+            FontSize::Small => lv_font_montserrat_12,
+            FontSize::Body => lv_font_montserrat_14,
+            FontSize::Medium => lv_font_montserrat_16,
+            FontSize::Large => lv_font_montserrat_24,
+            FontSize::Huge => lv_font_montserrat_36,
+        })
+    }
+}
+
+//============================================================================
+//                                 Spacing
+//============================================================================
+/// Padding of the panes on the sides
+pub const PANE_PAD: lv_coord_t = 12;
+/// Padding of the settings panes at the top and bottom
+pub const PANE_PAD_TOP: lv_coord_t = 4;
+/// Padding inside the text areas and the charts
+pub const WIDGET_PAD: lv_coord_t = 4;
+/// Padding inside the button matrices and the city panes
+pub const BUTTONS_PAD: lv_coord_t = 2;
+pub const BUTTON_RADIUS: lv_coord_t = 6;
+
+pub fn set_pad(style: &mut Style, vertical: lv_coord_t, horizontal: lv_coord_t) {
+    style.set_pad_top(vertical);
+    style.set_pad_bottom(vertical);
+    style.set_pad_left(horizontal);
+    style.set_pad_right(horizontal);
+}
+
+//============================================================================
+//                             Style Registry
+//============================================================================
+/// Sets the properties of a style from the palette, it must set the same properties whatever
+/// the palette
+pub type StyleFn = fn(&mut Style, &Palette);
+
+struct ThemedStyle {
+    style: *mut Style,
+    apply: StyleFn,
+}
+
+// The styles are leaked and only used by the UI thread
+unsafe impl Send for ThemedStyle {}
+
+struct ThemeState {
+    name: ThemeName,
+    dusk: u8,
+    palette: Palette,
+    styles: Vec<ThemedStyle>,
+}
+
+static THEME: Mutex<ThemeState> = Mutex::new(ThemeState {
+    name: ThemeName::Dark,
+    dusk: 0,
+    palette: DARK,
+    styles: Vec::new(),
+});
+
+/// Creates a style for the lifetime of the App, it is built again when the theme is switched
+pub fn style(apply: StyleFn) -> &'static mut Style {
+    let mut theme = THEME.lock().unwrap();
+
+    let style = Box::leak(Box::new(Style::default()));
+    apply(style, &theme.palette);
+    theme.styles.push(ThemedStyle {
+        style: style as *mut Style,
+        apply,
+    });
+
+    style
+}
+
+pub fn current() -> ThemeName {
+    THEME.lock().unwrap().name
+}
+
+/// Palette used at the moment, blended into the dark theme during the twilight
+pub fn palette() -> Palette {
+    THEME.lock().unwrap().palette
+}
+
+/// Builds every style again with the palette of the theme blended `dusk` of `DUSK_STEPS` into
+/// the dark theme and redraws the screen, call it from the UI thread
+pub fn set_theme(name: ThemeName, dusk: u8) {
+    let mut theme = THEME.lock().unwrap();
+    if theme.name == name && theme.dusk == dusk {
+        return;
+    }
+
+    theme.name = name;
+    theme.dusk = dusk;
+    theme.palette = blend(theme_palette(name), &DARK, dusk);
+    for themed_style in theme.styles.iter() {
+        unsafe { (themed_style.apply)(&mut *themed_style.style, &theme.palette) };
+    }
+    drop(theme);
+
+    // Every object picks up the changed styles
+    unsafe { lv_obj_report_style_change(core::ptr::null_mut()) };
+}
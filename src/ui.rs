@@ -1,14 +1,26 @@
+use crate::cities::CityInfo;
 use crate::cities_settings_pane::CitiesSettingsPane;
+use crate::clock_pane::ClockPane;
+use crate::display_settings_pane::DisplaySettingsPane;
 use crate::gt911::{TouchState, GT911};
+use crate::history::CityHistory;
+use crate::history_pane::HistoryPane;
 use crate::home_pane::HomePane;
+use crate::layout;
 use crate::lcd_panel::{LcdPanel, PanelConfig, PanelFlagsConfig, TimingFlagsConfig, TimingsConfig};
 use crate::model::ModelRequest;
-use crate::model::{CityForecast, CityInfo};
-use crate::navigation_pane::NavigationPane;
+use crate::model::{CityForecast, NetworkStatus};
+use crate::orientation::Orientation;
+use crate::pane::Pane;
+use crate::pane_router::PaneRouter;
+use crate::settings::Settings;
+use crate::settings::ThemeName;
 use crate::startup_pane::StartupPane;
+use crate::ui_event::UiEventQueue;
+use crate::wifi_manager::WifiState;
 use crate::wifi_settings_pane::WifiSettingsPane;
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::sync::mpsc::{Receiver, Sender};
 use std::thread;
 
@@ -31,13 +43,26 @@ pub enum UiRequest {
     ShowStartup,
     ShowWifiSettings,
     ShowHome,
+    /// Shows the clock over the pane shown, unless the Startup pane is shown
+    ShowClock,
     SetStartupMessage(String),
+    SetStartupProgress(Option<u8>),
+    SetStartupNotice(String),
     SetWifiSettingsErrorMessage(String),
     SetCityTime(usize, String, String),
     SetCitiesSettings(Vec<CityInfo>),
     SetCitiesTitles(Vec<CityInfo>),
     SetCityForecast(usize, CityForecast),
     SetWifiCreds(String, String),
+    SetWifiState(WifiState),
+    SetNetworkStatus(NetworkStatus),
+    SetHistory(CityHistory),
+    SetHistoryMessage(String),
+    /// The theme and how many steps it is blended into the dark theme
+    SetTheme(ThemeName, u8),
+    /// The backlight is dimmed or off, the next touch only wakes the screen
+    SetScreenAsleep(bool),
+    SetSettings(Settings),
 }
 
 pub struct UserInterface<I2C>
@@ -45,6 +70,7 @@ where
     I2C: I2c,
 {
     gt911: GT911<I2C>,
+    orientation: Orientation,
     tx: Sender<ModelRequest>,
     rx: Receiver<UiRequest>,
 }
@@ -53,17 +79,34 @@ impl<I2C> UserInterface<I2C>
 where
     I2C: I2c + Send + 'static,
 {
-    pub fn new(gt911: GT911<I2C>, tx: Sender<ModelRequest>, rx: Receiver<UiRequest>) -> Self {
-        Self { gt911, tx, rx }
+    /// The touchscreen transforms its points for `orientation`, the display is rotated to match
+    pub fn new(
+        gt911: GT911<I2C>,
+        orientation: Orientation,
+        tx: Sender<ModelRequest>,
+        rx: Receiver<UiRequest>,
+    ) -> Self {
+        Self {
+            gt911,
+            orientation,
+            tx,
+            rx,
+        }
     }
 
     pub fn run(self) {
         info!("---------- Creating UI Thread ----------");
         let _lvgl_thread = thread::Builder::new().stack_size(24 * 1024).spawn(move || {
-            const HOR_RES: u32 = 800;
-            const VER_RES: u32 = 480;
+            // The draw buffer holds LINES of the longest side of the panel
+            const MAX_RES: u32 = layout::PANEL_SIZE.width as u32;
             const LINES: u32 = 4; // The number of lines (rows) that will be refreshed
-            const DRAW_BUFFER_SIZE: usize = (HOR_RES * LINES) as usize;
+            const DRAW_BUFFER_SIZE: usize = (MAX_RES * LINES) as usize;
+
+            // The panes are laid out for the orientation, LVGL draws the screen upright and the
+            // panel rotates the bitmaps
+            layout::init(self.orientation);
+            let hor_res = layout::screen_width() as u32;
+            let ver_res = layout::screen_height() as u32;
 
             lvgl::init();
 
@@ -74,9 +117,10 @@ where
                 &TimingFlagsConfig::new(),
             )
             .unwrap();
+            lcd_panel.set_orientation(self.orientation).unwrap();
 
             let draw_buffer = DrawBuffer::<{ DRAW_BUFFER_SIZE }>::default();
-            let display = Display::register(draw_buffer, HOR_RES, VER_RES, |refresh| {
+            let display = Display::register(draw_buffer, hor_res, ver_res, |refresh| {
                 lcd_panel
                     .set_pixels_lvgl_color(
                         refresh.area.x1.into(),
@@ -89,23 +133,40 @@ where
             })
             .unwrap();
 
+            // Every touch is reported to the model for the backlight.  The touch that wakes the
+            // screen is reported to LVGL as released until the finger is lifted, so it doesn't
+            // click on what could not be seen.
             let touch_screen = RefCell::new(self.gt911);
+            let screen_asleep = Cell::new(false);
+            let touch_pressed = Cell::new(false);
+            let swallow_touch = Cell::new(false);
+            let activity_tx = self.tx.clone();
             let read_touchscreen_cb = || {
                 let touch = touch_screen.borrow_mut().read_touch().unwrap();
 
                 match touch {
                     TouchState::PRESSED(tp) => {
                         //info!("Pressed");
-                        PointerInputData::Touch(Point {
+                        if !touch_pressed.replace(true) {
+                            activity_tx.send(ModelRequest::UserActivity).unwrap();
+                            swallow_touch.set(screen_asleep.replace(false));
+                        }
+
+                        let point = PointerInputData::Touch(Point {
                             x: tp.x as i32,
                             y: tp.y as i32,
-                        })
-                        .pressed()
-                        .once()
+                        });
+                        if swallow_touch.get() {
+                            point.released().once()
+                        } else {
+                            point.pressed().once()
+                        }
                     }
 
                     TouchState::RELEASED(tp) => {
                         //info!("Released");
+                        touch_pressed.set(false);
+                        swallow_touch.set(false);
                         PointerInputData::Touch(Point {
                             x: tp.x as i32,
                             y: tp.y as i32,
@@ -119,323 +180,63 @@ where
             // Register the touchscreen callback with the display
             let _the_touch_screen = Pointer::register(read_touchscreen_cb, &display).unwrap();
 
-            let mut startup_pane_parent = display.get_scr_act().unwrap();
-            let mut startup_pane = StartupPane::new(&mut startup_pane_parent);
-            startup_pane.hide();
-
-            let mut navigation_pane_parent = display.get_scr_act().unwrap();
-            let mut nav_pane = NavigationPane::new(&mut navigation_pane_parent);
-            nav_pane.hide();
+            // Pushed by the LVGL event handlers of the panes
+            let events = UiEventQueue::default();
 
+            // The panes are drawn in this order, the nav bar is created last by the router.  The
+            // nav bar buttons follow the order of the panes.
+            let mut startup_pane_parent = display.get_scr_act().unwrap();
             let mut home_pane_parent = display.get_scr_act().unwrap();
-            let mut home_pane = HomePane::new(&mut home_pane_parent);
-            home_pane.hide();
-
             let mut wifi_settings_pane_parent = display.get_scr_act().unwrap();
-            let mut ws_pane = WifiSettingsPane::new(&mut wifi_settings_pane_parent);
-            ws_pane.hide();
-
             let mut cities_settings_pane_parent = display.get_scr_act().unwrap();
-            let mut cs_pane = CitiesSettingsPane::new(&mut cities_settings_pane_parent);
-            cs_pane.hide();
-
-            // Textarea events - assign the virtual keyboard to this textarea
-            ws_pane
-                .ssid_ta
-                .on_event(|mut ta, event| {
-                    if event == lvgl::Event::Clicked {
-                        ws_pane.keyboard.set_textarea(&mut ta);
-                    }
-                })
-                .unwrap();
-
-            ws_pane
-                .pswd_ta
-                .on_event(|mut ta, event| {
-                    if event == lvgl::Event::Clicked {
-                        ws_pane.keyboard.set_textarea(&mut ta);
-                    }
-                })
-                .unwrap();
-
-            const CITY_1: usize = 0;
-            const CITY_2: usize = 1;
-            const CITY_3: usize = 2;
-            const CITY_4: usize = 3;
-
-            cs_pane.cities_widgets[CITY_1]
-                .city_name
-                .on_event(|mut ta, event| {
-                    if event == lvgl::Event::Clicked {
-                        cs_pane.keyboard.set_textarea(&mut ta);
-                    }
-                })
-                .unwrap();
-
-            cs_pane.cities_widgets[CITY_1]
-                .zipcode
-                .on_event(|mut ta, event| {
-                    if event == lvgl::Event::Clicked {
-                        cs_pane.keyboard.set_textarea(&mut ta);
-                    }
-                })
-                .unwrap();
-
-            cs_pane.cities_widgets[CITY_2]
-                .city_name
-                .on_event(|mut ta, event| {
-                    if event == lvgl::Event::Clicked {
-                        cs_pane.keyboard.set_textarea(&mut ta);
-                    }
-                })
-                .unwrap();
+            let mut history_pane_parent = display.get_scr_act().unwrap();
+            let mut display_settings_pane_parent = display.get_scr_act().unwrap();
+            let mut clock_pane_parent = display.get_scr_act().unwrap();
+            let panes: Vec<Box<dyn Pane<'_> + '_>> = vec![
+                Box::new(StartupPane::create(
+                    &mut startup_pane_parent,
+                    &self.tx,
+                    &events,
+                )),
+                Box::new(HomePane::create(&mut home_pane_parent, &self.tx, &events)),
+                Box::new(WifiSettingsPane::create(
+                    &mut wifi_settings_pane_parent,
+                    &self.tx,
+                    &events,
+                )),
+                Box::new(CitiesSettingsPane::create(
+                    &mut cities_settings_pane_parent,
+                    &self.tx,
+                    &events,
+                )),
+                Box::new(HistoryPane::create(
+                    &mut history_pane_parent,
+                    &self.tx,
+                    &events,
+                )),
+                Box::new(DisplaySettingsPane::create(
+                    &mut display_settings_pane_parent,
+                    &self.tx,
+                    &events,
+                )),
+                Box::new(ClockPane::create(&mut clock_pane_parent, &self.tx, &events)),
+            ];
 
-            cs_pane.cities_widgets[CITY_2]
-                .zipcode
-                .on_event(|mut ta, event| {
-                    if event == lvgl::Event::Clicked {
-                        cs_pane.keyboard.set_textarea(&mut ta);
-                    }
-                })
-                .unwrap();
-
-            cs_pane.cities_widgets[CITY_3]
-                .city_name
-                .on_event(|mut ta, event| {
-                    if event == lvgl::Event::Clicked {
-                        cs_pane.keyboard.set_textarea(&mut ta);
-                    }
-                })
-                .unwrap();
-
-            cs_pane.cities_widgets[CITY_3]
-                .zipcode
-                .on_event(|mut ta, event| {
-                    if event == lvgl::Event::Clicked {
-                        cs_pane.keyboard.set_textarea(&mut ta);
-                    }
-                })
-                .unwrap();
-
-            cs_pane.cities_widgets[CITY_4]
-                .city_name
-                .on_event(|mut ta, event| {
-                    if event == lvgl::Event::Clicked {
-                        cs_pane.keyboard.set_textarea(&mut ta);
-                    }
-                })
-                .unwrap();
-
-            cs_pane.cities_widgets[CITY_4]
-                .zipcode
-                .on_event(|mut ta, event| {
-                    if event == lvgl::Event::Clicked {
-                        cs_pane.keyboard.set_textarea(&mut ta);
-                    }
-                })
-                .unwrap();
-
-            // Button clicked events - set the appropriate flag that will be checked inside the loop
-            let mut ws_pane_edit_btn_clicked = false;
-            ws_pane
-                .edit_btn
-                .on_event(|_btn, event| {
-                    if let lvgl::Event::Clicked = event {
-                        ws_pane_edit_btn_clicked = true;
-                    }
-                })
-                .unwrap();
-
-            let mut ws_pane_save_btn_clicked = false;
-            ws_pane
-                .save_btn
-                .on_event(|_btn, event| {
-                    if let lvgl::Event::Clicked = event {
-                        ws_pane_save_btn_clicked = true;
-                    }
-                })
-                .unwrap();
-
-            let mut ws_pane_exit_btn_clicked = false;
-            ws_pane
-                .exit_btn
-                .on_event(|_btn, event| {
-                    if let lvgl::Event::Clicked = event {
-                        ws_pane_exit_btn_clicked = true;
-                    }
-                })
-                .unwrap();
-
-            let mut cs_pane_edit_btn_clicked = false;
-            cs_pane
-                .edit_btn
-                .on_event(|_btn, event| {
-                    if let lvgl::Event::Clicked = event {
-                        cs_pane_edit_btn_clicked = true;
-                    }
-                })
-                .unwrap();
-
-            let mut cs_pane_save_btn_clicked = false;
-            cs_pane
-                .save_btn
-                .on_event(|_btn, event| {
-                    if let lvgl::Event::Clicked = event {
-                        cs_pane_save_btn_clicked = true;
-                    }
-                })
-                .unwrap();
-
-            let mut cs_pane_exit_btn_clicked = false;
-            cs_pane
-                .exit_btn
-                .on_event(|_btn, event| {
-                    if let lvgl::Event::Clicked = event {
-                        cs_pane_exit_btn_clicked = true;
-                    }
-                })
-                .unwrap();
-
-            let mut nav_pane_btn_matrix_btn_clicked = false;
-            nav_pane
-                .btn_matrix
-                .on_event(|_btnm, event| {
-                    if let lvgl::Event::Pressed = event {
-                        nav_pane_btn_matrix_btn_clicked = true;
-                    }
-                })
-                .unwrap();
-
-            const BTN_ID_HOME: u16 = 0;
-            const BTN_ID_WIFI_SETTINGS: u16 = 1;
-            const BTN_ID_CITIES_SETTINGS: u16 = 2;
+            let mut navigation_pane_parent = display.get_scr_act().unwrap();
+            let mut router = PaneRouter::new(&mut navigation_pane_parent, panes, &events);
 
             info!("Entering UI thread loop");
 
             // The loop - do not exit thread and run forever in this thread
             loop {
-                if let Ok(gui_request) = self.rx.try_recv() {
-                    match gui_request {
-                        UiRequest::ShowStartup => {
-                            ws_pane.hide();
-                            nav_pane.hide();
-                            home_pane.hide();
-                            startup_pane.show();
-                        }
-
-                        UiRequest::SetStartupMessage(msg) => {
-                            startup_pane.set_message(msg);
-                        }
-
-                        UiRequest::ShowHome => {
-                            startup_pane.hide();
-                            nav_pane.show();
-                            nav_pane.set_new_btn_selected(BTN_ID_HOME);
-                            home_pane.show();
-                        }
-
-                        UiRequest::ShowWifiSettings => {
-                            startup_pane.hide();
-                            nav_pane.set_new_btn_selected(BTN_ID_WIFI_SETTINGS);
-                            nav_pane.show();
-                            ws_pane.show();
-                        }
-
-                        UiRequest::SetWifiSettingsErrorMessage(msg) => {
-                            ws_pane.set_error_message(msg);
-                        }
-
-                        UiRequest::SetCityTime(city_number, time, date) => {
-                            home_pane.set_city_time_date(city_number, time, date);
-                        }
-
-                        UiRequest::SetCitiesTitles(cities_info) => {
-                            home_pane.set_cities_title(cities_info);
-                        }
-
-                        UiRequest::SetCitiesSettings(cities_info) => {
-                            cs_pane.set_cities_settings(cities_info);
-                        }
-
-                        UiRequest::SetCityForecast(city_number, city_forecast) => {
-                            home_pane.set_city_forecast(city_number, city_forecast);
-                        }
-
-                        UiRequest::SetWifiCreds(ssid, pass) => {
-                            ws_pane.set_wifi_credentials(ssid, pass);
-                        }
-                    }
-                }
-                // Check button clicked flags
-                if nav_pane_btn_matrix_btn_clicked {
-                    let btn_id = nav_pane.get_btn_selected();
-                    nav_pane.set_new_btn_selected(btn_id);
-
-                    match btn_id {
-                        // Home button clicked
-                        BTN_ID_HOME => {
-                            cs_pane.hide();
-                            ws_pane.hide();
-                            home_pane.show();
-                        }
-                        // Wifi settings button clicked
-                        BTN_ID_WIFI_SETTINGS => {
-                            home_pane.hide();
-                            cs_pane.hide();
-                            ws_pane.show();
-                        }
-                        // Cities settings button clicked
-                        BTN_ID_CITIES_SETTINGS => {
-                            home_pane.hide();
-                            ws_pane.hide();
-                            cs_pane.show();
-                        }
-                        _ => {}
-                    }
-                    nav_pane_btn_matrix_btn_clicked = false;
-                }
-
-                if cs_pane_edit_btn_clicked {
-                    cs_pane_edit_btn_clicked = false;
-                    cs_pane.enable_editing();
-                }
-
-                if cs_pane_save_btn_clicked {
-                    cs_pane_save_btn_clicked = false;
-
-                    if let Some(user_cities_info) = cs_pane.get_user_cities_settings() {
-                        send_cities_info_update(&self.tx, user_cities_info);
-                        cs_pane.hide();
-                    }
-                }
-
-                if cs_pane_exit_btn_clicked {
-                    cs_pane_exit_btn_clicked = false;
-                    nav_pane.set_home_btn_selected();
-                    cs_pane.hide();
-                    home_pane.show();
-                }
-
-                if ws_pane_edit_btn_clicked {
-                    ws_pane_edit_btn_clicked = false;
-                    ws_pane.enable_editing();
-                }
-
-                if ws_pane_save_btn_clicked {
-                    ws_pane_save_btn_clicked = false;
-                    let (ssid, pass) = ws_pane.get_user_wifi_creds_entries();
-                    send_wifi_credentials_update(&self.tx, ssid.clone(), pass.clone());
-                    ws_pane.hide();
-                }
-
-                if ws_pane_exit_btn_clicked {
-                    ws_pane_exit_btn_clicked = false;
-                    nav_pane.set_home_btn_selected();
-                    ws_pane.hide();
-                    home_pane.show();
+                match self.rx.try_recv() {
+                    Ok(UiRequest::SetScreenAsleep(asleep)) => screen_asleep.set(asleep),
+                    Ok(ui_request) => router.handle_request(ui_request),
+                    Err(_) => {}
                 }
 
                 lvgl::task_handler();
+                router.dispatch_events();
 
                 // Give other threads chance to run
                 FreeRtos::delay_ms(10);
@@ -443,12 +244,3 @@ where
         });
     }
 }
-
-fn send_wifi_credentials_update(tx: &Sender<ModelRequest>, ssid: String, pass: String) {
-    tx.send(ModelRequest::UpdateWifiCreds(ssid, pass)).unwrap();
-}
-
-fn send_cities_info_update(tx: &Sender<ModelRequest>, cities_info: Vec<CityInfo>) {
-    tx.send(ModelRequest::UpdateCitiesInfo(cities_info.clone()))
-        .unwrap();
-}
@@ -0,0 +1,220 @@
+//============================================================================
+//                          Weather Icon Widget
+//============================================================================
+use crate::lvgl_misc::*;
+use crate::theme::{self, FontSize, Palette};
+use crate::weather_icons::{CloudShade, IconParts, Sky, WeatherIcon};
+use cstr_core::CString;
+use lvgl::font::Font;
+use lvgl::style::{Opacity, Style};
+use lvgl::widgets::Label;
+use lvgl::{Align, Color, NativeObject, Obj, Part, TextAlign, Widget};
+use lvgl_sys::*;
+
+pub const ICON_WIDTH: i32 = 36;
+pub const ICON_HEIGHT: i32 = 36;
+
+// The cloud glyph of cloud24.c
+const CLOUD_GLYPH: &str = "\u{f0c2}";
+
+// LV_SYMBOL_CHARGE of the montserrat fonts
+const LIGHTNING_GLYPH: &str = "\u{f0e7}";
+
+// Size of the sun or the moon alone and behind the cloud
+const SKY_SIZE: i32 = 26;
+const SKY_BEHIND_CLOUD_SIZE: i32 = 16;
+
+/// A weather icon drawn with a few objects, the parts of the icon are shown or hidden
+pub struct WeatherIconWidget<'a> {
+    icon: Obj<'a>,
+    sky: Obj<'a>,
+    cloud: Label<'a>,
+    precipitation: Label<'a>,
+    thunder: Label<'a>,
+    fog: Label<'a>,
+    weather_icon: Option<WeatherIcon>,
+}
+
+impl<'a> WeatherIconWidget<'a> {
+    /// Creates the icon hidden, until `set_icon` is called
+    pub fn new(parent: &mut impl NativeObject) -> Self {
+        let mut icon = Obj::create(parent).unwrap();
+        icon.set_size(ICON_WIDTH, ICON_HEIGHT);
+        icon.add_style(Part::Main, theme::style(icon_style));
+
+        // Created first so it is drawn behind the cloud
+        let mut sky = Obj::create(&mut icon).unwrap();
+        sky.add_style(Part::Main, theme::style(sky_style));
+
+        let mut cloud = Label::create(&mut icon).unwrap();
+        cloud.set_align(Align::TopLeft, 2, 2);
+        cloud.add_style(Part::Main, theme::style(cloud_style));
+        cloud
+            .set_text(CString::new(CLOUD_GLYPH).unwrap().as_c_str())
+            .unwrap();
+
+        let mut precipitation = Label::create(&mut icon).unwrap();
+        precipitation.add_style(Part::Main, theme::style(precipitation_style));
+
+        let mut thunder = Label::create(&mut icon).unwrap();
+        thunder.add_style(Part::Main, theme::style(thunder_style));
+        thunder
+            .set_text(CString::new(LIGHTNING_GLYPH).unwrap().as_c_str())
+            .unwrap();
+
+        let mut fog = Label::create(&mut icon).unwrap();
+        fog.set_align(Align::Center, 0, 0);
+        fog.add_style(Part::Main, theme::style(fog_style));
+        fog.set_text(CString::new("~~~~\n~~~~").unwrap().as_c_str())
+            .unwrap();
+
+        hide_obj(&mut icon);
+
+        Self {
+            icon,
+            sky,
+            cloud,
+            precipitation,
+            thunder,
+            fog,
+            weather_icon: None,
+        }
+    }
+
+    pub fn set_align(&mut self, align: Align, x: i32, y: i32) {
+        self.icon.set_align(align, x, y);
+    }
+
+    /// Shows the icon, None hides it
+    pub fn set_icon(&mut self, weather_icon: Option<WeatherIcon>) {
+        self.weather_icon = weather_icon;
+        let Some(weather_icon) = weather_icon else {
+            hide_obj(&mut self.icon);
+            return;
+        };
+
+        let parts = weather_icon.parts();
+        let palette = theme::palette();
+        self.set_sky(&parts);
+
+        match parts.cloud {
+            Some(shade) => {
+                let color = match shade {
+                    CloudShade::Light => theme::color(palette.cloud),
+                    CloudShade::Dark => theme::color(palette.dark_cloud),
+                };
+                set_label_text_color(&mut self.cloud, color);
+                show_label(&mut self.cloud);
+            }
+            None => hide_label(&mut self.cloud),
+        }
+
+        match parts.precipitation {
+            Some(precipitation) => {
+                // Leave room for the lightning bolt on the right
+                if parts.thunder {
+                    self.precipitation.set_align(Align::BottomLeft, 2, 0);
+                } else {
+                    self.precipitation.set_align(Align::BottomMid, 0, 0);
+                }
+
+                let color = if precipitation.is_snow() {
+                    theme::color(palette.cloud)
+                } else {
+                    Color::from_rgb((30, 144, 255)) // dodger blue 1
+                };
+                set_label_text_color(&mut self.precipitation, color);
+                self.precipitation
+                    .set_text(CString::new(precipitation.marks()).unwrap().as_c_str())
+                    .unwrap();
+                show_label(&mut self.precipitation);
+            }
+            None => hide_label(&mut self.precipitation),
+        }
+
+        if parts.thunder {
+            if parts.precipitation.is_some() {
+                self.thunder.set_align(Align::BottomRight, -2, 0);
+            } else {
+                self.thunder.set_align(Align::BottomMid, 0, 0);
+            }
+            show_label(&mut self.thunder);
+        } else {
+            hide_label(&mut self.thunder);
+        }
+
+        if parts.fog {
+            show_label(&mut self.fog);
+        } else {
+            hide_label(&mut self.fog);
+        }
+
+        show_obj(&mut self.icon);
+    }
+
+    /// Draws the icon again with the colors of the theme
+    pub fn apply_theme(&mut self) {
+        self.set_icon(self.weather_icon);
+    }
+
+    // The sun or the moon fills the icon on a clear sky and peeks out from behind the cloud
+    // otherwise
+    fn set_sky(&mut self, parts: &IconParts) {
+        let Some(sky) = parts.sky else {
+            hide_obj(&mut self.sky);
+            return;
+        };
+
+        if parts.cloud.is_some() {
+            self.sky
+                .set_size(SKY_BEHIND_CLOUD_SIZE, SKY_BEHIND_CLOUD_SIZE);
+            self.sky.set_align(Align::TopRight, 0, 0);
+        } else {
+            self.sky.set_size(SKY_SIZE, SKY_SIZE);
+            self.sky.set_align(Align::Center, 0, 0);
+        }
+
+        let color = match sky {
+            Sky::Sun => Color::from_rgb((255, 140, 0)), // dark orange
+            Sky::Moon => Color::from_rgb((192, 192, 192)), // silver
+        };
+        set_obj_bg_color(&mut self.sky, color);
+        show_obj(&mut self.sky);
+    }
+}
+
+//*****************************************************************************
+//                      Sytles for Weather Icon Widget
+//*****************************************************************************
+fn icon_style(style: &mut Style, _palette: &Palette) {
+    style.set_bg_opa(Opacity::OPA_TRANSP);
+    style.set_border_width(0);
+    style.set_radius(0);
+    theme::set_pad(style, 0, 0);
+}
+
+fn sky_style(style: &mut Style, _palette: &Palette) {
+    style.set_bg_opa(Opacity::OPA_COVER);
+    style.set_border_width(0);
+    style.set_radius(LV_RADIUS_CIRCLE as _);
+}
+
+fn cloud_style(style: &mut Style, _palette: &Palette) {
+    style.set_text_font(unsafe { Font::new_raw(cloud24) });
+}
+
+fn precipitation_style(style: &mut Style, _palette: &Palette) {
+    style.set_text_align(TextAlign::Center);
+    style.set_text_font(theme::font(FontSize::Tiny));
+}
+
+fn thunder_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.surface_title));
+    style.set_text_font(theme::font(FontSize::Body));
+}
+
+fn fog_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.muted));
+    style.set_text_align(TextAlign::Center);
+    style.set_text_font(theme::font(FontSize::Small));
+}
@@ -0,0 +1,266 @@
+//! Background Wifi connection manager.
+//!
+//! The manager is a small state machine that is polled from the model loop.  It never blocks,
+//! connection attempts are started with the non-blocking `EspWifi::connect` and their outcome is
+//! reported back through the Wifi and IP events posted on the system event loop.  When a connection
+//! attempt fails or an established connection drops the manager backs off and tries again, the
+//! back off time doubles after every failed attempt up to `MAX_BACKOFF`.
+use crate::i18n;
+use crate::model::WifiCredentials;
+
+use anyhow::Result;
+use esp_idf_svc::{
+    eventloop::{EspSubscription, EspSystemEventLoop, System},
+    netif::IpEvent,
+    wifi::{AuthMethod, ClientConfiguration, Configuration, EspWifi, WifiEvent},
+};
+
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+use std::sync::Arc;
+use std::time::Instant;
+
+use log::{info, warn};
+
+// How long to wait for an IP address before the connection attempt is considered failed
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(20);
+const MIN_BACKOFF: Duration = Duration::from_secs(2);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// State of the Wifi connection
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WifiState {
+    Disconnected,
+    Connecting,
+    Connected,
+    /// The connection attempt failed or the connection was lost, waiting before the next attempt
+    BackingOff,
+}
+
+impl WifiState {
+    pub fn description(&self) -> &'static str {
+        match self {
+            WifiState::Disconnected => i18n::text().status_disconnected,
+            WifiState::Connecting => i18n::text().status_connecting,
+            WifiState::Connected => i18n::text().status_connected,
+            WifiState::BackingOff => i18n::text().status_backing_off,
+        }
+    }
+}
+
+/// Flags set from the system event loop callbacks and consumed by `WifiManager::poll`
+#[derive(Default)]
+struct WifiEvents {
+    disconnected: AtomicBool,
+    got_ip: AtomicBool,
+}
+
+pub struct WifiManager {
+    wifi: EspWifi<'static>,
+    state: WifiState,
+    events: Arc<WifiEvents>,
+    _wifi_subscription: EspSubscription<'static, System>,
+    _ip_subscription: EspSubscription<'static, System>,
+    attempt_started: Instant,
+    backoff_until: Instant,
+    backoff: Duration,
+    has_connected: bool,
+}
+
+impl WifiManager {
+    pub fn new(wifi: EspWifi<'static>, sys_loop: &EspSystemEventLoop) -> Result<Self> {
+        let events = Arc::new(WifiEvents::default());
+
+        let wifi_events = events.clone();
+        let wifi_subscription = sys_loop.subscribe::<WifiEvent, _>(move |event| {
+            if let WifiEvent::StaDisconnected(_) = event {
+                wifi_events.disconnected.store(true, Ordering::Relaxed);
+            }
+        })?;
+
+        let ip_events = events.clone();
+        let ip_subscription = sys_loop.subscribe::<IpEvent, _>(move |event| {
+            if let IpEvent::DhcpIpAssigned(_) = event {
+                ip_events.got_ip.store(true, Ordering::Relaxed);
+            }
+        })?;
+
+        let now = Instant::now();
+
+        Ok(Self {
+            wifi,
+            state: WifiState::Disconnected,
+            events,
+            _wifi_subscription: wifi_subscription,
+            _ip_subscription: ip_subscription,
+            attempt_started: now,
+            backoff_until: now,
+            backoff: MIN_BACKOFF,
+            has_connected: false,
+        })
+    }
+
+    /// Configures the station with the credentials and starts the Wifi driver.  The connection
+    /// itself is made by the following calls to `poll`.
+    pub fn start(&mut self, credentials: &WifiCredentials) -> Result<()> {
+        self.configure(credentials)?;
+        self.wifi.start()?;
+
+        // If you are getting broken pipe on terminal after wifi.start then USB power is probably glitching,
+        // you can try and reduce wifi TX power with the following unsafe method.  Your best option is to get
+        // a better power source for the dev board; possibly a powered hub.
+        //unsafe { esp_idf_svc::sys::esp_wifi_set_max_tx_power(34) };
+        info!("Wifi started");
+
+        self.state = WifiState::Disconnected;
+        Ok(())
+    }
+
+    /// Drops the current connection and reconnects using the new credentials.
+    pub fn set_credentials(&mut self, credentials: &WifiCredentials) -> Result<()> {
+        if let Err(e) = self.wifi.disconnect() {
+            warn!("Wifi disconnect error = {}", e);
+        }
+
+        self.configure(credentials)?;
+
+        // Give the disconnect event time to arrive before the next connection attempt
+        self.has_connected = false;
+        self.backoff = MIN_BACKOFF;
+        self.backoff_until = Instant::now() + Duration::from_secs(1);
+        self.state = WifiState::BackingOff;
+        Ok(())
+    }
+
+    pub fn state(&self) -> WifiState {
+        self.state
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.state == WifiState::Connected
+    }
+
+    /// Signal strength of the access point we are connected to
+    pub fn rssi(&self) -> Option<i8> {
+        if !self.is_connected() {
+            return None;
+        }
+
+        let mut ap_info = esp_idf_svc::sys::wifi_ap_record_t::default();
+        let result = unsafe { esp_idf_svc::sys::esp_wifi_sta_get_ap_info(&mut ap_info) };
+        if result == esp_idf_svc::sys::ESP_OK {
+            Some(ap_info.rssi)
+        } else {
+            None
+        }
+    }
+
+    /// IP address assigned to the station by DHCP
+    pub fn ip_address(&self) -> Option<String> {
+        if !self.is_connected() {
+            return None;
+        }
+
+        self.wifi
+            .sta_netif()
+            .get_ip_info()
+            .ok()
+            .map(|ip_info| ip_info.ip.to_string())
+    }
+
+    /// True once a connection has been made with the current credentials
+    pub fn has_connected(&self) -> bool {
+        self.has_connected
+    }
+
+    /// Advances the state machine, returns the new state if it changed.
+    pub fn poll(&mut self) -> Option<WifiState> {
+        let disconnected = self.events.disconnected.swap(false, Ordering::Relaxed);
+        let got_ip = self.events.got_ip.swap(false, Ordering::Relaxed);
+        let now = Instant::now();
+
+        let new_state = match self.state {
+            WifiState::Disconnected => {
+                // Any events taken above belong to the previous connection and are ignored
+                self.attempt_started = now;
+
+                match self.wifi.connect() {
+                    Ok(()) => WifiState::Connecting,
+                    Err(e) => {
+                        warn!("Wifi connect error = {}", e);
+                        self.start_backoff(now)
+                    }
+                }
+            }
+
+            WifiState::Connecting => {
+                if got_ip {
+                    info!("Wifi connected");
+                    self.has_connected = true;
+                    self.backoff = MIN_BACKOFF;
+                    WifiState::Connected
+                } else if disconnected || now - self.attempt_started > CONNECT_TIMEOUT {
+                    warn!("---------- Error connecting to Wifi ---------");
+                    let _ = self.wifi.disconnect();
+                    self.start_backoff(now)
+                } else {
+                    WifiState::Connecting
+                }
+            }
+
+            WifiState::Connected => {
+                if disconnected {
+                    warn!("Wifi connection lost");
+                    self.start_backoff(now)
+                } else {
+                    WifiState::Connected
+                }
+            }
+
+            WifiState::BackingOff => {
+                if now >= self.backoff_until {
+                    WifiState::Disconnected
+                } else {
+                    WifiState::BackingOff
+                }
+            }
+        };
+
+        if new_state != self.state {
+            self.state = new_state;
+            Some(new_state)
+        } else {
+            None
+        }
+    }
+
+    fn start_backoff(&mut self, now: Instant) -> WifiState {
+        self.backoff_until = now + self.backoff;
+        info!("Wifi retry in {} seconds", self.backoff.as_secs());
+        self.backoff = (self.backoff * 2).min(MAX_BACKOFF);
+        WifiState::BackingOff
+    }
+
+    fn configure(&mut self, credentials: &WifiCredentials) -> Result<()> {
+        let wifi_configuration: Configuration = Configuration::Client(ClientConfiguration {
+            ssid: credentials
+                .ssid
+                .as_str()
+                .try_into()
+                .map_err(|_| anyhow::anyhow!("SSID is too long"))?,
+            bssid: None,
+            auth_method: AuthMethod::WPA2Personal,
+            password: credentials
+                .pass
+                .as_str()
+                .try_into()
+                .map_err(|_| anyhow::anyhow!("Password is too long"))?,
+            channel: None,
+            ..Default::default()
+        });
+
+        self.wifi.set_configuration(&wifi_configuration)?;
+
+        Ok(())
+    }
+}
@@ -1,33 +1,45 @@
 //============================================================================
 //                           Wifi Settings Pane
 //============================================================================
+use crate::i18n;
+use crate::layout;
 use crate::lvgl_misc;
+use crate::model::ModelRequest;
+use crate::pane::{pane_navigation, Navigation, Pane, PaneId};
+use crate::theme::{self, FontSize, Palette};
+use crate::ui::UiRequest;
+use crate::ui_event::{Button, TextField, UiEvent, UiEventQueue};
+use crate::wifi_manager::WifiState;
 use cstr_core::CString;
-use lvgl::font::Font;
 use lvgl::style::Style;
 use lvgl::widgets::{Btn, Keyboard, Label, Textarea};
-use lvgl::{Align, Color, NativeObject, Obj, Part, Screen, TextAlign, Widget};
+use lvgl::{Align, NativeObject, Obj, Part, Screen, TextAlign, Widget};
 use lvgl_sys::*;
+use std::sync::mpsc::Sender;
 
 pub struct WifiSettingsPane<'a> {
     pane: Obj<'a>,
-    pub keyboard: Keyboard<'a>,
-    pub ssid_ta: Textarea<'a>,
-    pub pswd_ta: Textarea<'a>,
-    pub error_message: Label<'a>,
-    pub edit_btn: Btn<'a>,
-    pub save_btn: Btn<'a>,
-    pub exit_btn: Btn<'a>,
+    keyboard: Keyboard<'a>,
+    ssid_ta: Textarea<'a>,
+    pswd_ta: Textarea<'a>,
+    error_message: Label<'a>,
+    connection_status: Label<'a>,
+    save_btn: Btn<'a>,
     ssid: String,
     pswd: String,
+    tx: Sender<ModelRequest>,
 }
 
-impl<'a> WifiSettingsPane<'a> {
-    pub fn new(wifi_settings_pane_parent: &'a mut Screen) -> Self {
+impl<'a> Pane<'a> for WifiSettingsPane<'a> {
+    fn create(
+        wifi_settings_pane_parent: &'a mut Screen,
+        tx: &Sender<ModelRequest>,
+        events: &UiEventQueue,
+    ) -> Self {
         let mut pane = Obj::create(wifi_settings_pane_parent).unwrap();
-        pane.set_size(800, 429);
-        pane.set_align(Align::TopLeft, 0, 51);
-        pane.add_style(Part::Main, Box::leak(settings_pane_style()));
+        pane.set_size(layout::screen_width(), layout::pane_height());
+        pane.set_align(Align::TopLeft, 0, layout::nav_bar_height());
+        pane.add_style(Part::Main, theme::style(settings_pane_style));
 
         let ssid = String::from("");
         let pswd = String::from("");
@@ -35,96 +47,198 @@ impl<'a> WifiSettingsPane<'a> {
         // SSID title
         let mut label = Label::create(&mut pane).unwrap();
         label.set_align(Align::TopLeft, 70, 10);
-        label.add_style(Part::Main, Box::leak(font_12_color_white_style()));
-        let mut text = CString::new("Wifi Network Name").unwrap();
+        label.add_style(Part::Main, theme::style(column_title_style));
+        let mut text = CString::new(i18n::text().wifi_network_name).unwrap();
         label.set_text(text.as_c_str()).unwrap();
 
-        // Password title
+        // Password title, in portrait the password is below the SSID
         label = Label::create(&mut pane).unwrap();
-        label.set_align(Align::TopLeft, 260, 10);
-        label.add_style(Part::Main, Box::leak(font_12_color_white_style()));
-        text = CString::new("Password").unwrap();
+        label.set_align(Align::TopLeft, layout::pick(260, 70), layout::pick(10, 60));
+        label.add_style(Part::Main, theme::style(column_title_style));
+        text = CString::new(i18n::text().password).unwrap();
         label.set_text(text.as_c_str()).unwrap();
 
         // Row title
         label = Label::create(&mut pane).unwrap();
         label.set_align(Align::TopLeft, 10, 30);
-        label.add_style(Part::Main, Box::leak(font_12_color_yellow_style()));
-        text = CString::new("Settings").unwrap();
+        label.add_style(Part::Main, theme::style(row_title_style));
+        text = CString::new(i18n::text().settings).unwrap();
         label.set_text(text.as_c_str()).unwrap();
 
         // SSID text area
         let mut ssid_ta = Textarea::create(&mut pane).unwrap();
         let _ = ssid_ta.set_one_line(true);
         ssid_ta.set_width(160);
-        ssid_ta.add_style(Part::Main, Box::leak(text_area_style()));
+        ssid_ta.add_style(Part::Main, theme::style(text_area_style));
         ssid_ta.set_align(Align::TopLeft, 70, 30);
 
         // Password text area
         let mut pswd_ta = Textarea::create(&mut pane).unwrap();
         let _ = pswd_ta.set_one_line(true);
         pswd_ta.set_width(160);
-        pswd_ta.add_style(Part::Main, Box::leak(text_area_style()));
-        pswd_ta.set_align(Align::TopLeft, 260, 30);
+        pswd_ta.add_style(Part::Main, theme::style(text_area_style));
+        pswd_ta.set_align(Align::TopLeft, layout::pick(260, 70), layout::pick(30, 80));
 
         // Error message
         let mut error_message = Label::create(&mut pane).unwrap();
-        error_message.add_style(Part::Main, Box::leak(error_message_style()));
-        error_message.set_width(740);
+        error_message.add_style(Part::Main, theme::style(error_message_style));
+        error_message.set_width(layout::screen_width() - 60);
         error_message.set_align(Align::TopLeft, 10, 182);
         text = CString::new("").unwrap();
         error_message.set_text(text.as_c_str()).unwrap();
 
+        // Connection status
+        let mut connection_status = Label::create(&mut pane).unwrap();
+        connection_status.add_style(Part::Main, theme::style(connection_status_style));
+        connection_status.set_width(400);
+        connection_status.set_align(Align::TopLeft, 70, layout::pick(70, 130));
+        text = CString::new(i18n::fill(
+            i18n::text().status,
+            &[&WifiState::Disconnected.description()],
+        ))
+        .unwrap();
+        connection_status.set_text(text.as_c_str()).unwrap();
+
         // Edit button
         let mut edit_btn = Btn::create(&mut pane).unwrap();
         edit_btn.set_size(70, 40);
-        edit_btn.add_style(Part::Main, Box::leak(button_style()));
-        edit_btn.set_align(Align::TopRight, -50, 30);
+        edit_btn.add_style(Part::Main, theme::style(button_style));
+        edit_btn.set_align(Align::TopRight, layout::pick(-50, -20), 30);
         let mut btn_label = Label::create(&mut edit_btn).unwrap();
         btn_label.set_align(Align::Center, 0, 0);
-        text = CString::new("Edit").unwrap();
+        text = CString::new(i18n::text().edit).unwrap();
         btn_label.set_text(text.as_c_str()).unwrap();
 
         // Save button
         let mut save_btn = Btn::create(&mut pane).unwrap();
         save_btn.set_size(70, 40);
-        save_btn.add_style(Part::Main, Box::leak(button_style()));
-        save_btn.set_align(Align::TopRight, -50, 80);
+        save_btn.add_style(Part::Main, theme::style(button_style));
+        save_btn.set_align(Align::TopRight, layout::pick(-50, -20), 80);
         btn_label = Label::create(&mut save_btn).unwrap();
         btn_label.set_align(Align::Center, 0, 0);
-        text = CString::new("Save").unwrap();
+        text = CString::new(i18n::text().save).unwrap();
         btn_label.set_text(text.as_c_str()).unwrap();
 
         // Exit button
         let mut exit_btn = Btn::create(&mut pane).unwrap();
         exit_btn.set_size(70, 40);
-        exit_btn.add_style(Part::Main, Box::leak(button_style()));
-        exit_btn.set_align(Align::TopRight, -50, 130);
+        exit_btn.add_style(Part::Main, theme::style(button_style));
+        exit_btn.set_align(Align::TopRight, layout::pick(-50, -20), 130);
         btn_label = Label::create(&mut exit_btn).unwrap();
         btn_label.set_align(Align::Center, 0, 0);
-        text = CString::new("Exit").unwrap();
+        text = CString::new(i18n::text().exit).unwrap();
         btn_label.set_text(text.as_c_str()).unwrap();
 
         // Create keyboard
         let mut keyboard = Keyboard::create(&mut pane).unwrap();
-        keyboard.set_size(740, 200);
-        keyboard.set_align(Align::Center, 0, 100);
-        keyboard.add_style(Part::Main, Box::leak(keyboard_style()));
+        keyboard.set_size(layout::screen_width() - 60, 200);
+        if layout::is_portrait() {
+            keyboard.set_align(Align::BottomMid, 0, 0);
+        } else {
+            keyboard.set_align(Align::Center, 0, 100);
+        }
+        keyboard.add_style(Part::Main, theme::style(keyboard_style));
+
+        // Textarea events - the virtual keyboard is assigned to the textarea clicked
+        for (ta, text_field) in [
+            (&mut ssid_ta, TextField::WifiSsid),
+            (&mut pswd_ta, TextField::WifiPassword),
+        ] {
+            let queue = events.clone();
+            ta.on_event(move |_ta, event| {
+                if let lvgl::Event::Clicked = event {
+                    queue.push(UiEvent::TextFieldClicked(text_field));
+                }
+            })
+            .unwrap();
+        }
 
-        Self {
+        // Button clicked events
+        for (btn, button) in [
+            (&mut edit_btn, Button::Edit),
+            (&mut save_btn, Button::Save),
+            (&mut exit_btn, Button::Exit),
+        ] {
+            let queue = events.clone();
+            btn.on_event(move |_btn, event| {
+                if let lvgl::Event::Clicked = event {
+                    queue.push(UiEvent::ButtonClicked(PaneId::WifiSettings, button));
+                }
+            })
+            .unwrap();
+        }
+
+        let mut ws_pane = Self {
             pane,
             keyboard,
             ssid_ta,
             pswd_ta,
             error_message,
-            edit_btn,
+            connection_status,
             save_btn,
-            exit_btn,
             ssid,
             pswd,
+            tx: tx.clone(),
+        };
+        ws_pane.hide();
+        ws_pane
+    }
+
+    fn id(&self) -> PaneId {
+        PaneId::WifiSettings
+    }
+
+    fn nav_title(&self) -> Option<&'static str> {
+        Some(i18n::text().nav_wifi)
+    }
+
+    fn show(&mut self) {
+        self.disable_editing();
+        //self.clear_error_message();
+        lvgl_misc::show_obj(&mut self.pane);
+    }
+
+    fn hide(&mut self) {
+        self.reset_wifi_creds_entries();
+        lvgl_misc::hide_obj(&mut self.pane);
+    }
+
+    fn handle_request(&mut self, request: &UiRequest) {
+        match request {
+            UiRequest::SetWifiSettingsErrorMessage(msg) => self.set_error_message(msg.clone()),
+            UiRequest::SetWifiCreds(ssid, pass) => {
+                self.set_wifi_credentials(ssid.clone(), pass.clone())
+            }
+            UiRequest::SetWifiState(wifi_state) => {
+                self.set_connection_status(wifi_state.description())
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_event(&mut self, event: &UiEvent) -> Option<Navigation> {
+        match event {
+            UiEvent::TextFieldClicked(TextField::WifiSsid) => {
+                self.keyboard.set_textarea(&mut self.ssid_ta);
+            }
+            UiEvent::TextFieldClicked(TextField::WifiPassword) => {
+                self.keyboard.set_textarea(&mut self.pswd_ta);
+            }
+            UiEvent::ButtonClicked(PaneId::WifiSettings, Button::Edit) => self.enable_editing(),
+            UiEvent::ButtonClicked(PaneId::WifiSettings, Button::Save) => {
+                let (ssid, pass) = self.get_user_wifi_creds_entries();
+                self.tx
+                    .send(ModelRequest::UpdateWifiCreds(ssid, pass))
+                    .unwrap();
+            }
+            _ => {}
         }
+
+        pane_navigation(PaneId::WifiSettings, event)
     }
+}
 
+impl<'a> WifiSettingsPane<'a> {
     pub fn show_keyboard(&mut self) {
         unsafe {
             lv_obj_clear_flag(
@@ -143,17 +257,6 @@ impl<'a> WifiSettingsPane<'a> {
         }
     }
 
-    pub fn show(&mut self) {
-        self.disable_editing();
-        //self.clear_error_message();
-        lvgl_misc::show_obj(&mut self.pane);
-    }
-
-    pub fn hide(&mut self) {
-        self.reset_wifi_creds_entries();
-        lvgl_misc::hide_obj(&mut self.pane);
-    }
-
     pub fn disable_editing(&mut self) {
         lvgl_misc::hide_button(&mut self.save_btn);
         lvgl_misc::hide_keyboard(&mut self.keyboard);
@@ -207,6 +310,13 @@ impl<'a> WifiSettingsPane<'a> {
             .unwrap();
     }
 
+    pub fn set_connection_status(&mut self, status: &str) {
+        let status = i18n::fill(i18n::text().status, &[&status]);
+        self.connection_status
+            .set_text(CString::new(status.as_str()).unwrap().as_c_str())
+            .unwrap();
+    }
+
     pub fn clear_error_message(&mut self) {
         self.error_message
             .set_text(CString::new("").unwrap().as_c_str())
@@ -218,79 +328,57 @@ impl<'a> WifiSettingsPane<'a> {
 //                      Sytles for Wifi Settings Pane
 //*****************************************************************************
 
-fn settings_pane_style() -> Box<Style> {
-    let mut style = Style::default();
-    style.set_bg_color(Color::from_rgb((0, 0, 0))); // black
+fn settings_pane_style(style: &mut Style, palette: &Palette) {
+    style.set_bg_color(theme::color(palette.background));
     style.set_radius(0);
     style.set_border_width(0);
-    style.set_pad_top(4);
-    style.set_pad_bottom(4);
-    style.set_pad_left(12);
-    style.set_pad_right(12);
-
-    Box::new(style)
+    theme::set_pad(style, theme::PANE_PAD_TOP, theme::PANE_PAD);
 }
 
-fn text_area_style() -> Box<Style> {
-    let mut style = Style::default();
-    //style.set_bg_color(Color::from_rgb((0, 0, 0)));
+fn text_area_style(style: &mut Style, _palette: &Palette) {
     style.set_radius(0);
     style.set_border_width(0);
-    style.set_pad_top(4);
-    style.set_pad_bottom(4);
-    style.set_pad_left(4);
-    style.set_pad_right(4);
-
-    Box::new(style)
+    theme::set_pad(style, theme::WIDGET_PAD, theme::WIDGET_PAD);
 }
 
-fn keyboard_style() -> Box<Style> {
-    let mut style = Style::default();
-    style.set_text_color(Color::from_rgb((0, 0, 0))); // black
-    style.set_bg_color(Color::from_rgb((0, 0, 139))); // navy blue
+fn keyboard_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.on_accent));
+    style.set_bg_color(theme::color(palette.keyboard));
     style.set_text_align(TextAlign::Right);
-    style.set_text_font(unsafe { Font::new_raw(lv_font_montserrat_16) });
-
-    Box::new(style)
+    style.set_text_font(theme::font(FontSize::Medium));
 }
 
-fn font_12_color_white_style() -> Box<Style> {
-    let mut style = Style::default();
-    style.set_text_color(Color::from_rgb((255, 255, 255))); // white
+fn column_title_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.text));
     style.set_text_align(TextAlign::Center);
-    style.set_text_font(unsafe { Font::new_raw(lv_font_montserrat_12) });
-
-    Box::new(style)
+    style.set_text_font(theme::font(FontSize::Small));
 }
 
-fn font_12_color_yellow_style() -> Box<Style> {
-    let mut style = Style::default();
-    style.set_text_color(Color::from_rgb((255, 215, 0))); // gold
+fn row_title_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.highlight));
     style.set_text_align(TextAlign::Center);
-    style.set_text_font(unsafe { Font::new_raw(lv_font_montserrat_12) });
+    style.set_text_font(theme::font(FontSize::Small));
+}
 
-    Box::new(style)
+fn connection_status_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.text));
+    style.set_text_align(TextAlign::Left);
+    style.set_text_font(theme::font(FontSize::Small));
 }
 
-fn button_style() -> Box<Style> {
-    let mut style = Style::default();
-    //style.set_text_color(Color::from_rgb((255, 255, 255))); // white
-    style.set_text_color(Color::from_rgb((0, 0, 0))); // black
+fn button_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.on_accent));
     style.set_text_align(TextAlign::Center);
-    style.set_bg_color(Color::from_rgb((30, 144, 255))); // dodger blue 1
-    style.set_radius(6);
-    style.set_border_width(0);
+    style.set_bg_color(theme::color(palette.accent));
+    style.set_radius(theme::BUTTON_RADIUS);
+    style.set_border_color(theme::color(palette.outline));
+    style.set_border_width(palette.outline_width);
     style.set_shadow_width(0);
-    style.set_text_font(unsafe { Font::new_raw(lv_font_montserrat_12) });
-
-    Box::new(style)
+    style.set_text_font(theme::font(FontSize::Small));
 }
 
-fn error_message_style() -> Box<Style> {
-    let mut style = Style::default();
-    style.set_text_color(Color::from_rgb((255, 0, 0))); // red
+fn error_message_style(style: &mut Style, palette: &Palette) {
+    style.set_text_color(theme::color(palette.error));
     style.set_text_align(TextAlign::Left);
-    style.set_text_font(unsafe { Font::new_raw(lv_font_montserrat_14) });
-
-    Box::new(style)
+    style.set_text_font(theme::font(FontSize::Body));
 }
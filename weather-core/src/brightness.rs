@@ -0,0 +1,99 @@
+//! Brightness of the backlight.
+//!
+//! The level of the backlight for the time since the last touch and how far into the night it is
+//! in the home city, see backlight.rs.  Percents are turned into duties with a gamma curve so
+//! equal steps of the slider look like equal steps of brightness.
+use crate::settings::Settings;
+
+use core::time::Duration;
+
+// Perceived brightness is about the duty to the power 1 / GAMMA
+const GAMMA: f32 = 2.2;
+
+// How long a touch wakes the screen at night when the screen is never dimmed by day
+const NIGHT_WAKE: Duration = Duration::from_secs(60);
+
+/// Brightness in percent after `idle` without a touch, `night_part` goes from 0 by day to 1 by
+/// night
+pub fn backlight_level(settings: &Settings, idle: Duration, night_part: f32) -> u8 {
+    if is_awake(settings, idle, night_part) {
+        return settings.brightness;
+    }
+
+    // Without dimming the screen stays at the brightness setting by day
+    let day_level = match settings.dim_after_min {
+        0 => settings.brightness,
+        _ => settings.dim_brightness,
+    } as f32;
+    let night_level = settings.night_brightness as f32;
+    let night_part = night_part.clamp(0.0, 1.0);
+
+    (day_level + (night_level - day_level) * night_part).round() as u8
+}
+
+/// Duty of a brightness in percent, a brightness above 0 never turns the backlight off
+pub fn gamma_duty(percent: u8, max_duty: u32) -> u32 {
+    if percent == 0 {
+        return 0;
+    }
+
+    let level = (percent.min(100) as f32 / 100.0).powf(GAMMA);
+    ((level * max_duty as f32).round() as u32).clamp(1, max_duty)
+}
+
+/// Whether the backlight is at the brightness setting, a touch only wakes the screen otherwise
+pub fn is_awake(settings: &Settings, idle: Duration, night_part: f32) -> bool {
+    let dim_after = match settings.dim_after_min {
+        0 if night_part > 0.0 => NIGHT_WAKE,
+        0 => return true,
+        minutes => Duration::from_secs(minutes as u64 * 60),
+    };
+
+    idle < dim_after
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn minutes(minutes: u64) -> Duration {
+        Duration::from_secs(minutes * 60)
+    }
+
+    #[test]
+    fn idle_level_is_ramped_between_day_and_night() {
+        // 100% in use, 30% dimmed after 10 minutes and 10% at night
+        let settings = Settings::default();
+
+        assert_eq!(backlight_level(&settings, minutes(0), 1.0), 100);
+        assert_eq!(backlight_level(&settings, minutes(11), 0.0), 30);
+        assert_eq!(backlight_level(&settings, minutes(11), 0.5), 20);
+        assert_eq!(backlight_level(&settings, minutes(11), 1.0), 10);
+        assert_eq!(backlight_level(&settings, minutes(11), 2.0), 10);
+    }
+
+    #[test]
+    fn screen_never_dimmed_by_day_is_dimmed_at_night() {
+        let settings = Settings {
+            dim_after_min: 0,
+            ..Settings::default()
+        };
+
+        assert_eq!(backlight_level(&settings, minutes(600), 0.0), 100);
+        assert!(is_awake(&settings, minutes(600), 0.0));
+
+        // A touch wakes the screen for a minute at night
+        assert!(is_awake(&settings, Duration::from_secs(30), 1.0));
+        assert_eq!(backlight_level(&settings, minutes(2), 0.5), 55);
+        assert_eq!(backlight_level(&settings, minutes(2), 1.0), 10);
+    }
+
+    #[test]
+    fn duties_follow_the_gamma_curve() {
+        assert_eq!(gamma_duty(0, 1023), 0);
+        assert_eq!(gamma_duty(1, 1023), 1);
+        assert_eq!(gamma_duty(50, 1023), 223);
+        assert_eq!(gamma_duty(100, 1023), 1023);
+        assert_eq!(gamma_duty(150, 1023), 1023);
+    }
+}
@@ -0,0 +1,110 @@
+//! The cities of the App and their current conditions.
+//!
+//! The cities settings are kept in CITIES.TXT and edited on the Cities pane or with the web API,
+//! both check them with `check_cities_info` before they are sent to the model.  The conditions
+//! are the numbers of the last forecast, in the units of weatherapi, published over MQTT, logged
+//! to the history and served by the web API.
+use crate::i18n::{self, Messages};
+
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CityInfo {
+    pub city_name: String,
+    pub state: String,
+    pub zipcode: String,
+    pub timezone: String,
+}
+
+/// Current conditions of a city as numbers, published over MQTT and logged to the history
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CityConditions {
+    pub temp_f: f64,
+    pub feels_like_f: f64,
+    pub humidity: i32,
+    pub pressure_mb: f64,
+    pub wind_mph: f64,
+    pub wind_gust_mph: f64,
+    pub wind_dir: String,
+    pub uv: f64,
+    pub aqi: u16,
+    pub condition: String,
+    pub last_updated_epoch: i64,
+}
+
+// Check the cities settings before they are used, returns the error message in the language of
+// `text`, the one of the locale for the Cities pane and English for the web API
+pub fn check_cities_info(cities_info: &[CityInfo], text: &Messages) -> Result<(), String> {
+    for (i, city) in cities_info.iter().enumerate() {
+        let zipcode = city.zipcode.as_str();
+        is_zipcode_valid(zipcode, text)
+            .map_err(|e| i18n::fill(text.city_error, &[&(i + 1), &e]))?;
+
+        city.timezone.parse::<Tz>().map_err(|_| {
+            let error = i18n::fill(text.timezone_not_valid, &[&city.timezone]);
+            i18n::fill(text.city_error, &[&(i + 1), &error])
+        })?;
+    }
+
+    Ok(())
+}
+
+fn is_zipcode_valid(city_zipcode: &str, text: &Messages) -> Result<(), String> {
+    // 1st check if zipcode is a number
+    // 2nd check if zipcode has 5 digits
+    // 3rd check if zipcode is within zipcode numbers assigned by USPS
+    let zipcode_vec: Vec<char> = city_zipcode.chars().collect();
+    if let Ok(zipcode) = city_zipcode.to_string().parse::<u32>() {
+        if zipcode_vec.len() == 5 {
+            if let 501..=99950 = zipcode {
+                Ok(())
+            } else {
+                Err(text.zipcode_range.to_string())
+            }
+        } else {
+            Err(text.zipcode_digits.to_string())
+        }
+    } else {
+        Err(text.zipcode_not_number.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::i18n::Locale;
+
+    fn city(zipcode: &str, timezone: &str) -> CityInfo {
+        CityInfo {
+            city_name: "Denver".to_string(),
+            state: "CO".to_string(),
+            zipcode: zipcode.to_string(),
+            timezone: timezone.to_string(),
+        }
+    }
+
+    #[test]
+    fn cities_errors_are_in_the_language_given() {
+        let english = &Locale::English.catalog().text;
+        let spanish = &Locale::Spanish.catalog().text;
+        let good = city("80264", "US/Mountain");
+
+        assert_eq!(
+            check_cities_info(std::slice::from_ref(&good), english),
+            Ok(())
+        );
+        assert_eq!(
+            check_cities_info(&[good.clone(), city("8026", "US/Mountain")], english),
+            Err("City 2 - Zipcode must be 5 digits".to_string())
+        );
+        assert_eq!(
+            check_cities_info(&[city("00100", "US/Mountain")], spanish),
+            Err("Ciudad 1 - El codigo postal debe ser de 00501 a 99950".to_string())
+        );
+        assert_eq!(
+            check_cities_info(&[good, city("80264", "Mars/Base")], english),
+            Err("City 2 - Timezone Mars/Base is not valid".to_string())
+        );
+    }
+}
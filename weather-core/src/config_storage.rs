@@ -0,0 +1,385 @@
+//! Where the settings files are kept.
+//!
+//! The model only sees the `ConfigStorage` trait so it does not depend on the SDCard.  There are
+//! three implementations:
+//! - `FileStore`, the SDCard
+//! - `FlashStorage`, the `app_storage` FAT partition in the internal flash, used when there is no
+//!   SDCard
+//! - `MemoryStorage`, files kept in RAM, used when neither can be mounted and for host testing
+//!
+//! `RemovableStorage` puts the SDCard in front of one of the others, it uses the SDCard while a
+//! card is inserted and reports when the card is inserted or removed.  It and `FlashStorage` need
+//! the ESP-IDF so they are in device_storage.rs of the App.
+//!
+//! File names are 8.3 names in the root directory and are not case sensitive.
+use crate::file_store::FileStore;
+
+use anyhow::{bail, Result};
+use embedded_sdmmc::BlockDevice;
+
+use std::collections::{BTreeMap, HashMap};
+
+pub trait ConfigStorage {
+    /// Name of the storage shown in the logs
+    fn name(&self) -> &'static str;
+
+    fn read_lines(&mut self, file_name: &str) -> Result<Vec<String>>;
+
+    /// Reads the whole file as UTF-8 text, files larger than `limit` bytes are an error
+    fn read_to_string(&mut self, file_name: &str, limit: usize) -> Result<String>;
+
+    /// Replaces the contents of the file, a power cut leaves either the old or the new contents
+    /// once `recover` has run
+    fn write_atomic(&mut self, file_name: &str, contents: &[u8]) -> Result<()>;
+
+    /// Finishes or undoes an interrupted `write_atomic`, call it before reading the file
+    fn recover(&mut self, file_name: &str) -> Result<()>;
+
+    /// Called once the system clock has been synced so files can be dated
+    fn set_clock_synced(&mut self) {}
+
+    /// Appends to a file in a directory, both are created when missing and `header` is written
+    /// first to a new file.  Only the SDCard has room for directories of logs.
+    fn append_in_dir(
+        &mut self,
+        _dir_name: &str,
+        _file_name: &str,
+        _header: &[u8],
+        _contents: &[u8],
+    ) -> Result<()> {
+        bail!("{} has no directories", self.name())
+    }
+
+    /// Names of the files in a directory, a missing directory has none
+    fn list_dir(&mut self, _dir_name: &str) -> Result<Vec<String>> {
+        bail!("{} has no directories", self.name())
+    }
+
+    fn delete_in_dir(&mut self, _dir_name: &str, _file_name: &str) -> Result<()> {
+        bail!("{} has no directories", self.name())
+    }
+
+    /// Size of a file in a directory, None when either is missing
+    fn file_size_in_dir(&mut self, _dir_name: &str, _file_name: &str) -> Result<Option<u32>> {
+        bail!("{} has no directories", self.name())
+    }
+
+    /// Calls `f` with each line of a file in a directory, the file is read a line at a time
+    fn for_each_line_in_dir(
+        &mut self,
+        _dir_name: &str,
+        _file_name: &str,
+        _f: &mut dyn FnMut(&str),
+    ) -> Result<()> {
+        bail!("{} has no directories", self.name())
+    }
+
+    /// Checks for a change of the storage media, removable storage only
+    fn check_media(&mut self) -> Option<MediaChange> {
+        None
+    }
+
+    /// Why the preferred storage is not used, shown to the user
+    fn notice(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Change of the storage media reported by `ConfigStorage::check_media`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MediaChange {
+    Inserted,
+    Removed(String),
+}
+
+//============================================================================
+//                              SDCard
+//============================================================================
+impl<D: BlockDevice> ConfigStorage for FileStore<D> {
+    fn name(&self) -> &'static str {
+        "SDCard"
+    }
+
+    fn read_lines(&mut self, file_name: &str) -> Result<Vec<String>> {
+        self.read_lines_from_file(file_name)
+    }
+
+    fn read_to_string(&mut self, file_name: &str, limit: usize) -> Result<String> {
+        FileStore::read_to_string(self, file_name, limit)
+    }
+
+    fn write_atomic(&mut self, file_name: &str, contents: &[u8]) -> Result<()> {
+        self.write_lines_to_file(file_name, contents)
+    }
+
+    fn recover(&mut self, file_name: &str) -> Result<()> {
+        self.recover_file(file_name)
+    }
+
+    fn set_clock_synced(&mut self) {
+        FileStore::set_clock_synced(self);
+    }
+
+    fn append_in_dir(
+        &mut self,
+        dir_name: &str,
+        file_name: &str,
+        header: &[u8],
+        contents: &[u8],
+    ) -> Result<()> {
+        self.append_to_file_in_dir(dir_name, file_name, header, contents)
+    }
+
+    fn list_dir(&mut self, dir_name: &str) -> Result<Vec<String>> {
+        FileStore::list_dir(self, dir_name)
+    }
+
+    fn delete_in_dir(&mut self, dir_name: &str, file_name: &str) -> Result<()> {
+        self.delete_file_in_dir(dir_name, file_name)
+    }
+
+    fn file_size_in_dir(&mut self, dir_name: &str, file_name: &str) -> Result<Option<u32>> {
+        FileStore::file_size_in_dir(self, dir_name, file_name)
+    }
+
+    fn for_each_line_in_dir(
+        &mut self,
+        dir_name: &str,
+        file_name: &str,
+        f: &mut dyn FnMut(&str),
+    ) -> Result<()> {
+        for line in self.lines_in_dir(dir_name, file_name)? {
+            f(&line?);
+        }
+
+        Ok(())
+    }
+}
+
+//============================================================================
+//                              Memory
+//============================================================================
+// Files of the directories by directory name, listed in name order
+type MemoryDirs = BTreeMap<String, BTreeMap<String, Vec<u8>>>;
+
+/// Files kept in RAM, they are lost at restart.  The device keeps only the settings files in RAM,
+/// the directories of the history are kept by `with_directories` for the tests.
+#[derive(Default)]
+pub struct MemoryStorage {
+    files: HashMap<String, Vec<u8>>,
+    dirs: Option<MemoryDirs>,
+}
+
+impl MemoryStorage {
+    pub fn with_directories() -> Self {
+        Self {
+            dirs: Some(MemoryDirs::new()),
+            ..Self::default()
+        }
+    }
+
+    fn get(&self, file_name: &str) -> Result<&Vec<u8>> {
+        match self.files.get(&file_name.to_uppercase()) {
+            Some(contents) => Ok(contents),
+            None => bail!("{} not found", file_name),
+        }
+    }
+
+    fn dirs(&mut self) -> Result<&mut MemoryDirs> {
+        match &mut self.dirs {
+            Some(dirs) => Ok(dirs),
+            None => bail!("Memory has no directories"),
+        }
+    }
+
+    fn get_in_dir(&mut self, dir_name: &str, file_name: &str) -> Result<Option<&Vec<u8>>> {
+        Ok(self
+            .dirs()?
+            .get(&dir_name.to_uppercase())
+            .and_then(|dir| dir.get(&file_name.to_uppercase())))
+    }
+}
+
+impl ConfigStorage for MemoryStorage {
+    fn name(&self) -> &'static str {
+        "Memory"
+    }
+
+    fn read_lines(&mut self, file_name: &str) -> Result<Vec<String>> {
+        let text = std::str::from_utf8(self.get(file_name)?)?;
+        Ok(text.lines().map(String::from).collect())
+    }
+
+    fn read_to_string(&mut self, file_name: &str, limit: usize) -> Result<String> {
+        let contents = self.get(file_name)?;
+        if contents.len() > limit {
+            bail!(
+                "{} is {} bytes, the limit is {} bytes",
+                file_name,
+                contents.len(),
+                limit
+            );
+        }
+
+        Ok(String::from_utf8(contents.clone())?)
+    }
+
+    fn write_atomic(&mut self, file_name: &str, contents: &[u8]) -> Result<()> {
+        self.files
+            .insert(file_name.to_uppercase(), contents.to_vec());
+        Ok(())
+    }
+
+    fn recover(&mut self, _file_name: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn append_in_dir(
+        &mut self,
+        dir_name: &str,
+        file_name: &str,
+        header: &[u8],
+        contents: &[u8],
+    ) -> Result<()> {
+        let file = self
+            .dirs()?
+            .entry(dir_name.to_uppercase())
+            .or_default()
+            .entry(file_name.to_uppercase())
+            .or_insert_with(|| header.to_vec());
+        file.extend_from_slice(contents);
+        Ok(())
+    }
+
+    fn list_dir(&mut self, dir_name: &str) -> Result<Vec<String>> {
+        Ok(self
+            .dirs()?
+            .get(&dir_name.to_uppercase())
+            .map(|dir| dir.keys().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    fn delete_in_dir(&mut self, dir_name: &str, file_name: &str) -> Result<()> {
+        let removed = self
+            .dirs()?
+            .get_mut(&dir_name.to_uppercase())
+            .and_then(|dir| dir.remove(&file_name.to_uppercase()));
+        match removed {
+            Some(_) => Ok(()),
+            None => bail!("{} not found", file_name),
+        }
+    }
+
+    fn file_size_in_dir(&mut self, dir_name: &str, file_name: &str) -> Result<Option<u32>> {
+        Ok(self
+            .get_in_dir(dir_name, file_name)?
+            .map(|contents| contents.len() as u32))
+    }
+
+    fn for_each_line_in_dir(
+        &mut self,
+        dir_name: &str,
+        file_name: &str,
+        f: &mut dyn FnMut(&str),
+    ) -> Result<()> {
+        match self.get_in_dir(dir_name, file_name)? {
+            Some(contents) => {
+                std::str::from_utf8(contents)?.lines().for_each(f);
+                Ok(())
+            }
+            None => bail!("{} not found", file_name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::{HistoryConfig, HISTORY_DIR};
+    use crate::settings::{Settings, ThemeName};
+
+    #[test]
+    fn settings_are_saved_and_read_again() {
+        let mut storage = MemoryStorage::default();
+        assert!(storage.read_lines("settings.txt").is_err());
+
+        let settings = Settings {
+            theme: ThemeName::Light,
+            brightness: 60,
+            home_city: 2,
+            ..Settings::default()
+        };
+        storage
+            .write_atomic("settings.txt", settings.to_lines().as_bytes())
+            .unwrap();
+        storage.recover("SETTINGS.TXT").unwrap();
+
+        let lines = storage.read_lines("SETTINGS.TXT").unwrap();
+        assert_eq!(Settings::from_lines(&lines), Ok(settings));
+        assert!(storage.read_to_string("settings.txt", 10).is_err());
+    }
+
+    #[test]
+    fn history_settings_are_read() {
+        let mut storage = MemoryStorage::default();
+        storage
+            .write_atomic("history.txt", b"retention_days=30\n")
+            .unwrap();
+
+        let lines = storage.read_lines("history.txt").unwrap();
+        assert_eq!(
+            HistoryConfig::from_lines(&lines),
+            Ok(HistoryConfig { retention_days: 30 })
+        );
+    }
+
+    #[test]
+    fn files_in_directories() {
+        let mut storage = MemoryStorage::default();
+        assert!(storage.list_dir(HISTORY_DIR).is_err());
+
+        let mut storage = MemoryStorage::with_directories();
+        assert!(storage.list_dir(HISTORY_DIR).unwrap().is_empty());
+        assert_eq!(
+            storage
+                .file_size_in_dir(HISTORY_DIR, "20261019.CSV")
+                .unwrap(),
+            None
+        );
+
+        storage
+            .append_in_dir(HISTORY_DIR, "20261019.csv", b"header\n", b"row 1\n")
+            .unwrap();
+        storage
+            .append_in_dir(HISTORY_DIR, "20261019.CSV", b"header\n", b"row 2\n")
+            .unwrap();
+        storage
+            .append_in_dir(HISTORY_DIR, "20261018.CSV", b"header\n", b"row 0\n")
+            .unwrap();
+        assert_eq!(
+            storage.list_dir("history").unwrap(),
+            vec!["20261018.CSV".to_string(), "20261019.CSV".to_string()]
+        );
+        assert_eq!(
+            storage
+                .file_size_in_dir(HISTORY_DIR, "20261019.CSV")
+                .unwrap(),
+            Some(19)
+        );
+
+        let mut lines = Vec::new();
+        storage
+            .for_each_line_in_dir(HISTORY_DIR, "20261019.CSV", &mut |line| {
+                lines.push(line.to_string())
+            })
+            .unwrap();
+        assert_eq!(lines, vec!["header", "row 1", "row 2"]);
+
+        storage.delete_in_dir(HISTORY_DIR, "20261018.CSV").unwrap();
+        assert!(storage.delete_in_dir(HISTORY_DIR, "20261018.CSV").is_err());
+        assert_eq!(storage.list_dir(HISTORY_DIR).unwrap().len(), 1);
+
+        // The root directory files are apart from the directories
+        assert!(storage.read_lines("20261019.CSV").is_err());
+    }
+}
@@ -0,0 +1,103 @@
+//! Formats of the times and dates shown by the panes.
+//!
+//! The settings choose a 12 or 24 hour clock, whether the clocks show the seconds and the order
+//! of the dates, US (10/19/26), ISO (2026-10-19) or European (19/10/26).  The formats are chrono
+//! formats, the weekday and month names are put in by `i18n::localize`.
+use crate::key_value;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DateOrder {
+    #[default]
+    Us,
+    Iso,
+    Eu,
+}
+
+impl DateOrder {
+    pub const ALL: [DateOrder; 3] = [DateOrder::Us, DateOrder::Iso, DateOrder::Eu];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DateOrder::Us => "us",
+            DateOrder::Iso => "iso",
+            DateOrder::Eu => "eu",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        key_value::parse_named(&Self::ALL, Self::as_str, "a date order", value)
+    }
+
+    /// Title of the Display pane button
+    pub fn title(&self) -> &'static str {
+        match self {
+            DateOrder::Us => "US",
+            DateOrder::Iso => "ISO",
+            DateOrder::Eu => "EU",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DateFormat {
+    pub time_24h: bool,
+    pub show_seconds: bool,
+    pub date_order: DateOrder,
+}
+
+impl DateFormat {
+    /// Time of the city clocks
+    pub fn time(&self) -> String {
+        match (self.time_24h, self.show_seconds) {
+            (false, false) => "%I:%M%P",
+            (false, true) => "%I:%M:%S%P",
+            (true, false) => "%H:%M",
+            (true, true) => "%H:%M:%S",
+        }
+        .to_string()
+    }
+
+    /// Date of the city clocks
+    pub fn date(&self) -> String {
+        match self.date_order {
+            DateOrder::Us => "%a %b %d %Y",
+            DateOrder::Iso => "%a %Y-%m-%d",
+            DateOrder::Eu => "%a %d %b %Y",
+        }
+        .to_string()
+    }
+
+    /// Weekday and day of the forecasts
+    pub fn day(&self) -> String {
+        match self.date_order {
+            DateOrder::Us => "%a %-m/%-d",
+            DateOrder::Iso => "%a %m-%d",
+            DateOrder::Eu => "%a %-d/%-m",
+        }
+        .to_string()
+    }
+
+    /// Time span of the history charts
+    pub fn date_time(&self) -> String {
+        format!("{} {}", self.day(), self.short_time())
+    }
+
+    /// When the forecast was updated
+    pub fn stamp(&self) -> String {
+        let date = match self.date_order {
+            DateOrder::Us => "%m/%d/%y",
+            DateOrder::Iso => "%Y-%m-%d",
+            DateOrder::Eu => "%d/%m/%y",
+        };
+        format!("{} {}", date, self.short_time())
+    }
+
+    // The time without the seconds
+    fn short_time(&self) -> &'static str {
+        if self.time_24h {
+            "%H:%M"
+        } else {
+            "%I:%M%P"
+        }
+    }
+}
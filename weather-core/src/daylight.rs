@@ -0,0 +1,172 @@
+//! Day and night of a city.
+//!
+//! weatherapi.com gives the sunrise and the sunset of each forecast day as local times like
+//! "06:42 AM", and "No sunrise" or "No sunset" near the poles.  `SunTimes` keeps them with the
+//! timezone of the city so the App can tell whether it is night there, whatever the timezone of
+//! the device.  Without a forecast the night is the fixed window of the settings.
+//!
+//! The backlight and the theme don't switch at sunset, they are ramped over `TWILIGHT` around
+//! sunset and sunrise with `night_part`.
+use chrono::{DateTime, NaiveTime, Timelike, Utc};
+use chrono_tz::Tz;
+
+/// How long the ramp between day and night lasts, centered on sunset and sunrise
+pub const TWILIGHT_MIN: i32 = 40;
+
+const MINUTES_PER_DAY: i32 = 24 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SunTimes {
+    pub sunrise: NaiveTime,
+    pub sunset: NaiveTime,
+    pub timezone: Tz,
+}
+
+impl SunTimes {
+    /// From the astro times of a forecast day, None when the sun doesn't rise or set that day
+    pub fn from_astro(sunrise: &str, sunset: &str, timezone: Tz) -> Option<Self> {
+        Some(Self {
+            sunrise: parse_astro_time(sunrise)?,
+            sunset: parse_astro_time(sunset)?,
+            timezone,
+        })
+    }
+
+    /// The night of a fixed schedule, from `night_start` to `night_end` in the city
+    pub fn fixed(night_start: NaiveTime, night_end: NaiveTime, timezone: Tz) -> Self {
+        Self {
+            sunrise: night_end,
+            sunset: night_start,
+            timezone,
+        }
+    }
+
+    /// How far into the night `now` is in the city, 0 by day, 1 by night and in between during
+    /// the twilight around sunset and sunrise
+    pub fn night_part(&self, now: &DateTime<Utc>) -> f32 {
+        let local_time = now.with_timezone(&self.timezone).time();
+        night_part(self.sunrise, self.sunset, local_time)
+    }
+}
+
+/// How far into the night `local_time` is, the night from `sunset` to `sunrise` may wrap around
+/// midnight and there is no night when they are equal
+pub fn night_part(sunrise: NaiveTime, sunset: NaiveTime, local_time: NaiveTime) -> f32 {
+    if sunrise == sunset {
+        return 0.0;
+    }
+
+    let half_twilight = TWILIGHT_MIN as f32 / 2.0;
+
+    // Minutes after sunset and after sunrise, negative before
+    let from_sunset = minutes_between(sunset, local_time) as f32;
+    let from_sunrise = minutes_between(sunrise, local_time) as f32;
+
+    if from_sunset.abs() < half_twilight {
+        0.5 + from_sunset / TWILIGHT_MIN as f32
+    } else if from_sunrise.abs() < half_twilight {
+        0.5 - from_sunrise / TWILIGHT_MIN as f32
+    } else if is_between(sunset, sunrise, local_time) {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+// Minutes from `from` to `to` the shortest way around the clock
+fn minutes_between(from: NaiveTime, to: NaiveTime) -> i32 {
+    let minutes = |time: NaiveTime| (time.num_seconds_from_midnight() / 60) as i32;
+    let difference = (minutes(to) - minutes(from)).rem_euclid(MINUTES_PER_DAY);
+    if difference > MINUTES_PER_DAY / 2 {
+        difference - MINUTES_PER_DAY
+    } else {
+        difference
+    }
+}
+
+// Whether `time` is in the window from `start` to `end`, the window may wrap around midnight
+fn is_between(start: NaiveTime, end: NaiveTime, time: NaiveTime) -> bool {
+    if start <= end {
+        time >= start && time < end
+    } else {
+        time >= start || time < end
+    }
+}
+
+fn parse_astro_time(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value.trim(), "%I:%M %p").ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(text: &str) -> NaiveTime {
+        NaiveTime::parse_from_str(text, "%H:%M").unwrap()
+    }
+
+    #[test]
+    fn night_is_ramped_around_sunset() {
+        let (sunrise, sunset) = (time("06:00"), time("18:00"));
+        let night_part = |local_time| night_part(sunrise, sunset, time(local_time));
+
+        assert_eq!(night_part("12:00"), 0.0);
+        assert_eq!(night_part("17:40"), 0.0);
+        assert_eq!(night_part("17:50"), 0.25);
+        assert_eq!(night_part("18:00"), 0.5);
+        assert_eq!(night_part("18:10"), 0.75);
+        assert_eq!(night_part("18:20"), 1.0);
+        assert_eq!(night_part("00:00"), 1.0);
+    }
+
+    #[test]
+    fn night_is_ramped_around_sunrise() {
+        let (sunrise, sunset) = (time("06:00"), time("18:00"));
+        let night_part = |local_time| night_part(sunrise, sunset, time(local_time));
+
+        assert_eq!(night_part("05:40"), 1.0);
+        assert_eq!(night_part("05:50"), 0.75);
+        assert_eq!(night_part("06:00"), 0.5);
+        assert_eq!(night_part("06:10"), 0.25);
+        assert_eq!(night_part("06:20"), 0.0);
+    }
+
+    #[test]
+    fn twilight_wraps_around_midnight() {
+        // Sunset just before midnight, the ramp goes on after midnight
+        let night_part_late =
+            |local_time| night_part(time("07:00"), time("23:50"), time(local_time));
+        assert_eq!(night_part_late("23:40"), 0.25);
+        assert_eq!(night_part_late("00:05"), 0.875);
+        assert_eq!(night_part_late("00:10"), 1.0);
+        assert_eq!(night_part_late("12:00"), 0.0);
+
+        // Sunrise just after midnight, the ramp starts before midnight
+        let night_part_early =
+            |local_time| night_part(time("00:10"), time("20:00"), time(local_time));
+        assert_eq!(night_part_early("23:45"), 1.0);
+        assert_eq!(night_part_early("23:55"), 0.875);
+        assert_eq!(night_part_early("00:20"), 0.25);
+        assert_eq!(night_part_early("01:00"), 0.0);
+
+        // A fixed window over midnight, no night when it is empty
+        let fixed = SunTimes::fixed(time("22:00"), time("07:00"), chrono_tz::US::Pacific);
+        assert_eq!(night_part(fixed.sunrise, fixed.sunset, time("02:00")), 1.0);
+        assert_eq!(night_part(time("07:00"), time("07:00"), time("02:00")), 0.0);
+    }
+
+    #[test]
+    fn sun_times_are_local_to_the_city() {
+        let sun_times =
+            SunTimes::from_astro("06:42 AM", "06:20 PM", chrono_tz::US::Pacific).unwrap();
+        assert_eq!(sun_times.sunrise, time("06:42"));
+        assert_eq!(sun_times.sunset, time("18:20"));
+        assert!(SunTimes::from_astro("No sunrise", "06:20 PM", chrono_tz::US::Pacific).is_none());
+
+        // 01:20 UTC is 18:20 PDT the day before
+        let now = DateTime::parse_from_rfc3339("2026-10-20T01:20:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(sun_times.night_part(&now), 0.5);
+    }
+}
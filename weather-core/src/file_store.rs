@@ -0,0 +1,1013 @@
+use anyhow::Result;
+
+use embedded_sdmmc::{
+    BlockDevice, Error, Mode, RawDirectory, RawFile, RawVolume, VolumeIdx, VolumeManager,
+};
+use embedded_sdmmc::{SdCard, TimeSource, Timestamp};
+
+use embedded_hal::{delay::DelayNs, digital::OutputPin, spi::SpiDevice};
+
+use chrono::{DateTime, Datelike, Timelike, Utc};
+
+use core::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use log::warn;
+
+// Size of the chunks files are read in
+const READ_CHUNK_SIZE: usize = 512;
+
+// Longest line returned by the line reader, longer lines are an error
+pub const MAX_LINE_LENGTH: usize = 1024;
+
+// Largest file written with `write_lines_to_file`
+pub const MAX_ATOMIC_FILE_SIZE: usize = 16 * 1024;
+
+// Size of the footer holding the length and the CRC-32 of the .TMP and .BAK files
+const FOOTER_SIZE: usize = 8;
+
+/// FAT timestamps from the system clock.  Until the clock has been synced the files are dated
+/// with the FAT epoch, 1980-01-01 00:00:00.
+/// The timestamps are in UTC since the cities can be in different timezones.
+#[derive(Clone, Default)]
+pub struct SdMmcClock {
+    synced: Arc<AtomicBool>,
+}
+
+impl TimeSource for SdMmcClock {
+    fn get_timestamp(&self) -> Timestamp {
+        if self.synced.load(Ordering::Relaxed) {
+            timestamp_from_datetime(&Utc::now())
+        } else {
+            FAT_EPOCH
+        }
+    }
+}
+
+const FAT_EPOCH: Timestamp = Timestamp {
+    year_since_1970: 10,
+    zero_indexed_month: 0,
+    zero_indexed_day: 0,
+    hours: 0,
+    minutes: 0,
+    seconds: 0,
+};
+
+/// FAT timestamp of the date and time, dates FAT can't hold are clamped to 1980 and 2107
+pub fn timestamp_from_datetime(datetime: &DateTime<Utc>) -> Timestamp {
+    let year = datetime.year();
+    if year < 1980 {
+        return FAT_EPOCH;
+    }
+
+    Timestamp {
+        year_since_1970: (year - 1970).min(137) as u8,
+        zero_indexed_month: datetime.month0() as u8,
+        zero_indexed_day: datetime.day0() as u8,
+        hours: datetime.hour() as u8,
+        minutes: datetime.minute() as u8,
+        seconds: datetime.second() as u8,
+    }
+}
+
+/// Files in the root directory of the first FAT volume of a block device, the SDCard on the device.
+/// Being generic over the block device lets the file handling run against an in-memory disk image.
+pub struct FileStore<D: BlockDevice> {
+    pub volume_mgr: VolumeManager<D, SdMmcClock>,
+    clock_synced: Arc<AtomicBool>,
+}
+
+/// The SDCard itself, `sd_card::init` of the App makes one on the SPI bus of the device
+impl<SPI, CS, DELAYER> FileStore<SdCard<SPI, CS, DELAYER>>
+where
+    SPI: SpiDevice<u8>,
+    CS: OutputPin,
+    DELAYER: DelayNs,
+{
+    /// Checks that a card is inserted and holds a FAT volume.  The card is initialized again every
+    /// time so a card that was removed and inserted again is picked up.
+    pub fn probe_card(&mut self) -> Result<()> {
+        let sdcard = self.volume_mgr.device();
+        sdcard.mark_card_uninit();
+        sdcard
+            .num_bytes()
+            .map_err(|e| anyhow::anyhow!("SdCard error: {:?}", e))?;
+
+        let volume = self
+            .volume_mgr
+            .open_raw_volume(VolumeIdx(0))
+            .map_err(|e| anyhow::anyhow!("SdCard error: {:?}", e))?;
+        let _ = self.volume_mgr.close_volume(volume);
+
+        Ok(())
+    }
+
+    /// Checks that the card that was probed is still there
+    pub fn is_card_present(&mut self) -> bool {
+        self.volume_mgr.device().num_bytes().is_ok()
+    }
+}
+
+impl<D: BlockDevice> FileStore<D> {
+    pub fn new(block_device: D) -> Self {
+        let clock = SdMmcClock::default();
+        let clock_synced = clock.synced.clone();
+
+        Self {
+            volume_mgr: VolumeManager::new(block_device, clock),
+            clock_synced,
+        }
+    }
+
+    /// Call once the system clock has been synced, files are dated with it from then on
+    pub fn set_clock_synced(&self) {
+        self.clock_synced.store(true, Ordering::Relaxed);
+    }
+
+    pub fn read_lines_from_file(&mut self, file_name: &str) -> Result<Vec<String>> {
+        self.lines(file_name)?.collect()
+    }
+
+    /// Returns an iterator over the lines of the file, without the line endings.  The file is read
+    /// in small chunks so only the current line is held in memory.
+    pub fn lines(&mut self, file_name: &str) -> Result<Lines<'_, D>> {
+        self.open_lines(None, file_name)
+    }
+
+    /// Same as `lines` for a file in a directory of the root directory
+    pub fn lines_in_dir(&mut self, dir_name: &str, file_name: &str) -> Result<Lines<'_, D>> {
+        self.open_lines(Some(dir_name), file_name)
+    }
+
+    fn open_lines(&mut self, dir_name: Option<&str>, file_name: &str) -> Result<Lines<'_, D>> {
+        let volume = self
+            .volume_mgr
+            .open_raw_volume(VolumeIdx(0))
+            .map_err(|e| anyhow::anyhow!("SdCard error: {:?}", e))?;
+
+        match open_raw_file(&mut self.volume_mgr, volume, dir_name, file_name) {
+            Ok(file) => Ok(Lines {
+                volume_mgr: &mut self.volume_mgr,
+                volume,
+                file,
+                chunk: vec![0; READ_CHUNK_SIZE],
+                chunk_len: 0,
+                chunk_pos: 0,
+                finished: false,
+            }),
+            Err(e) => {
+                let _ = self.volume_mgr.close_volume(volume);
+                Err(e)
+            }
+        }
+    }
+
+    /// Reads the whole file, files larger than `limit` bytes are an error
+    pub fn read_to_end(&mut self, file_name: &str, limit: usize) -> Result<Vec<u8>> {
+        let mut volume = self
+            .volume_mgr
+            .open_volume(VolumeIdx(0))
+            .map_err(|e| anyhow::anyhow!("SdCard error: {:?}", e))?;
+
+        let mut root_dir = volume
+            .open_root_dir()
+            .map_err(|e| anyhow::anyhow!("SdCard error: {:?}", e))?;
+
+        let mut file = root_dir
+            .open_file_in_dir(file_name, Mode::ReadOnly)
+            .map_err(|e| anyhow::anyhow!("SdCard error: {:?}", e))?;
+
+        let length = file.length() as usize;
+        if length > limit {
+            anyhow::bail!(
+                "{} is {} bytes, the limit is {} bytes",
+                file_name,
+                length,
+                limit
+            );
+        }
+
+        let mut buffer: Vec<u8> = vec![0; length];
+        let mut bytes_read = 0;
+
+        while bytes_read < length && !file.is_eof() {
+            bytes_read += file
+                .read(&mut buffer[bytes_read..])
+                .map_err(|e| anyhow::anyhow!("SdCard error: {:?}", e))?;
+        }
+
+        buffer.truncate(bytes_read);
+        Ok(buffer)
+    }
+
+    /// Reads the whole file as UTF-8 text, files larger than `limit` bytes are an error
+    pub fn read_to_string(&mut self, file_name: &str, limit: usize) -> Result<String> {
+        let buffer = self.read_to_end(file_name, limit)?;
+        Ok(String::from_utf8(buffer)?)
+    }
+
+    /// Replaces the contents of the file so a power cut at any point leaves either the old or the
+    /// new contents once `recover_file` has run at the next startup.
+    ///
+    /// embedded-sdmmc can't rename files, so the new contents are first written to a .TMP file with
+    /// a length and CRC-32 footer.  Once the .TMP file reads back correctly it is the commit point,
+    /// the old contents are kept in a .BAK file with the same footer and then the file itself is
+    /// rewritten and the .TMP file deleted.
+    pub fn write_lines_to_file(&mut self, file_name: &str, buffer: &[u8]) -> Result<()> {
+        if buffer.len() > MAX_ATOMIC_FILE_SIZE {
+            anyhow::bail!("{} is limited to {} bytes", file_name, MAX_ATOMIC_FILE_SIZE);
+        }
+
+        let tmp_name = sibling_file_name(file_name, "TMP")?;
+        let bak_name = sibling_file_name(file_name, "BAK")?;
+
+        // Write and verify the new contents in the .TMP file
+        let footed = append_footer(buffer);
+        self.write_file(&tmp_name, &footed)?;
+        if self.read_footed_file(&tmp_name)?.as_deref() != Some(buffer) {
+            anyhow::bail!("{} did not read back correctly", tmp_name);
+        }
+
+        // Keep the old contents, a file that can't be read has nothing worth keeping
+        if let Ok(old_contents) = self.read_to_end(file_name, MAX_ATOMIC_FILE_SIZE) {
+            if !old_contents.is_empty() {
+                self.write_file(&bak_name, &append_footer(&old_contents))?;
+            }
+        }
+
+        self.write_file(file_name, buffer)?;
+        if self.read_to_end(file_name, MAX_ATOMIC_FILE_SIZE)? != buffer {
+            anyhow::bail!("{} did not read back correctly", file_name);
+        }
+
+        self.delete_file(&tmp_name)
+    }
+
+    /// Finishes or undoes an interrupted `write_lines_to_file`, call it before reading the file.
+    /// A valid .TMP file holds contents that were committed but maybe not written to the file, an
+    /// invalid one was cut short and is thrown away.  A missing or empty file is restored from the
+    /// .BAK file.
+    pub fn recover_file(&mut self, file_name: &str) -> Result<()> {
+        let tmp_name = sibling_file_name(file_name, "TMP")?;
+        let bak_name = sibling_file_name(file_name, "BAK")?;
+
+        if self.file_exists(&tmp_name)? {
+            if let Some(contents) = self.read_footed_file(&tmp_name)? {
+                warn!("Completing interrupted write of {}", file_name);
+                self.write_file(file_name, &contents)?;
+            } else {
+                warn!("Discarding interrupted write of {}", file_name);
+            }
+
+            self.delete_file(&tmp_name)?;
+        }
+
+        let is_missing = !self.file_exists(file_name)? || self.is_file_empty(file_name)?;
+        if is_missing && self.file_exists(&bak_name)? {
+            if let Some(contents) = self.read_footed_file(&bak_name)? {
+                warn!("Restoring {} from {}", file_name, bak_name);
+                self.write_file(file_name, &contents)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn file_exists(&mut self, file_name: &str) -> Result<bool> {
+        let mut volume = self
+            .volume_mgr
+            .open_volume(VolumeIdx(0))
+            .map_err(|e| anyhow::anyhow!("SdCard error: {:?}", e))?;
+
+        let mut root_dir = volume
+            .open_root_dir()
+            .map_err(|e| anyhow::anyhow!("SdCard error: {:?}", e))?;
+
+        match root_dir.find_directory_entry(file_name) {
+            Ok(_) => Ok(true),
+            Err(Error::NotFound) => Ok(false),
+            Err(e) => Err(anyhow::anyhow!("SdCard error: {:?}", e)),
+        }
+    }
+
+    pub fn delete_file(&mut self, file_name: &str) -> Result<()> {
+        let mut volume = self
+            .volume_mgr
+            .open_volume(VolumeIdx(0))
+            .map_err(|e| anyhow::anyhow!("SdCard error: {:?}", e))?;
+
+        let mut root_dir = volume
+            .open_root_dir()
+            .map_err(|e| anyhow::anyhow!("SdCard error: {:?}", e))?;
+
+        match root_dir.delete_file_in_dir(file_name) {
+            Ok(()) | Err(Error::NotFound) => Ok(()),
+            Err(e) => Err(anyhow::anyhow!("SdCard error: {:?}", e)),
+        }
+    }
+
+    /// Appends the buffer to a file in a directory of the root directory, the directory and the
+    /// file are created when missing.  `header` is written first when the file is new.
+    pub fn append_to_file_in_dir(
+        &mut self,
+        dir_name: &str,
+        file_name: &str,
+        header: &[u8],
+        buffer: &[u8],
+    ) -> Result<()> {
+        let volume = self
+            .volume_mgr
+            .open_raw_volume(VolumeIdx(0))
+            .map_err(|e| anyhow::anyhow!("SdCard error: {:?}", e))?;
+
+        let result = match open_or_make_dir(&mut self.volume_mgr, volume, dir_name) {
+            Ok(dir) => {
+                let result = append_raw_file(&mut self.volume_mgr, dir, file_name, header, buffer);
+                let _ = self.volume_mgr.close_dir(dir);
+                result
+            }
+            Err(e) => Err(e),
+        };
+        let _ = self.volume_mgr.close_volume(volume);
+
+        result
+    }
+
+    /// Names of the files in a directory of the root directory, a missing directory has none
+    pub fn list_dir(&mut self, dir_name: &str) -> Result<Vec<String>> {
+        let mut volume = self
+            .volume_mgr
+            .open_volume(VolumeIdx(0))
+            .map_err(|e| anyhow::anyhow!("SdCard error: {:?}", e))?;
+
+        let mut root_dir = volume
+            .open_root_dir()
+            .map_err(|e| anyhow::anyhow!("SdCard error: {:?}", e))?;
+
+        let mut dir = match root_dir.open_dir(dir_name) {
+            Ok(dir) => dir,
+            Err(Error::NotFound) => return Ok(Vec::new()),
+            Err(e) => return Err(anyhow::anyhow!("SdCard error: {:?}", e)),
+        };
+
+        let mut file_names = Vec::new();
+        dir.iterate_dir(|entry| {
+            if !entry.attributes.is_directory() && !entry.attributes.is_volume() {
+                file_names.push(entry.name.to_string());
+            }
+        })
+        .map_err(|e| anyhow::anyhow!("SdCard error: {:?}", e))?;
+
+        Ok(file_names)
+    }
+
+    /// Size of a file in a directory of the root directory, None when either is missing
+    pub fn file_size_in_dir(&mut self, dir_name: &str, file_name: &str) -> Result<Option<u32>> {
+        let mut volume = self
+            .volume_mgr
+            .open_volume(VolumeIdx(0))
+            .map_err(|e| anyhow::anyhow!("SdCard error: {:?}", e))?;
+
+        let mut root_dir = volume
+            .open_root_dir()
+            .map_err(|e| anyhow::anyhow!("SdCard error: {:?}", e))?;
+
+        let mut dir = match root_dir.open_dir(dir_name) {
+            Ok(dir) => dir,
+            Err(Error::NotFound) => return Ok(None),
+            Err(e) => return Err(anyhow::anyhow!("SdCard error: {:?}", e)),
+        };
+
+        match dir.find_directory_entry(file_name) {
+            Ok(entry) => Ok(Some(entry.size)),
+            Err(Error::NotFound) => Ok(None),
+            Err(e) => Err(anyhow::anyhow!("SdCard error: {:?}", e)),
+        }
+    }
+
+    pub fn delete_file_in_dir(&mut self, dir_name: &str, file_name: &str) -> Result<()> {
+        let mut volume = self
+            .volume_mgr
+            .open_volume(VolumeIdx(0))
+            .map_err(|e| anyhow::anyhow!("SdCard error: {:?}", e))?;
+
+        let mut root_dir = volume
+            .open_root_dir()
+            .map_err(|e| anyhow::anyhow!("SdCard error: {:?}", e))?;
+
+        let mut dir = root_dir
+            .open_dir(dir_name)
+            .map_err(|e| anyhow::anyhow!("SdCard error: {:?}", e))?;
+
+        match dir.delete_file_in_dir(file_name) {
+            Ok(()) | Err(Error::NotFound) => Ok(()),
+            Err(e) => Err(anyhow::anyhow!("SdCard error: {:?}", e)),
+        }
+    }
+
+    // Creates or truncates the file and writes the buffer.  The file is closed explicitly, closing
+    // it updates its directory entry so a failure there has to be reported.
+    fn write_file(&mut self, file_name: &str, buffer: &[u8]) -> Result<()> {
+        let volume = self
+            .volume_mgr
+            .open_raw_volume(VolumeIdx(0))
+            .map_err(|e| anyhow::anyhow!("SdCard error: {:?}", e))?;
+
+        let result = write_raw_file(&mut self.volume_mgr, volume, file_name, buffer);
+        let _ = self.volume_mgr.close_volume(volume);
+
+        result
+    }
+
+    // Reads a file written with a footer, returns None when the footer does not match
+    fn read_footed_file(&mut self, file_name: &str) -> Result<Option<Vec<u8>>> {
+        let contents = self.read_to_end(file_name, MAX_ATOMIC_FILE_SIZE + FOOTER_SIZE)?;
+        Ok(strip_footer(&contents).map(|data| data.to_vec()))
+    }
+
+    pub fn is_file_empty(&mut self, file_name: &str) -> Result<bool> {
+        let mut volume = self
+            .volume_mgr
+            .open_volume(VolumeIdx(0))
+            .map_err(|e| anyhow::anyhow!("SdCard error: {:?}", e))?;
+
+        let mut root_dir = volume
+            .open_root_dir()
+            .map_err(|e| anyhow::anyhow!("SdCard error: {:?}", e))?;
+
+        // Open the file located in the root directory
+        let file = root_dir
+            .open_file_in_dir(file_name, Mode::ReadOnly)
+            .map_err(|e| anyhow::anyhow!("SdCard error: {:?}", e))?;
+
+        Ok(file.length() == 0)
+    }
+}
+
+// Name of the file with the same base name and another extension, e.g. WIFI.TXT -> WIFI.TMP
+fn sibling_file_name(file_name: &str, extension: &str) -> Result<String> {
+    match file_name.rsplit_once('.') {
+        Some((base_name, _)) if !base_name.is_empty() => Ok(format!("{}.{}", base_name, extension)),
+        None if !file_name.is_empty() => Ok(format!("{}.{}", file_name, extension)),
+        _ => anyhow::bail!("{} is not a valid file name", file_name),
+    }
+}
+
+// Appends the footer, the data length and its CRC-32 both as little endian u32
+fn append_footer(data: &[u8]) -> Vec<u8> {
+    let mut footed = Vec::with_capacity(data.len() + FOOTER_SIZE);
+    footed.extend_from_slice(data);
+    footed.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    footed.extend_from_slice(&crc32(data).to_le_bytes());
+    footed
+}
+
+// Returns the data in front of the footer if the footer matches it
+fn strip_footer(footed: &[u8]) -> Option<&[u8]> {
+    let data_len = footed.len().checked_sub(FOOTER_SIZE)?;
+    let (data, footer) = footed.split_at(data_len);
+
+    let length = u32::from_le_bytes(footer[0..4].try_into().ok()?);
+    let crc = u32::from_le_bytes(footer[4..8].try_into().ok()?);
+
+    if length as usize == data.len() && crc == crc32(data) {
+        Some(data)
+    } else {
+        None
+    }
+}
+
+// CRC-32 (IEEE 802.3) computed bit by bit, the files are small
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc: u32 = 0xFFFF_FFFF;
+
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+
+    !crc
+}
+
+// Creates or truncates a file in the root directory of the volume and writes the buffer
+fn write_raw_file<D: BlockDevice>(
+    volume_mgr: &mut VolumeManager<D, SdMmcClock>,
+    volume: RawVolume,
+    file_name: &str,
+    buffer: &[u8],
+) -> Result<()> {
+    let root_dir = volume_mgr
+        .open_root_dir(volume)
+        .map_err(|e| anyhow::anyhow!("SdCard error: {:?}", e))?;
+
+    let file = volume_mgr.open_file_in_dir(root_dir, file_name, Mode::ReadWriteCreateOrTruncate);
+    let _ = volume_mgr.close_dir(root_dir);
+    let file = file.map_err(|e| anyhow::anyhow!("SdCard error: {:?}", e))?;
+
+    let written = volume_mgr.write(file, buffer);
+    let closed = volume_mgr.close_file(file);
+
+    written.map_err(|e| anyhow::anyhow!("SdCard error: {:?}", e))?;
+    closed.map_err(|e| anyhow::anyhow!("SdCard error: {:?}", e))?;
+
+    Ok(())
+}
+
+// Opens a directory in the root directory of the volume, it is created when missing
+fn open_or_make_dir<D: BlockDevice>(
+    volume_mgr: &mut VolumeManager<D, SdMmcClock>,
+    volume: RawVolume,
+    dir_name: &str,
+) -> Result<RawDirectory> {
+    let root_dir = volume_mgr
+        .open_root_dir(volume)
+        .map_err(|e| anyhow::anyhow!("SdCard error: {:?}", e))?;
+
+    let dir = match volume_mgr.open_dir(root_dir, dir_name) {
+        Err(Error::NotFound) => volume_mgr
+            .make_dir_in_dir(root_dir, dir_name)
+            .and_then(|()| volume_mgr.open_dir(root_dir, dir_name)),
+        dir => dir,
+    };
+    let _ = volume_mgr.close_dir(root_dir);
+
+    dir.map_err(|e| anyhow::anyhow!("SdCard error: {:?}", e))
+}
+
+// Appends to a file in the directory, a new file gets the header first
+fn append_raw_file<D: BlockDevice>(
+    volume_mgr: &mut VolumeManager<D, SdMmcClock>,
+    dir: RawDirectory,
+    file_name: &str,
+    header: &[u8],
+    buffer: &[u8],
+) -> Result<()> {
+    let file = volume_mgr
+        .open_file_in_dir(dir, file_name, Mode::ReadWriteCreateOrAppend)
+        .map_err(|e| anyhow::anyhow!("SdCard error: {:?}", e))?;
+
+    let mut written = Ok(());
+    if volume_mgr.file_length(file).unwrap_or(0) == 0 {
+        written = volume_mgr.write(file, header);
+    }
+    if written.is_ok() {
+        written = volume_mgr.write(file, buffer);
+    }
+    let closed = volume_mgr.close_file(file);
+
+    written.map_err(|e| anyhow::anyhow!("SdCard error: {:?}", e))?;
+    closed.map_err(|e| anyhow::anyhow!("SdCard error: {:?}", e))?;
+
+    Ok(())
+}
+
+// Opens a file in the root directory of the volume, or in a directory of the root directory, for
+// reading
+fn open_raw_file<D: BlockDevice>(
+    volume_mgr: &mut VolumeManager<D, SdMmcClock>,
+    volume: RawVolume,
+    dir_name: Option<&str>,
+    file_name: &str,
+) -> Result<RawFile> {
+    let root_dir = volume_mgr
+        .open_root_dir(volume)
+        .map_err(|e| anyhow::anyhow!("SdCard error: {:?}", e))?;
+
+    let file = match dir_name {
+        Some(dir_name) => volume_mgr.open_dir(root_dir, dir_name).and_then(|dir| {
+            let file = volume_mgr.open_file_in_dir(dir, file_name, Mode::ReadOnly);
+            let _ = volume_mgr.close_dir(dir);
+            file
+        }),
+        None => volume_mgr.open_file_in_dir(root_dir, file_name, Mode::ReadOnly),
+    };
+
+    // The open file does not need the directory
+    let _ = volume_mgr.close_dir(root_dir);
+
+    file.map_err(|e| anyhow::anyhow!("SdCard error: {:?}", e))
+}
+
+/// Iterator over the lines of a file, see `FileStore::lines`.  Both "\n" and "\r\n" line endings
+/// are accepted and the last line does not need a line ending.  The file is closed when the
+/// iterator is dropped.
+pub struct Lines<'a, D: BlockDevice> {
+    volume_mgr: &'a mut VolumeManager<D, SdMmcClock>,
+    volume: RawVolume,
+    file: RawFile,
+    chunk: Vec<u8>,
+    chunk_len: usize,
+    chunk_pos: usize,
+    finished: bool,
+}
+
+impl<D: BlockDevice> Lines<'_, D> {
+    // Makes sure there are unread bytes in the chunk, returns false at the end of the file
+    fn fill_chunk(&mut self) -> Result<bool> {
+        if self.chunk_pos < self.chunk_len {
+            return Ok(true);
+        }
+
+        let file_eof = self
+            .volume_mgr
+            .file_eof(self.file)
+            .map_err(|e| anyhow::anyhow!("SdCard error: {:?}", e))?;
+        if file_eof {
+            return Ok(false);
+        }
+
+        self.chunk_len = self
+            .volume_mgr
+            .read(self.file, &mut self.chunk)
+            .map_err(|e| anyhow::anyhow!("SdCard error: {:?}", e))?;
+        self.chunk_pos = 0;
+
+        Ok(self.chunk_len > 0)
+    }
+
+    fn read_line(&mut self) -> Result<Option<String>> {
+        let mut line: Vec<u8> = Vec::new();
+        let mut found_line_end = false;
+
+        while self.fill_chunk()? {
+            let unread = &self.chunk[self.chunk_pos..self.chunk_len];
+
+            let (taken, consumed) = match unread.iter().position(|b| *b == b'\n') {
+                Some(i) => {
+                    found_line_end = true;
+                    (&unread[..i], i + 1)
+                }
+                None => (unread, unread.len()),
+            };
+
+            if line.len() + taken.len() > MAX_LINE_LENGTH {
+                anyhow::bail!("Line is longer than {} bytes", MAX_LINE_LENGTH);
+            }
+
+            line.extend_from_slice(taken);
+            self.chunk_pos += consumed;
+
+            if found_line_end {
+                break;
+            }
+        }
+
+        if !found_line_end && line.is_empty() {
+            return Ok(None);
+        }
+
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+
+        Ok(Some(String::from_utf8(line)?))
+    }
+}
+
+impl<D: BlockDevice> Iterator for Lines<'_, D> {
+    type Item = Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let line = self.read_line().transpose();
+
+        // Stop after the end of the file or the first error
+        if !matches!(line, Some(Ok(_))) {
+            self.finished = true;
+        }
+
+        line
+    }
+}
+
+impl<D: BlockDevice> Drop for Lines<'_, D> {
+    fn drop(&mut self) {
+        let _ = self.volume_mgr.close_file(self.file);
+        let _ = self.volume_mgr.close_volume(self.volume);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use embedded_sdmmc::{Block, BlockCount, BlockIdx};
+    use std::cell::{Cell, RefCell};
+
+    const BLOCK_SIZE: usize = 512;
+
+    /// A disk image held in memory, the power is cut once `writes_left` blocks have been written
+    struct MemDisk {
+        image: RefCell<Vec<u8>>,
+        writes_left: Cell<usize>,
+    }
+
+    impl MemDisk {
+        fn new(image: Vec<u8>) -> Self {
+            Self {
+                image: RefCell::new(image),
+                writes_left: Cell::new(usize::MAX),
+            }
+        }
+    }
+
+    impl BlockDevice for MemDisk {
+        type Error = ();
+
+        fn read(&self, blocks: &mut [Block], start: BlockIdx, _reason: &str) -> Result<(), ()> {
+            let image = self.image.borrow();
+            for (i, block) in blocks.iter_mut().enumerate() {
+                let offset = (start.0 as usize + i) * BLOCK_SIZE;
+                block
+                    .contents
+                    .copy_from_slice(&image[offset..offset + BLOCK_SIZE]);
+            }
+            Ok(())
+        }
+
+        fn write(&self, blocks: &[Block], start: BlockIdx) -> Result<(), ()> {
+            let mut image = self.image.borrow_mut();
+            for (i, block) in blocks.iter().enumerate() {
+                if self.writes_left.get() == 0 {
+                    return Err(());
+                }
+                self.writes_left.set(self.writes_left.get() - 1);
+
+                let offset = (start.0 as usize + i) * BLOCK_SIZE;
+                image[offset..offset + BLOCK_SIZE].copy_from_slice(&block.contents);
+            }
+            Ok(())
+        }
+
+        fn num_blocks(&self) -> Result<BlockCount, ()> {
+            Ok(BlockCount((self.image.borrow().len() / BLOCK_SIZE) as u32))
+        }
+    }
+
+    // A 4 MB disk with a MBR and one empty FAT16 volume
+    fn formatted_disk() -> MemDisk {
+        const TOTAL_BLOCKS: u32 = 8192;
+        const VOLUME_START: u32 = 64;
+        const FAT_BLOCKS: u16 = 32;
+        let volume_blocks = TOTAL_BLOCKS - VOLUME_START;
+
+        let mut image = vec![0u8; TOTAL_BLOCKS as usize * BLOCK_SIZE];
+
+        let partition = &mut image[446..462];
+        partition[4] = 0x06; // FAT16
+        partition[8..12].copy_from_slice(&VOLUME_START.to_le_bytes());
+        partition[12..16].copy_from_slice(&volume_blocks.to_le_bytes());
+        image[510..512].copy_from_slice(&[0x55, 0xAA]);
+
+        let volume = VOLUME_START as usize * BLOCK_SIZE;
+        let boot = &mut image[volume..volume + BLOCK_SIZE];
+        boot[0..3].copy_from_slice(&[0xEB, 0x3C, 0x90]);
+        boot[3..11].copy_from_slice(b"MSWIN4.1");
+        boot[11..13].copy_from_slice(&(BLOCK_SIZE as u16).to_le_bytes());
+        boot[13] = 1; // blocks per cluster
+        boot[14..16].copy_from_slice(&1u16.to_le_bytes()); // reserved blocks
+        boot[16] = 2; // FATs
+        boot[17..19].copy_from_slice(&512u16.to_le_bytes()); // root directory entries
+        boot[21] = 0xF8;
+        boot[22..24].copy_from_slice(&FAT_BLOCKS.to_le_bytes());
+        boot[28..32].copy_from_slice(&VOLUME_START.to_le_bytes());
+        boot[32..36].copy_from_slice(&volume_blocks.to_le_bytes());
+        boot[38] = 0x29;
+        boot[43..54].copy_from_slice(b"NO NAME    ");
+        boot[54..62].copy_from_slice(b"FAT16   ");
+        boot[510..512].copy_from_slice(&[0x55, 0xAA]);
+
+        for fat in 0..2 {
+            let offset = volume + (1 + fat * FAT_BLOCKS as usize) * BLOCK_SIZE;
+            image[offset..offset + 4].copy_from_slice(&[0xF8, 0xFF, 0xFF, 0xFF]);
+        }
+
+        MemDisk::new(image)
+    }
+
+    fn store_with_file(file_name: &str, contents: &[u8]) -> FileStore<MemDisk> {
+        let mut store = FileStore::new(formatted_disk());
+        store.write_file(file_name, contents).unwrap();
+        store
+    }
+
+    fn lines(store: &mut FileStore<MemDisk>, file_name: &str) -> Vec<String> {
+        store
+            .lines(file_name)
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn lines_across_chunks() {
+        // The first line ends at the 512 byte chunk boundary, the "\r\n" of the second one is
+        // split by the 1 KB boundary and the third one is as long as allowed over 3 chunks
+        let mut contents = Vec::new();
+        contents.extend_from_slice(&[b'a'; 510]);
+        contents.extend_from_slice(b"\r\n");
+        contents.extend_from_slice(&[b'b'; 511]);
+        contents.extend_from_slice(b"\r\n");
+        contents.extend_from_slice(&[b'c'; MAX_LINE_LENGTH]);
+        contents.extend_from_slice(b"\n\nlast");
+        assert_eq!(contents[READ_CHUNK_SIZE * 2 - 1], b'\r');
+
+        let mut store = store_with_file("LINES.TXT", &contents);
+        assert_eq!(
+            lines(&mut store, "LINES.TXT"),
+            vec![
+                "a".repeat(510),
+                "b".repeat(511),
+                "c".repeat(MAX_LINE_LENGTH),
+                String::new(),
+                "last".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn lines_of_empty_file() {
+        let mut store = store_with_file("EMPTY.TXT", b"");
+        assert!(lines(&mut store, "EMPTY.TXT").is_empty());
+    }
+
+    #[test]
+    fn line_over_limit_is_an_error() {
+        let mut contents = vec![b'x'; MAX_LINE_LENGTH + 1];
+        contents.extend_from_slice(b"\nnext\n");
+
+        let mut store = store_with_file("LONG.TXT", &contents);
+        let mut lines = store.lines("LONG.TXT").unwrap();
+        let error = lines.next().unwrap().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!("Line is longer than {} bytes", MAX_LINE_LENGTH)
+        );
+        assert!(lines.next().is_none());
+    }
+
+    #[test]
+    fn lines_of_missing_file() {
+        let mut store = FileStore::new(formatted_disk());
+        assert!(store.lines("MISSING.TXT").is_err());
+        assert!(store.lines_in_dir("HISTORY", "MISSING.CSV").is_err());
+
+        // The volume was closed again, the files can still be opened
+        store.write_file("A.TXT", b"a").unwrap();
+        assert_eq!(lines(&mut store, "A.TXT"), vec!["a".to_string()]);
+    }
+
+    #[test]
+    fn read_to_end_up_to_limit() {
+        let contents: Vec<u8> = (0..3000).map(|i| (i % 251) as u8).collect();
+        let mut store = store_with_file("CERT.PEM", &contents);
+
+        assert_eq!(store.read_to_end("CERT.PEM", 3000).unwrap(), contents);
+        let error = store.read_to_end("CERT.PEM", 2999).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "CERT.PEM is 3000 bytes, the limit is 2999 bytes"
+        );
+        assert!(store.read_to_end("MISSING.PEM", 3000).is_err());
+    }
+
+    #[test]
+    fn read_to_string_needs_utf8() {
+        let mut store = store_with_file("TEXT.TXT", "Zürich\n".as_bytes());
+        assert_eq!(store.read_to_string("TEXT.TXT", 100).unwrap(), "Zürich\n");
+
+        store.write_file("BINARY.BIN", &[0xFF, 0xFE]).unwrap();
+        assert!(store.read_to_string("BINARY.BIN", 100).is_err());
+    }
+
+    // The footers of both contents straddle a block boundary
+    const OLD_CONTENTS: &[u8] = &[b'o'; 508];
+    const NEW_CONTENTS: &[u8] = &[b'n'; 1020];
+
+    fn recovered(store: &mut FileStore<MemDisk>) -> Vec<u8> {
+        // Start again from what is on the disk, like after a reboot
+        let image = store.volume_mgr.device().image.borrow().clone();
+        let mut store = FileStore::new(MemDisk::new(image));
+
+        store.recover_file("WIFI.TXT").unwrap();
+        assert!(!store.file_exists("WIFI.TMP").unwrap());
+        store.read_to_end("WIFI.TXT", MAX_ATOMIC_FILE_SIZE).unwrap()
+    }
+
+    #[test]
+    fn write_lines_to_file_keeps_backup() {
+        let mut store = store_with_file("WIFI.TXT", OLD_CONTENTS);
+        store.write_lines_to_file("WIFI.TXT", NEW_CONTENTS).unwrap();
+
+        assert_eq!(
+            store.read_to_end("WIFI.TXT", MAX_ATOMIC_FILE_SIZE).unwrap(),
+            NEW_CONTENTS
+        );
+        assert!(!store.file_exists("WIFI.TMP").unwrap());
+        assert_eq!(
+            store.read_footed_file("WIFI.BAK").unwrap().as_deref(),
+            Some(OLD_CONTENTS)
+        );
+    }
+
+    #[test]
+    fn power_cut_at_every_block_write() {
+        for cut_after in 0.. {
+            let mut store = store_with_file("WIFI.TXT", OLD_CONTENTS);
+            store.volume_mgr.device().writes_left.set(cut_after);
+            let written = store.write_lines_to_file("WIFI.TXT", NEW_CONTENTS).is_ok();
+
+            let contents = recovered(&mut store);
+            assert!(
+                contents == OLD_CONTENTS || contents == NEW_CONTENTS,
+                "torn file after {} block writes",
+                cut_after
+            );
+
+            if written {
+                assert_eq!(contents, NEW_CONTENTS);
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn recover_after_tmp_written() {
+        let mut store = store_with_file("WIFI.TXT", OLD_CONTENTS);
+        store
+            .write_file("WIFI.TMP", &append_footer(NEW_CONTENTS))
+            .unwrap();
+        assert_eq!(recovered(&mut store), NEW_CONTENTS);
+    }
+
+    #[test]
+    fn recover_after_bak_written() {
+        let mut store = store_with_file("WIFI.TXT", b"");
+        store
+            .write_file("WIFI.TMP", &append_footer(NEW_CONTENTS))
+            .unwrap();
+        store
+            .write_file("WIFI.BAK", &append_footer(OLD_CONTENTS))
+            .unwrap();
+        assert_eq!(recovered(&mut store), NEW_CONTENTS);
+    }
+
+    #[test]
+    fn recover_after_cut_in_footer() {
+        let footed = append_footer(NEW_CONTENTS);
+        let mut store = store_with_file("WIFI.TXT", OLD_CONTENTS);
+        store
+            .write_file("WIFI.TMP", &footed[..footed.len() - 3])
+            .unwrap();
+        assert_eq!(recovered(&mut store), OLD_CONTENTS);
+    }
+
+    #[test]
+    fn recover_empty_file_from_bak() {
+        let mut store = store_with_file("WIFI.TXT", b"");
+        store
+            .write_file("WIFI.BAK", &append_footer(OLD_CONTENTS))
+            .unwrap();
+        assert_eq!(recovered(&mut store), OLD_CONTENTS);
+    }
+
+    #[test]
+    fn files_are_dated_once_the_clock_is_synced() {
+        let mut store = FileStore::new(formatted_disk());
+        store.write_file("A.TXT", b"a").unwrap();
+        store.set_clock_synced();
+        store.write_file("B.TXT", b"b").unwrap();
+
+        let mut volume = store.volume_mgr.open_volume(VolumeIdx(0)).unwrap();
+        let mut root_dir = volume.open_root_dir().unwrap();
+        let a = root_dir.find_directory_entry("A.TXT").unwrap();
+        let b = root_dir.find_directory_entry("B.TXT").unwrap();
+        assert_eq!(a.mtime, FAT_EPOCH);
+        assert!(b.mtime.year_since_1970 >= 56);
+    }
+
+    #[test]
+    fn timestamps_are_clamped_to_fat_dates() {
+        let date = |year| Utc.with_ymd_and_hms(year, 10, 19, 7, 30, 15).unwrap();
+
+        assert_eq!(timestamp_from_datetime(&date(1970)), FAT_EPOCH);
+        assert_eq!(
+            timestamp_from_datetime(&date(2026)),
+            Timestamp {
+                year_since_1970: 56,
+                zero_indexed_month: 9,
+                zero_indexed_day: 18,
+                hours: 7,
+                minutes: 30,
+                seconds: 15,
+            }
+        );
+        assert_eq!(timestamp_from_datetime(&date(2200)).year_since_1970, 137);
+    }
+}
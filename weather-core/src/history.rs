@@ -0,0 +1,734 @@
+//! Local weather history.
+//!
+//! Every forecasts fetch appends one row per city to a daily CSV file in the HISTORY directory on
+//! the SDCard.  The files are named after the UTC date of the rows, e.g. HISTORY/20261019.CSV, so a
+//! new file is started every day at midnight UTC.  When a new file is started the files older than
+//! the retention limit are deleted.  The limit is read from the optional HISTORY.TXT file:
+//!
+//! ```text
+//! retention_days=365     optional, number of daily files kept, default 365
+//! ```
+//!
+//! With the default 10 minutes refresh a daily file holds 576 rows, about 50KB.  The date of the
+//! rows fetched before the clock has been synced is not known, the last 12 fetches are kept and
+//! dated from the time elapsed since they were fetched once it is.
+//!
+//! The History pane shows the rows of a city over the last 24 hours, 7 days or 30 days.  The rows
+//! are read a line at a time and summed up in `HistoryBuckets`, the min, max and average of each
+//! bucket of time is all the charts need.  `HistoryRead` reads one daily file each time around the
+//! model loop so the panes keep being served meanwhile.  The aggregation has no hardware so it can
+//! be checked on the host.
+use crate::cities::CityConditions;
+use crate::config_storage::ConfigStorage;
+use crate::i18n;
+use crate::key_value::parse_key_values;
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+
+use std::collections::VecDeque;
+use std::time::Instant;
+
+use log::{info, warn};
+
+pub const HISTORY_DIR: &str = "HISTORY";
+
+pub const CSV_HEADER: &str =
+    "timestamp,city,temp_f,feels_like_f,humidity,pressure_mb,wind_mph,wind_gust_mph,wind_dir,aqi,uv\n";
+
+pub const DEFAULT_RETENTION_DAYS: u32 = 365;
+
+// Ten years of daily files
+const MAX_RETENTION_DAYS: u32 = 3650;
+
+// Fetches kept until the clock is synced, 2 hours at the default refresh interval
+const MAX_UNDATED_FETCHES: usize = 12;
+
+/// History settings read from HISTORY.TXT
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryConfig {
+    pub retention_days: u32,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            retention_days: DEFAULT_RETENTION_DAYS,
+        }
+    }
+}
+
+impl HistoryConfig {
+    /// Reads HISTORY.TXT, see key_value.rs, returns the error message to log when it is not valid
+    pub fn from_lines(lines: &[String]) -> Result<Self, String> {
+        let mut config = Self::default();
+
+        parse_key_values("HISTORY.TXT", lines, |key, value| {
+            match key {
+                "retention_days" => {
+                    config.retention_days = value
+                        .parse()
+                        .ok()
+                        .filter(|days| (1..=MAX_RETENTION_DAYS).contains(days))
+                        .ok_or_else(|| {
+                            format!(
+                                "retention_days must be a number of days from 1 to {}",
+                                MAX_RETENTION_DAYS
+                            )
+                        })?
+                }
+                _ => return Ok(false),
+            }
+            Ok(true)
+        })?;
+
+        Ok(config)
+    }
+}
+
+/// Name of the daily file holding the rows of the date
+pub fn file_name(date: NaiveDate) -> String {
+    format!("{}.CSV", date.format("%Y%m%d"))
+}
+
+/// Date of a daily file, None for any other file
+pub fn date_from_file_name(file_name: &str) -> Option<NaiveDate> {
+    let (base_name, extension) = file_name.split_once('.')?;
+    if !extension.eq_ignore_ascii_case("CSV") || base_name.len() != 8 {
+        return None;
+    }
+
+    NaiveDate::parse_from_str(base_name, "%Y%m%d").ok()
+}
+
+/// CSV row of the city conditions, the city name can't hold the separator
+pub fn csv_row(timestamp: &DateTime<Utc>, city_name: &str, conditions: &CityConditions) -> String {
+    format!(
+        "{},{},{:.1},{:.1},{},{:.1},{:.1},{:.1},{},{},{:.1}\n",
+        timestamp.format("%Y-%m-%dT%H:%M:%SZ"),
+        city_name.replace(',', " "),
+        conditions.temp_f,
+        conditions.feels_like_f,
+        conditions.humidity,
+        conditions.pressure_mb,
+        conditions.wind_mph,
+        conditions.wind_gust_mph,
+        conditions.wind_dir.replace(',', " "),
+        conditions.aqi,
+        conditions.uv
+    )
+}
+
+/// Daily files that are older than the retention limit, the file of today counts as one day
+pub fn expired_files(file_names: &[String], today: NaiveDate, retention_days: u32) -> Vec<String> {
+    let oldest_kept = today - Duration::days(retention_days as i64 - 1);
+
+    file_names
+        .iter()
+        .filter(|file_name| date_from_file_name(file_name).is_some_and(|date| date < oldest_kept))
+        .cloned()
+        .collect()
+}
+
+/// City name and conditions of a history row
+pub type HistoryRow = (String, CityConditions);
+
+/// Writes the rows to the daily files and deletes the expired ones
+pub struct HistoryLog {
+    config: HistoryConfig,
+    // File the last rows were appended to
+    current_file: Option<String>,
+    // Rows fetched before the clock was synced, with the time of their fetch
+    undated: VecDeque<(Instant, Vec<HistoryRow>)>,
+}
+
+impl HistoryLog {
+    pub fn new(config: HistoryConfig) -> Self {
+        Self {
+            config,
+            current_file: None,
+            undated: VecDeque::new(),
+        }
+    }
+
+    /// Uses the settings of a HISTORY.TXT read again, the rows waiting for the clock are kept
+    pub fn set_config(&mut self, config: HistoryConfig) {
+        self.config = config;
+        self.current_file = None;
+    }
+
+    /// Appends the rows fetched at `now` to the file of the day, the expired files are deleted
+    /// when a new file is started
+    pub fn append(
+        &mut self,
+        storage: &mut dyn ConfigStorage,
+        now: &DateTime<Utc>,
+        rows: &[HistoryRow],
+    ) -> Result<()> {
+        let today = now.date_naive();
+        let file_name = file_name(today);
+
+        let csv_rows: String = rows
+            .iter()
+            .map(|(city_name, conditions)| csv_row(now, city_name, conditions))
+            .collect();
+        storage.append_in_dir(
+            HISTORY_DIR,
+            &file_name,
+            CSV_HEADER.as_bytes(),
+            csv_rows.as_bytes(),
+        )?;
+
+        if self.current_file.as_deref() != Some(file_name.as_str()) {
+            self.current_file = Some(file_name);
+
+            let file_names = storage.list_dir(HISTORY_DIR)?;
+            for expired_file in expired_files(&file_names, today, self.config.retention_days) {
+                info!("Deleting history file {}", expired_file);
+                storage.delete_in_dir(HISTORY_DIR, &expired_file)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Keeps the rows of a fetch made before the clock was synced, the oldest fetch is dropped
+    /// when there are too many
+    pub fn keep_undated(&mut self, fetched: Instant, rows: Vec<HistoryRow>) {
+        if self.undated.len() == MAX_UNDATED_FETCHES {
+            self.undated.pop_front();
+        }
+        self.undated.push_back((fetched, rows));
+    }
+
+    /// Appends the kept rows once the clock is synced, `now` and `now_instant` are the same time
+    pub fn append_undated(
+        &mut self,
+        storage: &mut dyn ConfigStorage,
+        now: &DateTime<Utc>,
+        now_instant: Instant,
+    ) -> Result<()> {
+        while let Some((fetched, rows)) = self.undated.pop_front() {
+            let elapsed = now_instant.saturating_duration_since(fetched);
+            let timestamp = *now - Duration::from_std(elapsed).unwrap_or_else(|_| Duration::zero());
+            self.append(storage, &timestamp, &rows)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Time span shown on the History pane
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryPeriod {
+    Day,
+    Week,
+    Month,
+}
+
+impl HistoryPeriod {
+    pub const ALL: [HistoryPeriod; 3] = [
+        HistoryPeriod::Day,
+        HistoryPeriod::Week,
+        HistoryPeriod::Month,
+    ];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            HistoryPeriod::Day => i18n::text().period_day,
+            HistoryPeriod::Week => i18n::text().period_week,
+            HistoryPeriod::Month => i18n::text().period_month,
+        }
+    }
+
+    /// Number of buckets, each one is a point of the charts
+    pub fn bucket_count(&self) -> usize {
+        match self {
+            HistoryPeriod::Day => 24,
+            HistoryPeriod::Week => 28,
+            HistoryPeriod::Month => 30,
+        }
+    }
+
+    /// Hourly buckets for a day, 6 hours for a week and daily for a month
+    pub fn bucket_duration(&self) -> Duration {
+        match self {
+            HistoryPeriod::Day => Duration::hours(1),
+            HistoryPeriod::Week => Duration::hours(6),
+            HistoryPeriod::Month => Duration::days(1),
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.bucket_duration() * self.bucket_count() as i32
+    }
+}
+
+/// The values of a history row shown on the History pane
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistorySample {
+    pub timestamp: DateTime<Utc>,
+    pub temp_f: f64,
+    pub pressure_mb: f64,
+    pub humidity: f64,
+}
+
+/// Parses a CSV row written by `csv_row`, None for the header, the rows of other cities and rows
+/// cut short by a power loss
+pub fn parse_row(line: &str, city_name: &str) -> Option<HistorySample> {
+    let fields: Vec<&str> = line.split(',').collect();
+    if fields.len() != 11 || fields[1] != city_name.replace(',', " ") {
+        return None;
+    }
+
+    Some(HistorySample {
+        timestamp: DateTime::parse_from_rfc3339(fields[0])
+            .ok()?
+            .with_timezone(&Utc),
+        temp_f: fields[2].parse().ok()?,
+        pressure_mb: fields[5].parse().ok()?,
+        humidity: fields[4].parse().ok()?,
+    })
+}
+
+/// Min, max and average of the values in a bucket
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BucketStats {
+    pub min: f64,
+    pub max: f64,
+    pub avg: f64,
+}
+
+// Running min, max and sum of a bucket
+#[derive(Debug, Clone, Copy)]
+struct Accumulator {
+    min: f64,
+    max: f64,
+    sum: f64,
+    count: u32,
+}
+
+impl Accumulator {
+    const EMPTY: Accumulator = Accumulator {
+        min: f64::INFINITY,
+        max: f64::NEG_INFINITY,
+        sum: 0.0,
+        count: 0,
+    };
+
+    fn add(&mut self, value: f64) {
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn stats(&self) -> Option<BucketStats> {
+        (self.count > 0).then(|| BucketStats {
+            min: self.min,
+            max: self.max,
+            avg: self.sum / self.count as f64,
+        })
+    }
+}
+
+/// History of a city ready to be charted, a bucket without rows is None
+#[derive(Debug, Clone, PartialEq)]
+pub struct CityHistory {
+    pub city_name: String,
+    pub period: HistoryPeriod,
+    pub start: DateTime<Utc>,
+    pub temp_f: Vec<Option<BucketStats>>,
+    pub pressure_mb: Vec<Option<BucketStats>>,
+    pub humidity: Vec<Option<BucketStats>>,
+}
+
+impl CityHistory {
+    pub fn is_empty(&self) -> bool {
+        self.temp_f.iter().all(|bucket| bucket.is_none())
+    }
+}
+
+/// Sums up the samples into the buckets of a period, the samples can come in any order
+pub struct HistoryBuckets {
+    period: HistoryPeriod,
+    start: DateTime<Utc>,
+    buckets: Vec<[Accumulator; 3]>,
+}
+
+impl HistoryBuckets {
+    /// The last bucket holds `now`, the buckets start on whole hours, 6 hours or days UTC
+    pub fn new(period: HistoryPeriod, now: &DateTime<Utc>) -> Self {
+        let bucket_secs = period.bucket_duration().num_seconds();
+        let end_secs = (now.timestamp().div_euclid(bucket_secs) + 1) * bucket_secs;
+        let start_secs = end_secs - period.duration().num_seconds();
+
+        Self {
+            period,
+            start: Utc.timestamp_opt(start_secs, 0).unwrap(),
+            buckets: vec![[Accumulator::EMPTY; 3]; period.bucket_count()],
+        }
+    }
+
+    /// Names of the daily files that can hold rows of the buckets
+    pub fn file_names(&self) -> Vec<String> {
+        let first_day = self.start.date_naive();
+        let last_day = (self.start + self.period.duration() - Duration::seconds(1)).date_naive();
+
+        first_day
+            .iter_days()
+            .take_while(|day| *day <= last_day)
+            .map(file_name)
+            .collect()
+    }
+
+    /// Adds the sample to its bucket, samples outside of the buckets are ignored
+    pub fn add(&mut self, sample: &HistorySample) {
+        let offset = (sample.timestamp - self.start).num_seconds();
+        if offset < 0 {
+            return;
+        }
+
+        let index = (offset / self.period.bucket_duration().num_seconds()) as usize;
+        if let Some(bucket) = self.buckets.get_mut(index) {
+            bucket[0].add(sample.temp_f);
+            bucket[1].add(sample.pressure_mb);
+            bucket[2].add(sample.humidity);
+        }
+    }
+
+    pub fn into_history(self, city_name: &str) -> CityHistory {
+        let series = |i: usize| -> Vec<Option<BucketStats>> {
+            self.buckets
+                .iter()
+                .map(|bucket| bucket[i].stats())
+                .collect()
+        };
+
+        CityHistory {
+            city_name: city_name.to_string(),
+            period: self.period,
+            start: self.start,
+            temp_f: series(0),
+            pressure_mb: series(1),
+            humidity: series(2),
+        }
+    }
+}
+
+/// Reads the history of a city one daily file at a time, so reading a month of files doesn't hold
+/// up the model loop
+pub struct HistoryRead {
+    city_name: String,
+    buckets: HistoryBuckets,
+    file_names: VecDeque<String>,
+}
+
+impl HistoryRead {
+    /// `existing_files` are the files of the history directory, the missing days are skipped
+    pub fn new(
+        city_name: &str,
+        period: HistoryPeriod,
+        now: &DateTime<Utc>,
+        existing_files: &[String],
+    ) -> Self {
+        let buckets = HistoryBuckets::new(period, now);
+        let file_names = buckets
+            .file_names()
+            .into_iter()
+            .filter(|file_name| existing_files.contains(file_name))
+            .collect();
+
+        Self {
+            city_name: city_name.to_string(),
+            buckets,
+            file_names,
+        }
+    }
+
+    /// Reads the next file, returns true once all the files have been read
+    pub fn read_next(&mut self, storage: &mut dyn ConfigStorage) -> bool {
+        if let Some(file_name) = self.file_names.pop_front() {
+            let city_name = &self.city_name;
+            let buckets = &mut self.buckets;
+            let result = storage.for_each_line_in_dir(HISTORY_DIR, &file_name, &mut |line| {
+                if let Some(sample) = parse_row(line, city_name) {
+                    buckets.add(&sample);
+                }
+            });
+
+            // Keep what was read, the rest of the file is missing from the charts
+            if let Err(e) = result {
+                warn!("History {} error = {}", file_name, e);
+            }
+        }
+
+        self.file_names.is_empty()
+    }
+
+    pub fn into_history(self) -> CityHistory {
+        self.buckets.into_history(&self.city_name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config_storage::MemoryStorage;
+
+    // Text of a file of the history directory
+    fn history_file(storage: &mut MemoryStorage, file_name: &str) -> String {
+        let mut text = String::new();
+        storage
+            .for_each_line_in_dir(HISTORY_DIR, file_name, &mut |line| {
+                text.push_str(line);
+                text.push('\n');
+            })
+            .unwrap();
+        text
+    }
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    fn conditions(temp_f: f64) -> CityConditions {
+        CityConditions {
+            temp_f,
+            feels_like_f: temp_f,
+            humidity: 50,
+            pressure_mb: 1013.0,
+            wind_mph: 5.0,
+            wind_gust_mph: 8.0,
+            wind_dir: "N".to_string(),
+            uv: 3.0,
+            aqi: 1,
+            condition: "Sunny".to_string(),
+            last_updated_epoch: 0,
+        }
+    }
+
+    fn utc(text: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(text)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn history_txt_settings() {
+        assert_eq!(
+            HistoryConfig::from_lines(&lines("# keep a month\nretention_days = 30\ntypo=1")),
+            Ok(HistoryConfig { retention_days: 30 })
+        );
+        assert!(HistoryConfig::from_lines(&lines("retention_days=0")).is_err());
+        assert!(HistoryConfig::from_lines(&lines("retention_days=3651")).is_err());
+    }
+
+    #[test]
+    fn rows_fetched_before_time_sync_are_dated_on_sync() {
+        let mut storage = MemoryStorage::with_directories();
+        let mut history = HistoryLog::new(HistoryConfig::default());
+
+        let booted = Instant::now();
+        let minutes = |minutes: u64| booted + std::time::Duration::from_secs(minutes * 60);
+        history.keep_undated(minutes(0), vec![("Denver".to_string(), conditions(50.0))]);
+        history.keep_undated(minutes(10), vec![("Denver".to_string(), conditions(52.0))]);
+
+        // Synced 20 minutes after the first fetch, a few minutes after midnight UTC
+        history
+            .append_undated(&mut storage, &utc("2026-10-19T00:05:00Z"), minutes(20))
+            .unwrap();
+        history
+            .append_undated(&mut storage, &utc("2026-10-19T00:06:00Z"), minutes(21))
+            .unwrap();
+
+        let file = history_file(&mut storage, "20261018.CSV");
+        let samples: Vec<HistorySample> = file
+            .lines()
+            .filter_map(|line| parse_row(line, "Denver"))
+            .collect();
+        assert!(file.starts_with(CSV_HEADER));
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[0].timestamp, utc("2026-10-18T23:45:00Z"));
+        assert_eq!(samples[0].temp_f, 50.0);
+        assert_eq!(samples[1].timestamp, utc("2026-10-18T23:55:00Z"));
+    }
+
+    #[test]
+    fn only_the_last_undated_fetches_are_kept() {
+        let mut storage = MemoryStorage::with_directories();
+        let mut history = HistoryLog::new(HistoryConfig::default());
+
+        let fetched = Instant::now();
+        for fetch in 0..MAX_UNDATED_FETCHES + 3 {
+            history.keep_undated(
+                fetched,
+                vec![("Denver".to_string(), conditions(fetch as f64))],
+            );
+        }
+        history.set_config(HistoryConfig { retention_days: 7 });
+        history
+            .append_undated(&mut storage, &utc("2026-10-19T12:00:00Z"), fetched)
+            .unwrap();
+
+        let file = history_file(&mut storage, "20261019.CSV");
+        let temps: Vec<f64> = file
+            .lines()
+            .filter_map(|line| parse_row(line, "Denver"))
+            .map(|sample| sample.temp_f)
+            .collect();
+        assert_eq!(temps.len(), MAX_UNDATED_FETCHES);
+        assert_eq!(temps[0], 3.0);
+    }
+
+    #[test]
+    fn files_older_than_the_retention_days_expire() {
+        let file_names = lines("20261012.CSV\n20261013.CSV\n20261019.CSV\nNOTES.TXT\n2026.CSV");
+        let today = NaiveDate::from_ymd_opt(2026, 10, 19).unwrap();
+
+        assert_eq!(expired_files(&file_names, today, 7), vec!["20261012.CSV"]);
+        assert_eq!(
+            expired_files(&file_names, today, 1),
+            vec!["20261012.CSV", "20261013.CSV"]
+        );
+        assert!(expired_files(&file_names, today, 8).is_empty());
+    }
+
+    #[test]
+    fn expired_files_are_deleted_when_the_day_changes() {
+        let mut storage = MemoryStorage::with_directories();
+        let mut history = HistoryLog::new(HistoryConfig { retention_days: 3 });
+        let rows = [("Denver".to_string(), conditions(50.0))];
+        let mut append = |storage: &mut MemoryStorage, timestamp| {
+            history.append(storage, &utc(timestamp), &rows).unwrap();
+            storage.list_dir(HISTORY_DIR).unwrap()
+        };
+
+        append(&mut storage, "2026-10-17T12:00:00Z");
+        append(&mut storage, "2026-10-18T12:00:00Z");
+        assert_eq!(
+            append(&mut storage, "2026-10-19T23:59:59Z"),
+            vec!["20261017.CSV", "20261018.CSV", "20261019.CSV"]
+        );
+
+        // The files are only checked when a new file is started
+        storage
+            .append_in_dir(HISTORY_DIR, "20261001.CSV", b"", b"")
+            .unwrap();
+        assert_eq!(append(&mut storage, "2026-10-19T23:59:59Z").len(), 4);
+
+        assert_eq!(
+            append(&mut storage, "2026-10-20T00:00:00Z"),
+            vec!["20261018.CSV", "20261019.CSV", "20261020.CSV"]
+        );
+    }
+
+    fn sample(timestamp: &str, temp_f: f64) -> HistorySample {
+        HistorySample {
+            timestamp: utc(timestamp),
+            temp_f,
+            pressure_mb: 1000.0 + temp_f,
+            humidity: 40.0,
+        }
+    }
+
+    #[test]
+    fn buckets_hold_min_max_and_average() {
+        let mut buckets = HistoryBuckets::new(HistoryPeriod::Day, &utc("2026-10-19T12:30:00Z"));
+        assert_eq!(
+            buckets.file_names(),
+            vec!["20261018.CSV".to_string(), "20261019.CSV".to_string()]
+        );
+
+        // Hourly buckets from 13:00 yesterday to 13:00 today, the samples outside are ignored
+        buckets.add(&sample("2026-10-18T12:59:59Z", -100.0));
+        buckets.add(&sample("2026-10-18T13:00:00Z", 40.0));
+        buckets.add(&sample("2026-10-19T12:45:00Z", 60.0));
+        buckets.add(&sample("2026-10-19T12:05:00Z", 50.0));
+        buckets.add(&sample("2026-10-19T12:10:00Z", 58.0));
+        buckets.add(&sample("2026-10-19T13:00:00Z", 100.0));
+
+        let history = buckets.into_history("Denver");
+        assert_eq!(history.start, utc("2026-10-18T13:00:00Z"));
+        assert_eq!(history.temp_f.len(), 24);
+        assert_eq!(
+            history.temp_f[0],
+            Some(BucketStats {
+                min: 40.0,
+                max: 40.0,
+                avg: 40.0
+            })
+        );
+        assert_eq!(
+            history.temp_f[23],
+            Some(BucketStats {
+                min: 50.0,
+                max: 60.0,
+                avg: 56.0
+            })
+        );
+        assert_eq!(history.pressure_mb[23].unwrap().max, 1060.0);
+        assert!(history.temp_f[1..23].iter().all(|bucket| bucket.is_none()));
+        assert!(!history.is_empty());
+    }
+
+    #[test]
+    fn history_without_rows_is_empty() {
+        let buckets = HistoryBuckets::new(HistoryPeriod::Month, &utc("2026-10-19T12:30:00Z"));
+        assert_eq!(buckets.file_names().len(), 30);
+
+        let history = buckets.into_history("Denver");
+        assert_eq!(history.start, utc("2026-09-20T00:00:00Z"));
+        assert!(history.is_empty());
+    }
+
+    #[test]
+    fn history_is_read_a_file_at_a_time() {
+        let mut storage = MemoryStorage::with_directories();
+        let mut history = HistoryLog::new(HistoryConfig::default());
+        for (timestamp, city_name, temp_f) in [
+            ("2026-10-17T12:00:00Z", "Denver", 30.0),
+            ("2026-10-18T12:00:00Z", "Denver", 40.0),
+            ("2026-10-18T12:00:00Z", "Chicago", 70.0),
+            ("2026-10-19T12:00:00Z", "Denver", 50.0),
+        ] {
+            history
+                .append(
+                    &mut storage,
+                    &utc(timestamp),
+                    &[(city_name.to_string(), conditions(temp_f))],
+                )
+                .unwrap();
+        }
+        storage
+            .append_in_dir(
+                HISTORY_DIR,
+                "20261019.CSV",
+                b"",
+                b"2026-10-19T12:10:00Z,Den",
+            )
+            .unwrap();
+
+        let existing_files = storage.list_dir(HISTORY_DIR).unwrap();
+        let mut read = HistoryRead::new(
+            "Denver",
+            HistoryPeriod::Week,
+            &utc("2026-10-19T12:30:00Z"),
+            &existing_files,
+        );
+        let mut files_read = 1;
+        while !read.read_next(&mut storage) {
+            files_read += 1;
+        }
+        assert_eq!(files_read, 3);
+
+        let temps: Vec<f64> = read
+            .into_history()
+            .temp_f
+            .iter()
+            .flatten()
+            .map(|stats| stats.avg)
+            .collect();
+        assert_eq!(temps, vec![30.0, 40.0, 50.0]);
+    }
+}
@@ -0,0 +1,695 @@
+//! Translations of the UI text.
+//!
+//! The locale is set with `locale=` in SETTINGS.TXT, `en`, `es` or `de`, and used from the next
+//! start since the labels of the panes are only set once.  Each locale has a `Catalog` with its
+//! messages, weekday and month names, decimal separator and the `lang=` of the weatherapi
+//! requests, which translates the condition text.  The order of the dates and the 12 or 24 hour
+//! clock are settings of their own, see date_format.rs.  The messages are the fields of
+//! `Messages`, so the compiler checks that every catalog has every message.
+//!
+//! A message with `{}` gets its values with `fill`.  The montserrat fonts of lv_conf.h only have
+//! the ASCII glyphs, the catalogs are written without accents, ue for ü, and the condition text
+//! of weatherapi is folded the same way with `fold_accents`.
+use crate::key_value;
+
+use chrono::Datelike;
+
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    English,
+    Spanish,
+    German,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 3] = [Locale::English, Locale::Spanish, Locale::German];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Locale::English => "en",
+            Locale::Spanish => "es",
+            Locale::German => "de",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        key_value::parse_named(&Self::ALL, Self::as_str, "a locale", value)
+    }
+
+    pub fn catalog(&self) -> &'static Catalog {
+        match self {
+            Locale::English => &ENGLISH,
+            Locale::Spanish => &SPANISH,
+            Locale::German => &GERMAN,
+        }
+    }
+}
+
+pub struct Catalog {
+    /// `lang=` of the weatherapi requests, English is the language of the API
+    pub api_lang: Option<&'static str>,
+    pub decimal_separator: char,
+    /// From Monday
+    pub weekdays: [&'static str; 7],
+    pub weekdays_short: [&'static str; 7],
+    pub months: [&'static str; 12],
+    pub months_short: [&'static str; 12],
+    pub text: Messages,
+}
+
+impl Catalog {
+    /// See `localize`
+    pub fn localize(&self, format: &str, date: &impl Datelike) -> String {
+        let weekday = date.weekday().num_days_from_monday() as usize;
+        let month = date.month0() as usize;
+
+        let mut localized = String::new();
+        let mut chars = format.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                localized.push(c);
+                continue;
+            }
+
+            match chars.next() {
+                Some('a') => localized += self.weekdays_short[weekday],
+                Some('A') => localized += self.weekdays[weekday],
+                Some('b') | Some('h') => localized += self.months_short[month],
+                Some('B') => localized += self.months[month],
+                Some(spec) => {
+                    localized.push('%');
+                    localized.push(spec);
+                }
+                None => localized.push('%'),
+            }
+        }
+
+        localized
+    }
+
+    /// See `number`
+    pub fn number(&self, value: f64, decimals: usize) -> String {
+        let number = format!("{:.*}", decimals, value);
+        match self.decimal_separator {
+            '.' => number,
+            separator => number.replace('.', &separator.to_string()),
+        }
+    }
+}
+
+pub struct Messages {
+    // Home pane
+    pub local_time: &'static str,
+    pub local_weather: &'static str,
+    pub uv: &'static str,
+    pub feels_like: &'static str,
+    pub aqi: &'static str,
+    pub wind: &'static str,
+    pub gust: &'static str,
+    pub wind_dir: &'static str,
+    pub local_forecast: &'static str,
+    pub last_update: &'static str,
+    // Nav bar
+    pub nav_home: &'static str,
+    pub nav_wifi: &'static str,
+    pub nav_cities: &'static str,
+    pub nav_history: &'static str,
+    pub nav_display: &'static str,
+    pub nav_clock: &'static str,
+    pub app_title: &'static str,
+    pub wifi_disconnected: &'static str,
+    pub wifi_connecting: &'static str,
+    pub wifi_down: &'static str,
+    pub ip_address: &'static str,
+    pub no_ip_address: &'static str,
+    pub time_synced: &'static str,
+    pub time_not_synced: &'static str,
+    pub updated_just_now: &'static str,
+    pub updated_minutes_ago: &'static str,
+    pub updated_hours_ago: &'static str,
+    pub not_updated: &'static str,
+    // Startup pane
+    pub connecting_wifi: &'static str,
+    pub waiting_forecasts: &'static str,
+    pub updating_firmware: &'static str,
+    pub firmware_updated: &'static str,
+    pub firmware_update_failed: &'static str,
+    // Settings panes
+    pub settings: &'static str,
+    pub wifi_network_name: &'static str,
+    pub password: &'static str,
+    pub status: &'static str,
+    pub status_disconnected: &'static str,
+    pub status_connecting: &'static str,
+    pub status_connected: &'static str,
+    pub status_backing_off: &'static str,
+    pub wifi_connection_failed: &'static str,
+    pub edit: &'static str,
+    pub save: &'static str,
+    pub exit: &'static str,
+    pub name: &'static str,
+    pub state: &'static str,
+    pub zipcode: &'static str,
+    pub timezone: &'static str,
+    pub city_row: &'static str,
+    pub city_error: &'static str,
+    pub timezone_not_valid: &'static str,
+    pub zipcode_range: &'static str,
+    pub zipcode_digits: &'static str,
+    pub zipcode_not_number: &'static str,
+    pub brightness: &'static str,
+    pub home_city: &'static str,
+    pub clock_format: &'static str,
+    pub seconds: &'static str,
+    pub date_order: &'static str,
+    pub hours_12: &'static str,
+    pub hours_24: &'static str,
+    pub off: &'static str,
+    pub on: &'static str,
+    pub dimming: &'static str,
+    pub dim_level: &'static str,
+    pub theme: &'static str,
+    pub theme_light: &'static str,
+    pub theme_dark: &'static str,
+    pub theme_contrast: &'static str,
+    pub auto_dark: &'static str,
+    pub temperature: &'static str,
+    pub refresh: &'static str,
+    pub rotation: &'static str,
+    pub applies_after_restart: &'static str,
+    pub defaults: &'static str,
+    // History pane
+    pub chart_temperature: &'static str,
+    pub chart_pressure: &'static str,
+    pub chart_humidity: &'static str,
+    pub reading_history: &'static str,
+    pub waiting_time_sync: &'static str,
+    pub history_error: &'static str,
+    pub no_history: &'static str,
+    pub history_span: &'static str,
+    pub period_day: &'static str,
+    pub period_week: &'static str,
+    pub period_month: &'static str,
+}
+
+static LOCALE: OnceLock<Locale> = OnceLock::new();
+
+/// Sets the locale of the App, later calls are ignored since the labels are not set again
+pub fn init(locale: Locale) {
+    let _ = LOCALE.set(locale);
+}
+
+pub fn locale() -> Locale {
+    LOCALE.get().copied().unwrap_or_default()
+}
+
+pub fn catalog() -> &'static Catalog {
+    locale().catalog()
+}
+
+/// Messages of the locale
+pub fn text() -> &'static Messages {
+    &catalog().text
+}
+
+/// Replaces the `{}` of a message with the values, in order
+pub fn fill(message: &str, values: &[&dyn Display]) -> String {
+    let mut parts = message.split("{}");
+    let mut filled = parts.next().unwrap_or_default().to_string();
+    let mut values = values.iter();
+
+    for part in parts {
+        if let Some(value) = values.next() {
+            filled += &value.to_string();
+        }
+        filled += part;
+    }
+
+    filled
+}
+
+/// Returns the chrono format with the weekday and month names of the locale for `date` in place
+/// of %a, %A, %b, %h and %B, chrono only has the English names
+pub fn localize(format: &str, date: &impl Datelike) -> String {
+    catalog().localize(format, date)
+}
+
+/// A number with the decimal separator of the locale
+pub fn number(value: f64, decimals: usize) -> String {
+    catalog().number(value, decimals)
+}
+
+/// Replaces the letters the fonts don't have, ä is written ae and á is written a
+pub fn fold_accents(text: &str) -> String {
+    let mut folded = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            'ä' => folded += "ae",
+            'ö' => folded += "oe",
+            'ü' => folded += "ue",
+            'Ä' => folded += "Ae",
+            'Ö' => folded += "Oe",
+            'Ü' => folded += "Ue",
+            'ß' => folded += "ss",
+            'á' | 'à' | 'â' => folded.push('a'),
+            'é' | 'è' | 'ê' => folded.push('e'),
+            'í' | 'ì' | 'î' => folded.push('i'),
+            'ó' | 'ò' | 'ô' => folded.push('o'),
+            'ú' | 'ù' | 'û' => folded.push('u'),
+            'Á' | 'À' | 'Â' => folded.push('A'),
+            'É' | 'È' | 'Ê' => folded.push('E'),
+            'Í' | 'Ì' | 'Î' => folded.push('I'),
+            'Ó' | 'Ò' | 'Ô' => folded.push('O'),
+            'Ú' | 'Ù' | 'Û' => folded.push('U'),
+            'ñ' => folded.push('n'),
+            'Ñ' => folded.push('N'),
+            'ç' => folded.push('c'),
+            '¿' | '¡' => {}
+            c if c.is_ascii() => folded.push(c),
+            _ => folded.push('?'),
+        }
+    }
+    folded
+}
+
+//*****************************************************************************
+//                                 Catalogs
+//*****************************************************************************
+
+static ENGLISH: Catalog = Catalog {
+    api_lang: None,
+    decimal_separator: '.',
+    weekdays: [
+        "Monday",
+        "Tuesday",
+        "Wednesday",
+        "Thursday",
+        "Friday",
+        "Saturday",
+        "Sunday",
+    ],
+    weekdays_short: ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"],
+    months: [
+        "January",
+        "February",
+        "March",
+        "April",
+        "May",
+        "June",
+        "July",
+        "August",
+        "September",
+        "October",
+        "November",
+        "December",
+    ],
+    months_short: [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ],
+    text: Messages {
+        local_time: "Local Time",
+        local_weather: "Local Weather",
+        uv: "UV",
+        feels_like: "Feels Like",
+        aqi: "AQI",
+        wind: "Wind",
+        gust: "Gust",
+        wind_dir: "Dir",
+        local_forecast: "Local Weather Forecast",
+        last_update: "Last update: {}",
+        nav_home: "Home",
+        nav_wifi: "Wifi",
+        nav_cities: "Cities",
+        nav_history: "History",
+        nav_display: "Display",
+        nav_clock: "Clock",
+        app_title: "Weather Forecaster",
+        wifi_disconnected: "Wifi disconnected",
+        wifi_connecting: "Wifi connecting",
+        wifi_down: "Wifi down",
+        ip_address: "IP {}",
+        no_ip_address: "No IP address",
+        time_synced: "Time synced",
+        time_not_synced: "Time not synced",
+        updated_just_now: "Updated just now",
+        updated_minutes_ago: "Updated {}m ago",
+        updated_hours_ago: "Updated {}h {}m ago",
+        not_updated: "Not updated",
+        connecting_wifi: "Trying to connect to Wifi",
+        waiting_forecasts: "Waiting for cities forecasts",
+        updating_firmware: "Updating firmware",
+        firmware_updated: "Firmware updated, restarting",
+        firmware_update_failed: "Firmware update failed",
+        settings: "Settings",
+        wifi_network_name: "Wifi Network Name",
+        password: "Password",
+        status: "Status: {}",
+        status_disconnected: "Disconnected",
+        status_connecting: "Connecting",
+        status_connected: "Connected",
+        status_backing_off: "Not connected, waiting to retry",
+        wifi_connection_failed: "Wifi connection failed check SSID and Password.",
+        edit: "Edit",
+        save: "Save",
+        exit: "Exit",
+        name: "Name",
+        state: "State",
+        zipcode: "Zipcode",
+        timezone: "Timezone",
+        city_row: "City {}:",
+        city_error: "City {} - {}",
+        timezone_not_valid: "Timezone {} is not valid",
+        zipcode_range: "Zipcode must be from 00501 to 99950",
+        zipcode_digits: "Zipcode must be 5 digits",
+        zipcode_not_number: "Zipcode is not a number",
+        brightness: "Brightness",
+        home_city: "Home City",
+        clock_format: "Clock",
+        seconds: "Seconds",
+        date_order: "Date",
+        hours_12: "12h",
+        hours_24: "24h",
+        off: "Off",
+        on: "On",
+        dimming: "Dimming",
+        dim_level: "Dim Level",
+        theme: "Theme",
+        theme_light: "Light",
+        theme_dark: "Dark",
+        theme_contrast: "Contrast",
+        auto_dark: "Auto Dark",
+        temperature: "Temp",
+        refresh: "Refresh",
+        rotation: "Rotation",
+        applies_after_restart: "Rotation applies after restart",
+        defaults: "Defaults",
+        chart_temperature: "Temperature {}",
+        chart_pressure: "Pressure mb",
+        chart_humidity: "Humidity %",
+        reading_history: "Reading the history",
+        waiting_time_sync: "Waiting for the time sync",
+        history_error: "History can't be read - {}",
+        no_history: "No history for {} in the last {}",
+        history_span: "{}, last {} from {} to {} UTC",
+        period_day: "24 hours",
+        period_week: "7 days",
+        period_month: "30 days",
+    },
+};
+
+static SPANISH: Catalog = Catalog {
+    api_lang: Some("es"),
+    decimal_separator: ',',
+    weekdays: [
+        "Lunes",
+        "Martes",
+        "Miercoles",
+        "Jueves",
+        "Viernes",
+        "Sabado",
+        "Domingo",
+    ],
+    weekdays_short: ["Lun", "Mar", "Mie", "Jue", "Vie", "Sab", "Dom"],
+    months: [
+        "Enero",
+        "Febrero",
+        "Marzo",
+        "Abril",
+        "Mayo",
+        "Junio",
+        "Julio",
+        "Agosto",
+        "Septiembre",
+        "Octubre",
+        "Noviembre",
+        "Diciembre",
+    ],
+    months_short: [
+        "Ene", "Feb", "Mar", "Abr", "May", "Jun", "Jul", "Ago", "Sep", "Oct", "Nov", "Dic",
+    ],
+    text: Messages {
+        local_time: "Hora Local",
+        local_weather: "Tiempo Local",
+        uv: "UV",
+        feels_like: "Sensacion",
+        aqi: "ICA",
+        wind: "Viento",
+        gust: "Racha",
+        wind_dir: "Dir",
+        local_forecast: "Pronostico Local",
+        last_update: "Actualizado: {}",
+        nav_home: "Inicio",
+        nav_wifi: "Wifi",
+        nav_cities: "Ciudades",
+        nav_history: "Historial",
+        nav_display: "Pantalla",
+        nav_clock: "Reloj",
+        app_title: "Pronostico del Tiempo",
+        wifi_disconnected: "Wifi desconectado",
+        wifi_connecting: "Wifi conectando",
+        wifi_down: "Wifi caido",
+        ip_address: "IP {}",
+        no_ip_address: "Sin direccion IP",
+        time_synced: "Hora sincronizada",
+        time_not_synced: "Hora sin sincronizar",
+        updated_just_now: "Actualizado ahora",
+        updated_minutes_ago: "Actualizado hace {}m",
+        updated_hours_ago: "Actualizado hace {}h {}m",
+        not_updated: "Sin actualizar",
+        connecting_wifi: "Intentando conectar al Wifi",
+        waiting_forecasts: "Esperando los pronosticos de las ciudades",
+        updating_firmware: "Actualizando el firmware",
+        firmware_updated: "Firmware actualizado, reiniciando",
+        firmware_update_failed: "Fallo la actualizacion del firmware",
+        settings: "Ajustes",
+        wifi_network_name: "Nombre de la red Wifi",
+        password: "Contrasena",
+        status: "Estado: {}",
+        status_disconnected: "Desconectado",
+        status_connecting: "Conectando",
+        status_connected: "Conectado",
+        status_backing_off: "Sin conexion, esperando para reintentar",
+        wifi_connection_failed: "Fallo la conexion wifi, revise el SSID y la contrasena.",
+        edit: "Editar",
+        save: "Guardar",
+        exit: "Salir",
+        name: "Nombre",
+        state: "Estado",
+        zipcode: "Cod. postal",
+        timezone: "Zona horaria",
+        city_row: "Ciudad {}:",
+        city_error: "Ciudad {} - {}",
+        timezone_not_valid: "La zona horaria {} no es valida",
+        zipcode_range: "El codigo postal debe ser de 00501 a 99950",
+        zipcode_digits: "El codigo postal debe tener 5 digitos",
+        zipcode_not_number: "El codigo postal no es un numero",
+        brightness: "Brillo",
+        home_city: "Ciudad Local",
+        clock_format: "Reloj",
+        seconds: "Segundos",
+        date_order: "Fecha",
+        hours_12: "12h",
+        hours_24: "24h",
+        off: "No",
+        on: "Si",
+        dimming: "Atenuar",
+        dim_level: "Nivel Atenuado",
+        theme: "Tema",
+        theme_light: "Claro",
+        theme_dark: "Oscuro",
+        theme_contrast: "Contraste",
+        auto_dark: "Oscuro Auto",
+        temperature: "Temp",
+        refresh: "Actualizar",
+        rotation: "Rotacion",
+        applies_after_restart: "La rotacion se aplica al reiniciar",
+        defaults: "Originales",
+        chart_temperature: "Temperatura {}",
+        chart_pressure: "Presion mb",
+        chart_humidity: "Humedad %",
+        reading_history: "Leyendo el historial",
+        waiting_time_sync: "Esperando la sincronizacion de la hora",
+        history_error: "No se puede leer el historial - {}",
+        no_history: "Sin historial de {} en {}",
+        history_span: "{}, {} del {} al {} UTC",
+        period_day: "24 horas",
+        period_week: "7 dias",
+        period_month: "30 dias",
+    },
+};
+
+static GERMAN: Catalog = Catalog {
+    api_lang: Some("de"),
+    decimal_separator: ',',
+    weekdays: [
+        "Montag",
+        "Dienstag",
+        "Mittwoch",
+        "Donnerstag",
+        "Freitag",
+        "Samstag",
+        "Sonntag",
+    ],
+    weekdays_short: ["Mo", "Di", "Mi", "Do", "Fr", "Sa", "So"],
+    months: [
+        "Januar",
+        "Februar",
+        "Maerz",
+        "April",
+        "Mai",
+        "Juni",
+        "Juli",
+        "August",
+        "September",
+        "Oktober",
+        "November",
+        "Dezember",
+    ],
+    months_short: [
+        "Jan", "Feb", "Mrz", "Apr", "Mai", "Jun", "Jul", "Aug", "Sep", "Okt", "Nov", "Dez",
+    ],
+    text: Messages {
+        local_time: "Ortszeit",
+        local_weather: "Wetter",
+        uv: "UV",
+        feels_like: "Gefuehlt",
+        aqi: "LQI",
+        wind: "Wind",
+        gust: "Boeen",
+        wind_dir: "Richt.",
+        local_forecast: "Wettervorhersage",
+        last_update: "Aktualisiert: {}",
+        nav_home: "Start",
+        nav_wifi: "WLAN",
+        nav_cities: "Orte",
+        nav_history: "Verlauf",
+        nav_display: "Anzeige",
+        nav_clock: "Uhr",
+        app_title: "Wettervorhersage",
+        wifi_disconnected: "WLAN getrennt",
+        wifi_connecting: "WLAN verbindet",
+        wifi_down: "WLAN aus",
+        ip_address: "IP {}",
+        no_ip_address: "Keine IP-Adresse",
+        time_synced: "Zeit synchronisiert",
+        time_not_synced: "Zeit nicht synchronisiert",
+        updated_just_now: "Gerade aktualisiert",
+        updated_minutes_ago: "Vor {}m aktualisiert",
+        updated_hours_ago: "Vor {}h {}m aktualisiert",
+        not_updated: "Nicht aktualisiert",
+        connecting_wifi: "Verbindung zum WLAN wird aufgebaut",
+        waiting_forecasts: "Warte auf die Vorhersagen der Orte",
+        updating_firmware: "Firmware wird aktualisiert",
+        firmware_updated: "Firmware aktualisiert, Neustart",
+        firmware_update_failed: "Firmware-Update fehlgeschlagen",
+        settings: "Zugang",
+        wifi_network_name: "WLAN-Name",
+        password: "Passwort",
+        status: "Status: {}",
+        status_disconnected: "Getrennt",
+        status_connecting: "Verbinde",
+        status_connected: "Verbunden",
+        status_backing_off: "Nicht verbunden, warte auf neuen Versuch",
+        wifi_connection_failed: "WLAN-Verbindung fehlgeschlagen, SSID und Passwort pruefen.",
+        edit: "Bearb.",
+        save: "Sichern",
+        exit: "Zurueck",
+        name: "Name",
+        state: "Staat",
+        zipcode: "PLZ",
+        timezone: "Zeitzone",
+        city_row: "Ort {}:",
+        city_error: "Ort {} - {}",
+        timezone_not_valid: "Zeitzone {} ist ungueltig",
+        zipcode_range: "PLZ muss von 00501 bis 99950 sein",
+        zipcode_digits: "PLZ muss 5 Ziffern haben",
+        zipcode_not_number: "PLZ ist keine Zahl",
+        brightness: "Helligkeit",
+        home_city: "Heimatort",
+        clock_format: "Uhr",
+        seconds: "Sekunden",
+        date_order: "Datum",
+        hours_12: "12h",
+        hours_24: "24h",
+        off: "Aus",
+        on: "Ein",
+        dimming: "Dimmen",
+        dim_level: "Gedimmt",
+        theme: "Design",
+        theme_light: "Hell",
+        theme_dark: "Dunkel",
+        theme_contrast: "Kontrast",
+        auto_dark: "Auto Dunkel",
+        temperature: "Temp",
+        refresh: "Abruf",
+        rotation: "Drehung",
+        applies_after_restart: "Drehung gilt nach Neustart",
+        defaults: "Standard",
+        chart_temperature: "Temperatur {}",
+        chart_pressure: "Luftdruck mb",
+        chart_humidity: "Feuchte %",
+        reading_history: "Verlauf wird gelesen",
+        waiting_time_sync: "Warte auf die Zeitsynchronisation",
+        history_error: "Verlauf nicht lesbar - {}",
+        no_history: "Kein Verlauf fuer {} in den letzten {}",
+        history_span: "{}, letzte {} vom {} bis {} UTC",
+        period_day: "24 Stunden",
+        period_week: "7 Tage",
+        period_month: "30 Tage",
+    },
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn fill_puts_the_values_in_order() {
+        assert_eq!(fill("City {} - {}", &[&2, &"bad"]), "City 2 - bad");
+        assert_eq!(fill("No values", &[&1]), "No values");
+        // A missing value leaves the place empty
+        assert_eq!(fill("{} to {}", &[&"a"]), "a to ");
+        assert_eq!(fill("{}{}", &[]), "");
+    }
+
+    #[test]
+    fn localize_names_the_weekdays_and_months() {
+        // A Monday of March
+        let date = NaiveDate::from_ymd_opt(2026, 3, 9).unwrap();
+
+        assert_eq!(
+            Locale::Spanish
+                .catalog()
+                .localize("%A %d %B, %a %b %h", &date),
+            "Lunes %d Marzo, Lun Mar Mar"
+        );
+        assert_eq!(
+            Locale::German
+                .catalog()
+                .localize("%a %-d. %b %Y %H:%M%%", &date),
+            "Mo %-d. Mrz %Y %H:%M%%"
+        );
+        assert_eq!(
+            Locale::English.catalog().localize("%A %", &date),
+            "Monday %"
+        );
+    }
+
+    #[test]
+    fn number_has_the_decimal_separator_of_the_locale() {
+        assert_eq!(Locale::English.catalog().number(3.46, 1), "3.5");
+        assert_eq!(Locale::Spanish.catalog().number(3.46, 1), "3,5");
+        assert_eq!(Locale::German.catalog().number(72.4, 0), "72");
+    }
+
+    #[test]
+    fn parse_locale() {
+        assert_eq!(Locale::parse("DE"), Ok(Locale::German));
+        assert!(Locale::parse("fr").is_err());
+    }
+}
@@ -0,0 +1,134 @@
+//! The `key=value` settings files of the SDCard, SETTINGS.TXT, MQTT.TXT, OTA.TXT, PROVIDER.TXT
+//! and HISTORY.TXT.
+//!
+//! A file holds one setting per line, blank lines and lines starting with `#` are skipped and the
+//! key and the value are trimmed.  A line without `=` or a value that is not valid is an error and
+//! the whole file is rejected.  An unknown key is logged and skipped, so a file written by a newer
+//! or an older firmware is still read after an update or a rollback.
+use log::warn;
+
+/// Hands every setting of the lines to `set`, which returns `Ok(false)` for a key it doesn't know
+/// and the error message of a value that is not valid.  `file_name` is only used in the log.
+pub fn parse_key_values(
+    file_name: &str,
+    lines: &[String],
+    mut set: impl FnMut(&str, &str) -> Result<bool, String>,
+) -> Result<(), String> {
+    for line in lines {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("Line '{}' is not key=value", line))?;
+        let key = key.trim();
+
+        if !set(key, value.trim())? {
+            warn!("{} unknown setting '{}' skipped", file_name, key);
+        }
+    }
+
+    Ok(())
+}
+
+/// The value of `all` whose name is `value`, ignoring the case, for the settings that are one of
+/// a few names.  `what` is the setting in the error message, "a theme" lists the names of `all`.
+pub fn parse_named<T: Copy>(
+    all: &[T],
+    name: impl Fn(&T) -> &'static str,
+    what: &str,
+    value: &str,
+) -> Result<T, String> {
+    all.iter()
+        .find(|named| name(named).eq_ignore_ascii_case(value))
+        .copied()
+        .ok_or_else(|| {
+            let names: Vec<&str> = all.iter().map(&name).collect();
+            let (last, first) = names.split_last().unwrap_or((&"", &[]));
+            if first.is_empty() {
+                format!("'{}' is not {}, {}", value, what, last)
+            } else {
+                format!(
+                    "'{}' is not {}, {} or {}",
+                    value,
+                    what,
+                    first.join(", "),
+                    last
+                )
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn settings_are_trimmed_and_comments_skipped() {
+        let mut settings = Vec::new();
+        parse_key_values(
+            "TEST.TXT",
+            &lines("# comment\n\n  url = mqtt://host \npass=a=b\n"),
+            |key, value| {
+                settings.push((key.to_string(), value.to_string()));
+                Ok(true)
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            settings,
+            vec![
+                ("url".to_string(), "mqtt://host".to_string()),
+                ("pass".to_string(), "a=b".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_keys_are_skipped() {
+        let mut known = 0;
+        let result = parse_key_values("TEST.TXT", &lines("typo=1\nurl=x"), |key, _| {
+            let is_known = key == "url";
+            known += is_known as u32;
+            Ok(is_known)
+        });
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(known, 1);
+    }
+
+    #[test]
+    fn errors_reject_the_file() {
+        assert_eq!(
+            parse_key_values("TEST.TXT", &lines("no separator"), |_, _| Ok(true)),
+            Err("Line 'no separator' is not key=value".to_string())
+        );
+        assert_eq!(
+            parse_key_values("TEST.TXT", &lines("days=x"), |_, _| Err("bad".to_string())),
+            Err("bad".to_string())
+        );
+    }
+
+    #[test]
+    fn named_values_ignore_the_case() {
+        let all = ["light", "dark", "high_contrast"];
+        let name = |named: &&'static str| *named;
+
+        assert_eq!(parse_named(&all, name, "a theme", "Dark"), Ok("dark"));
+        assert_eq!(
+            parse_named(&all, name, "a theme", "pink"),
+            Err("'pink' is not a theme, light, dark or high_contrast".to_string())
+        );
+        assert_eq!(
+            parse_named(&all[..1], name, "a theme", "pink"),
+            Err("'pink' is not a theme, light".to_string())
+        );
+    }
+}
@@ -0,0 +1,25 @@
+//! The parts of the Weather Forecaster App that don't touch the hardware.
+//!
+//! The settings files, the files of the FAT volume, the translations, the units, the web API, the
+//! MQTT messages and the navigation of the panes build on the host as well as on the ESP32-S3, so
+//! their tests run with `cargo test` on the computer, see the README.  The App crate re-exports the modules, they are
+//! used there as `crate::settings` and so on.
+pub mod brightness;
+pub mod cities;
+pub mod config_storage;
+pub mod date_format;
+pub mod daylight;
+pub mod file_store;
+pub mod history;
+pub mod i18n;
+pub mod key_value;
+pub mod mqtt;
+pub mod nav_stack;
+pub mod orientation;
+pub mod ota;
+pub mod provider;
+pub mod settings;
+pub mod ui_event;
+pub mod units;
+pub mod weather_icons;
+pub mod web_api;
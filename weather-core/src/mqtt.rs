@@ -0,0 +1,472 @@
+//! MQTT publishing of the cities current conditions and the device health.
+//!
+//! The broker settings are read from the MQTT.TXT file on the SDCard, MQTT is disabled when the
+//! file is not there.  The file holds one `key=value` setting per line:
+//!
+//! ```text
+//! url=mqtt://192.168.1.10:1883     mqtt:// or mqtts:// for TLS
+//! user=my user                     optional
+//! pass=my password                 optional
+//! topic=weather_forecaster         optional, base topic, also the Home Assistant node id with
+//!                                  the characters other than letters, digits, _ and - as _
+//! discovery=homeassistant          optional, Home Assistant discovery prefix
+//! ca=MQTT_CA.PEM                   optional, CA certificate of the broker for mqtts://
+//! skip_cn_check=1                  optional, don't check the broker certificate common name
+//! ```
+//!
+//! With mqtts:// the broker certificate is checked against the CA certificate given with `ca=`,
+//! or against the ESP-IDF certificate bundle when no CA certificate is given.
+//!
+//! This file has no hardware so the settings, the topics and the discovery payloads are checked
+//! on the host, the broker connection is made by `MqttPublisher` in mqtt_client.rs.
+use crate::cities::CityInfo;
+use crate::key_value::parse_key_values;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+const DEFAULT_BASE_TOPIC: &str = "weather_forecaster";
+const DEFAULT_DISCOVERY_PREFIX: &str = "homeassistant";
+pub const PAYLOAD_ONLINE: &str = "online";
+pub const PAYLOAD_OFFLINE: &str = "offline";
+
+/// Broker settings read from MQTT.TXT
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MqttConfig {
+    pub url: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub base_topic: String,
+    pub discovery_prefix: String,
+    pub ca_file: Option<String>,
+    pub skip_cn_check: bool,
+}
+
+impl MqttConfig {
+    /// Reads MQTT.TXT, see key_value.rs, returns the error message to log when it is not valid
+    pub fn from_lines(lines: &[String]) -> Result<Self, String> {
+        let mut url = None;
+        let mut config = MqttConfig {
+            url: String::new(),
+            username: None,
+            password: None,
+            base_topic: DEFAULT_BASE_TOPIC.to_string(),
+            discovery_prefix: DEFAULT_DISCOVERY_PREFIX.to_string(),
+            ca_file: None,
+            skip_cn_check: false,
+        };
+
+        parse_key_values("MQTT.TXT", lines, |key, value| {
+            match key {
+                "url" => url = Some(value.to_string()),
+                "user" => config.username = non_empty(value),
+                "pass" => config.password = non_empty(value),
+                "topic" => config.base_topic = value.trim_matches('/').to_string(),
+                "discovery" => config.discovery_prefix = value.trim_matches('/').to_string(),
+                "ca" => config.ca_file = non_empty(value),
+                "skip_cn_check" => config.skip_cn_check = value == "1" || value == "true",
+                _ => return Ok(false),
+            }
+            Ok(true)
+        })?;
+
+        config.url = url.ok_or("Missing url setting")?;
+        if !config.url.starts_with("mqtt://") && !config.url.starts_with("mqtts://") {
+            return Err("url must start with mqtt:// or mqtts://".to_string());
+        }
+
+        if config.base_topic.is_empty()
+            || config.base_topic.contains(['+', '#', ' '])
+            || config.discovery_prefix.is_empty()
+        {
+            return Err("topic and discovery must be valid topic names".to_string());
+        }
+
+        Ok(config)
+    }
+
+    pub fn uses_tls(&self) -> bool {
+        self.url.starts_with("mqtts://")
+    }
+
+    pub fn availability_topic(&self) -> String {
+        format!("{}/availability", self.base_topic)
+    }
+
+    pub fn city_state_topic(&self, city: usize) -> String {
+        format!("{}/city{}/state", self.base_topic, city + 1)
+    }
+
+    pub fn health_topic(&self) -> String {
+        format!("{}/health", self.base_topic)
+    }
+
+    /// Home Assistant node id of the discovery topics and device id, it is a single topic level
+    /// so a base topic like home/weather gives home_weather
+    pub fn node_id(&self) -> String {
+        self.base_topic
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect()
+    }
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+/// Device health published next to the cities conditions
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceHealth {
+    pub uptime_secs: u64,
+    pub free_heap: u32,
+    pub min_free_heap: u32,
+    pub rssi: Option<i8>,
+    pub ip_address: Option<String>,
+}
+
+/// A Home Assistant sensor, `field` is the member of the state JSON holding its value and
+/// `measurement` is false for text sensors
+struct SensorDescription {
+    key: &'static str,
+    name: &'static str,
+    field: &'static str,
+    unit: Option<&'static str>,
+    device_class: Option<&'static str>,
+    measurement: bool,
+}
+
+const CITY_SENSORS: [SensorDescription; 9] = [
+    SensorDescription {
+        key: "temperature",
+        name: "Temperature",
+        field: "temp_f",
+        unit: Some("°F"),
+        device_class: Some("temperature"),
+        measurement: true,
+    },
+    SensorDescription {
+        key: "feels_like",
+        name: "Feels Like",
+        field: "feels_like_f",
+        unit: Some("°F"),
+        device_class: Some("temperature"),
+        measurement: true,
+    },
+    SensorDescription {
+        key: "humidity",
+        name: "Humidity",
+        field: "humidity",
+        unit: Some("%"),
+        device_class: Some("humidity"),
+        measurement: true,
+    },
+    SensorDescription {
+        key: "pressure",
+        name: "Pressure",
+        field: "pressure_mb",
+        unit: Some("mbar"),
+        device_class: Some("pressure"),
+        measurement: true,
+    },
+    SensorDescription {
+        key: "wind_speed",
+        name: "Wind Speed",
+        field: "wind_mph",
+        unit: Some("mph"),
+        device_class: Some("wind_speed"),
+        measurement: true,
+    },
+    SensorDescription {
+        key: "wind_gust",
+        name: "Wind Gust",
+        field: "wind_gust_mph",
+        unit: Some("mph"),
+        device_class: Some("wind_speed"),
+        measurement: true,
+    },
+    SensorDescription {
+        key: "uv",
+        name: "UV Index",
+        field: "uv",
+        unit: None,
+        device_class: None,
+        measurement: true,
+    },
+    SensorDescription {
+        key: "aqi",
+        name: "Air Quality Index",
+        field: "aqi",
+        unit: None,
+        device_class: Some("aqi"),
+        measurement: true,
+    },
+    SensorDescription {
+        key: "condition",
+        name: "Condition",
+        field: "condition",
+        unit: None,
+        device_class: None,
+        measurement: false,
+    },
+];
+
+const HEALTH_SENSORS: [SensorDescription; 3] = [
+    SensorDescription {
+        key: "uptime",
+        name: "Uptime",
+        field: "uptime_secs",
+        unit: Some("s"),
+        device_class: Some("duration"),
+        measurement: true,
+    },
+    SensorDescription {
+        key: "free_heap",
+        name: "Free Heap",
+        field: "free_heap",
+        unit: Some("B"),
+        device_class: Some("data_size"),
+        measurement: true,
+    },
+    SensorDescription {
+        key: "rssi",
+        name: "Wifi Signal",
+        field: "rssi",
+        unit: Some("dBm"),
+        device_class: Some("signal_strength"),
+        measurement: true,
+    },
+];
+
+/// Builds the retained Home Assistant discovery messages as (topic, payload) pairs
+pub fn discovery_messages(config: &MqttConfig, cities_info: &[CityInfo]) -> Vec<(String, String)> {
+    let device = json!({
+        "identifiers": [config.node_id()],
+        "name": "Weather Forecaster",
+        "model": "ESP32-8048S070",
+        "manufacturer": "Sunton",
+    });
+
+    let mut messages = Vec::new();
+
+    for (city, city_info) in cities_info.iter().enumerate() {
+        let state_topic = config.city_state_topic(city);
+
+        for sensor in CITY_SENSORS.iter() {
+            let object_id = format!("city{}_{}", city + 1, sensor.key);
+            let name = format!("{} {}", city_info.city_name, sensor.name);
+            let payload = sensor_payload(config, sensor, &object_id, &name, &state_topic, &device);
+            messages.push((discovery_topic(config, &object_id), payload.to_string()));
+        }
+    }
+
+    let health_topic = config.health_topic();
+    for sensor in HEALTH_SENSORS.iter() {
+        let mut payload = sensor_payload(
+            config,
+            sensor,
+            sensor.key,
+            sensor.name,
+            &health_topic,
+            &device,
+        );
+        payload["entity_category"] = json!("diagnostic");
+        messages.push((discovery_topic(config, sensor.key), payload.to_string()));
+    }
+
+    messages
+}
+
+fn discovery_topic(config: &MqttConfig, object_id: &str) -> String {
+    format!(
+        "{}/sensor/{}/{}/config",
+        config.discovery_prefix,
+        config.node_id(),
+        object_id
+    )
+}
+
+fn sensor_payload(
+    config: &MqttConfig,
+    sensor: &SensorDescription,
+    object_id: &str,
+    name: &str,
+    state_topic: &str,
+    device: &Value,
+) -> Value {
+    let mut payload = json!({
+        "name": name,
+        "unique_id": format!("{}_{}", config.node_id(), object_id),
+        "state_topic": state_topic,
+        "value_template": format!("{{{{ value_json.{} }}}}", sensor.field),
+        "availability_topic": config.availability_topic(),
+        "device": device,
+    });
+
+    if let Some(unit) = sensor.unit {
+        payload["unit_of_measurement"] = json!(unit);
+    }
+
+    if sensor.measurement {
+        payload["state_class"] = json!("measurement");
+    }
+
+    if let Some(device_class) = sensor.device_class {
+        payload["device_class"] = json!(device_class);
+    }
+
+    payload
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cities::CityConditions;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    fn config() -> MqttConfig {
+        MqttConfig::from_lines(&lines("url=mqtt://192.168.1.10:1883")).unwrap()
+    }
+
+    fn city(city_name: &str) -> CityInfo {
+        CityInfo {
+            city_name: city_name.to_string(),
+            state: "CA".to_string(),
+            zipcode: "90079".to_string(),
+            timezone: "US/Pacific".to_string(),
+        }
+    }
+
+    #[test]
+    fn mqtt_txt_settings() {
+        let config = MqttConfig::from_lines(&lines(
+            "url=mqtts://broker:8883\nuser=me\npass=\ntopic=/home/weather/\nca=CA.PEM\nskip_cn_check=1",
+        ))
+        .unwrap();
+
+        assert!(config.uses_tls());
+        assert_eq!(config.username.as_deref(), Some("me"));
+        assert_eq!(config.password, None);
+        assert_eq!(config.base_topic, "home/weather");
+        assert_eq!(config.discovery_prefix, DEFAULT_DISCOVERY_PREFIX);
+        assert_eq!(config.ca_file.as_deref(), Some("CA.PEM"));
+        assert!(config.skip_cn_check);
+
+        assert_eq!(config.availability_topic(), "home/weather/availability");
+        assert_eq!(config.city_state_topic(0), "home/weather/city1/state");
+        assert_eq!(config.health_topic(), "home/weather/health");
+    }
+
+    #[test]
+    fn mqtt_txt_errors() {
+        assert!(MqttConfig::from_lines(&lines("user=me")).is_err());
+        assert!(MqttConfig::from_lines(&lines("url=http://broker")).is_err());
+        assert!(MqttConfig::from_lines(&lines("url=mqtt://broker\ntopic=a/#")).is_err());
+        assert!(MqttConfig::from_lines(&lines("url=mqtt://broker\ndiscovery=/")).is_err());
+    }
+
+    #[test]
+    fn discovery_topics_and_payloads() {
+        let config = config();
+        let messages = discovery_messages(&config, &[city("Los Angeles"), city("San Diego")]);
+        assert_eq!(
+            messages.len(),
+            2 * CITY_SENSORS.len() + HEALTH_SENSORS.len()
+        );
+
+        let (topic, payload) = &messages[CITY_SENSORS.len()];
+        assert_eq!(
+            topic,
+            "homeassistant/sensor/weather_forecaster/city2_temperature/config"
+        );
+        let payload: Value = serde_json::from_str(payload).unwrap();
+        assert_eq!(payload["name"], "San Diego Temperature");
+        assert_eq!(payload["unique_id"], "weather_forecaster_city2_temperature");
+        assert_eq!(payload["state_topic"], "weather_forecaster/city2/state");
+        assert_eq!(payload["value_template"], "{{ value_json.temp_f }}");
+        assert_eq!(
+            payload["availability_topic"],
+            "weather_forecaster/availability"
+        );
+        assert_eq!(payload["unit_of_measurement"], "°F");
+        assert_eq!(payload["state_class"], "measurement");
+        assert_eq!(payload["device"]["identifiers"][0], "weather_forecaster");
+
+        let (topic, payload) = messages.last().unwrap();
+        assert_eq!(topic, "homeassistant/sensor/weather_forecaster/rssi/config");
+        let payload: Value = serde_json::from_str(payload).unwrap();
+        assert_eq!(payload["state_topic"], "weather_forecaster/health");
+        assert_eq!(payload["entity_category"], "diagnostic");
+    }
+
+    #[test]
+    fn base_topic_with_levels_gives_a_valid_node_id() {
+        let config =
+            MqttConfig::from_lines(&lines("url=mqtt://broker\ntopic=home/weather.station"))
+                .unwrap();
+        assert_eq!(config.node_id(), "home_weather_station");
+
+        let messages = discovery_messages(&config, &[city("Los Angeles")]);
+        let (topic, payload) = &messages[0];
+        assert_eq!(
+            topic,
+            "homeassistant/sensor/home_weather_station/city1_temperature/config"
+        );
+        assert_eq!(topic.split('/').count(), 5);
+
+        let payload: Value = serde_json::from_str(payload).unwrap();
+        assert_eq!(
+            payload["unique_id"],
+            "home_weather_station_city1_temperature"
+        );
+        assert_eq!(payload["device"]["identifiers"][0], "home_weather_station");
+        assert_eq!(payload["state_topic"], "home/weather.station/city1/state");
+    }
+
+    // The value template of every sensor must name a member of the state payload
+    #[test]
+    fn sensor_fields_are_in_the_state_payloads() {
+        let conditions = serde_json::to_value(CityConditions {
+            temp_f: 72.5,
+            feels_like_f: 71.0,
+            humidity: 40,
+            pressure_mb: 1013.0,
+            wind_mph: 5.6,
+            wind_gust_mph: 9.4,
+            wind_dir: "WSW".to_string(),
+            uv: 6.0,
+            aqi: 2,
+            condition: "Sunny".to_string(),
+            last_updated_epoch: 1_790_000_000,
+        })
+        .unwrap();
+        for sensor in CITY_SENSORS.iter() {
+            assert!(conditions.get(sensor.field).is_some(), "{}", sensor.field);
+        }
+
+        let health = serde_json::to_value(DeviceHealth {
+            uptime_secs: 10,
+            free_heap: 100_000,
+            min_free_heap: 90_000,
+            rssi: Some(-60),
+            ip_address: None,
+        })
+        .unwrap();
+        for sensor in HEALTH_SENSORS.iter() {
+            assert!(health.get(sensor.field).is_some(), "{}", sensor.field);
+        }
+    }
+}
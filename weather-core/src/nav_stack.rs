@@ -0,0 +1,276 @@
+//! The navigation stack of the panes.
+//!
+//! The `PaneRouter` shows the pane on top of the stack.  A nav bar button and the requests of
+//! the model that show a pane start a new stack, a pane that handled an event pushes the pane it
+//! shows or goes back, `pane_navigation` tells where.  The stack has no LVGL objects so an event
+//! sequence can be checked on the host.
+use crate::ui_event::{Button, UiEvent};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaneId {
+    Startup,
+    Home,
+    WifiSettings,
+    CitiesSettings,
+    History,
+    DisplaySettings,
+    Clock,
+}
+
+/// Where the router goes once a pane has handled its events
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Navigation {
+    /// Show the pane on top of the current one
+    Show(PaneId),
+    /// Go back to the previous pane, the Home pane when there is none
+    Back,
+}
+
+// The pane at the bottom of the navigation stack
+pub const ROOT_PANE: PaneId = PaneId::Home;
+
+/// Where the pane `id` goes once it has handled `event`, a settings pane goes back on its Save
+/// and Exit buttons and the Clock pane on a tap.  A settings pane stays when the settings to save
+/// are not valid, it checks them before.
+pub fn pane_navigation(id: PaneId, event: &UiEvent) -> Option<Navigation> {
+    match (id, event) {
+        (
+            PaneId::WifiSettings | PaneId::CitiesSettings | PaneId::DisplaySettings,
+            UiEvent::ButtonClicked(pane, Button::Save | Button::Exit),
+        ) if *pane == id => Some(Navigation::Back),
+        (PaneId::Clock, UiEvent::ClockClicked) => Some(Navigation::Back),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NavStack {
+    // Pane of each nav bar button
+    nav_bar: Vec<PaneId>,
+    // The pane shown is the last one
+    stack: Vec<PaneId>,
+}
+
+impl NavStack {
+    /// `nav_bar` has the pane of each nav bar button, in the order of the buttons
+    pub fn new(nav_bar: Vec<PaneId>) -> Self {
+        Self {
+            nav_bar,
+            stack: Vec::new(),
+        }
+    }
+
+    /// The pane shown, None before the first pane is shown
+    pub fn top(&self) -> Option<PaneId> {
+        self.stack.last().copied()
+    }
+
+    /// Index of the nav bar button of `id`
+    pub fn nav_button(&self, id: PaneId) -> Option<usize> {
+        self.nav_bar.iter().position(|nav_id| *nav_id == id)
+    }
+
+    /// Starts a new stack with `id` on top of the Home pane, or only `id` for the Home and the
+    /// Startup panes
+    pub fn reset(&mut self, id: PaneId) {
+        self.stack = match id {
+            ROOT_PANE | PaneId::Startup => vec![id],
+            _ => vec![ROOT_PANE, id],
+        };
+    }
+
+    /// The clock is shown on top of the stack, but never over the Startup pane
+    pub fn show_clock(&mut self) {
+        if self.top() != Some(PaneId::Startup) {
+            self.navigate(Navigation::Show(PaneId::Clock));
+        }
+    }
+
+    /// A nav bar button starts a new stack, returns false for the events of the panes
+    pub fn handle_event(&mut self, event: &UiEvent) -> bool {
+        let UiEvent::NavButtonPressed(btn_id) = event else {
+            return false;
+        };
+
+        if let Some(id) = self.nav_bar.get(*btn_id as usize).copied() {
+            self.reset(id);
+        }
+        true
+    }
+
+    /// Where a pane goes once it handled an event
+    pub fn navigate(&mut self, navigation: Navigation) {
+        match navigation {
+            Navigation::Show(id) => {
+                self.stack.retain(|shown| *shown != id);
+                self.stack.push(id);
+            }
+            Navigation::Back => {
+                self.stack.pop();
+                if self.stack.is_empty() {
+                    self.stack.push(ROOT_PANE);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAV_BAR: [PaneId; 5] = [
+        PaneId::Home,
+        PaneId::WifiSettings,
+        PaneId::CitiesSettings,
+        PaneId::History,
+        PaneId::DisplaySettings,
+    ];
+
+    const PANES: [PaneId; 7] = [
+        PaneId::Startup,
+        PaneId::Home,
+        PaneId::WifiSettings,
+        PaneId::CitiesSettings,
+        PaneId::History,
+        PaneId::DisplaySettings,
+        PaneId::Clock,
+    ];
+
+    // Hands the events to the stack and then to every pane the way the router does
+    fn dispatch(nav_stack: &mut NavStack, events: &[UiEvent]) {
+        for event in events {
+            if nav_stack.handle_event(event) {
+                continue;
+            }
+
+            for id in PANES {
+                if let Some(navigation) = pane_navigation(id, event) {
+                    nav_stack.navigate(navigation);
+                }
+            }
+        }
+    }
+
+    fn home() -> NavStack {
+        let mut nav_stack = NavStack::new(NAV_BAR.to_vec());
+        nav_stack.reset(PaneId::Home);
+        nav_stack
+    }
+
+    #[test]
+    fn nav_button_shows_its_pane_over_home() {
+        let mut nav_stack = home();
+
+        dispatch(&mut nav_stack, &[UiEvent::NavButtonPressed(1)]);
+        assert_eq!(nav_stack.stack, vec![PaneId::Home, PaneId::WifiSettings]);
+        assert_eq!(nav_stack.nav_button(PaneId::WifiSettings), Some(1));
+
+        // Another button starts a new stack, an unknown one is ignored
+        dispatch(
+            &mut nav_stack,
+            &[UiEvent::NavButtonPressed(3), UiEvent::NavButtonPressed(9)],
+        );
+        assert_eq!(nav_stack.stack, vec![PaneId::Home, PaneId::History]);
+
+        dispatch(&mut nav_stack, &[UiEvent::NavButtonPressed(0)]);
+        assert_eq!(nav_stack.stack, vec![PaneId::Home]);
+    }
+
+    #[test]
+    fn save_goes_back_to_home() {
+        let mut nav_stack = home();
+
+        dispatch(
+            &mut nav_stack,
+            &[
+                UiEvent::NavButtonPressed(1),
+                UiEvent::ButtonClicked(PaneId::WifiSettings, Button::Save),
+            ],
+        );
+        assert_eq!(nav_stack.top(), Some(PaneId::Home));
+
+        // Back from Home stays on Home
+        dispatch(
+            &mut nav_stack,
+            &[UiEvent::ButtonClicked(PaneId::WifiSettings, Button::Exit)],
+        );
+        assert_eq!(nav_stack.stack, vec![PaneId::Home]);
+    }
+
+    #[test]
+    fn clock_tap_goes_back_to_the_pane_under_it() {
+        let mut nav_stack = home();
+
+        dispatch(&mut nav_stack, &[UiEvent::NavButtonPressed(2)]);
+        nav_stack.show_clock();
+        assert_eq!(nav_stack.top(), Some(PaneId::Clock));
+
+        // The clock is not stacked twice
+        nav_stack.show_clock();
+        assert_eq!(
+            nav_stack.stack,
+            vec![PaneId::Home, PaneId::CitiesSettings, PaneId::Clock]
+        );
+
+        dispatch(&mut nav_stack, &[UiEvent::ClockClicked]);
+        assert_eq!(nav_stack.top(), Some(PaneId::CitiesSettings));
+    }
+
+    #[test]
+    fn settings_panes_go_back_on_save_and_exit() {
+        for id in [
+            PaneId::WifiSettings,
+            PaneId::CitiesSettings,
+            PaneId::DisplaySettings,
+        ] {
+            for button in [Button::Save, Button::Exit] {
+                let event = UiEvent::ButtonClicked(id, button);
+                assert_eq!(pane_navigation(id, &event), Some(Navigation::Back));
+
+                // Only the pane of the button goes back
+                let others = PANES.iter().filter(|other| **other != id);
+                assert!(others
+                    .map(|other| pane_navigation(*other, &event))
+                    .all(|navigation| navigation.is_none()));
+            }
+
+            for button in [Button::Edit, Button::Defaults] {
+                let event = UiEvent::ButtonClicked(id, button);
+                assert_eq!(pane_navigation(id, &event), None);
+            }
+        }
+    }
+
+    #[test]
+    fn only_the_clock_goes_back_on_a_tap() {
+        assert_eq!(
+            pane_navigation(PaneId::Clock, &UiEvent::ClockClicked),
+            Some(Navigation::Back)
+        );
+        assert_eq!(pane_navigation(PaneId::Home, &UiEvent::ClockClicked), None);
+        assert_eq!(
+            pane_navigation(PaneId::Clock, &UiEvent::HistoryCityPressed(0)),
+            None
+        );
+        assert_eq!(
+            pane_navigation(
+                PaneId::History,
+                &UiEvent::ButtonClicked(PaneId::History, Button::Exit)
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn clock_is_not_shown_over_startup() {
+        let mut nav_stack = NavStack::new(NAV_BAR.to_vec());
+        nav_stack.reset(PaneId::Startup);
+
+        nav_stack.show_clock();
+        assert_eq!(nav_stack.stack, vec![PaneId::Startup]);
+
+        nav_stack.reset(PaneId::WifiSettings);
+        assert_eq!(nav_stack.stack, vec![PaneId::Home, PaneId::WifiSettings]);
+    }
+}
@@ -0,0 +1,72 @@
+//! Orientation of the screen.
+//!
+//! The panel is 800 x 480 in landscape, set with `orientation=` in SETTINGS.TXT, the Display pane
+//! or the web API.  The display, the touchscreen and the layout of the panes follow it from the
+//! next start since the panes are laid out once.
+use crate::key_value;
+
+/// Represents the orientation of the device.  Landscape is the orientation of the panel, Portrait
+/// is the panel turned a quarter turn clockwise so its left edge is at the top.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Orientation {
+    Portrait,
+    #[default]
+    Landscape,
+    InvertedPortrait,
+    InvertedLandscape,
+}
+
+impl Orientation {
+    pub const ALL: [Orientation; 4] = [
+        Orientation::Landscape,
+        Orientation::Portrait,
+        Orientation::InvertedLandscape,
+        Orientation::InvertedPortrait,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Orientation::Portrait => "portrait",
+            Orientation::Landscape => "landscape",
+            Orientation::InvertedPortrait => "inverted_portrait",
+            Orientation::InvertedLandscape => "inverted_landscape",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        key_value::parse_named(&Self::ALL, Self::as_str, "an orientation", value)
+    }
+
+    /// Clockwise turn of the panel from landscape, shown on the Display pane
+    pub fn degrees(&self) -> u16 {
+        match self {
+            Orientation::Landscape => 0,
+            Orientation::Portrait => 90,
+            Orientation::InvertedLandscape => 180,
+            Orientation::InvertedPortrait => 270,
+        }
+    }
+
+    pub fn is_portrait(&self) -> bool {
+        matches!(self, Orientation::Portrait | Orientation::InvertedPortrait)
+    }
+
+    /// Dimensions of the screen in this orientation for a panel of `size`
+    pub fn screen_size(&self, size: Dimension) -> Dimension {
+        if self.is_portrait() {
+            Dimension {
+                height: size.width,
+                width: size.height,
+            }
+        } else {
+            size
+        }
+    }
+}
+
+/// Represents the dimensions of the device
+#[derive(Copy, Clone, Debug)]
+pub struct Dimension {
+    pub height: u16,
+    pub width: u16,
+}
@@ -0,0 +1,187 @@
+//! Over-the-air firmware updates.
+//!
+//! The flash holds two OTA app slots, an update is written to the slot that is not running while
+//! the App keeps running from the other one.  The firmware image is downloaded from the url in the
+//! OTA.TXT file on the SDCard:
+//!
+//! ```text
+//! url=http://192.168.1.10:8000/weather-forecaster.bin
+//! sha256=9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08   or
+//! sha256_url=https://example.com/weather-forecaster.bin.sha256
+//! ```
+//!
+//! The expected SHA-256 of the image is either given in OTA.TXT with `sha256`, or read from
+//! `sha256_url` in the format written by `sha256sum`.  A hash downloaded over plain http could be
+//! swapped along with the image, so `sha256_url` must be an https:// url; without either setting
+//! the image url with `.sha256` added is used when it is https://.  The update is only completed
+//! when the hash of the downloaded image matches, otherwise the new slot is left untouched and the
+//! App keeps running.  The hash checks that the image is complete and is the one intended by
+//! whoever controls the SDCard or the https server, the image itself is not signed.
+//!
+//! This file has no hardware so the settings are checked on the host, the image is downloaded
+//! and installed by ota_update.rs of the App.
+use crate::key_value::parse_key_values;
+
+/// Where the expected SHA-256 of the image comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageHash {
+    /// Given in OTA.TXT
+    Pinned([u8; 32]),
+    /// Downloaded from an https:// url
+    Url(String),
+}
+
+/// Firmware location read from OTA.TXT
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OtaConfig {
+    pub url: String,
+    pub hash: ImageHash,
+}
+
+impl OtaConfig {
+    /// Reads OTA.TXT, see key_value.rs, returns the error message to log when it is not valid
+    pub fn from_lines(lines: &[String]) -> Result<Self, String> {
+        let mut url = None;
+        let mut sha256 = None;
+        let mut sha256_url = None;
+
+        parse_key_values("OTA.TXT", lines, |key, value| {
+            match key {
+                "url" => url = Some(value.to_string()),
+                "sha256" => sha256 = Some(parse_sha256(value)?),
+                "sha256_url" => sha256_url = Some(value.to_string()),
+                _ => return Ok(false),
+            }
+            Ok(true)
+        })?;
+
+        let url: String = url.ok_or("Missing url setting")?;
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(format!("{} is not a http:// or https:// url", url));
+        }
+
+        let hash = match (sha256, sha256_url) {
+            (Some(_), Some(_)) => return Err("Set either sha256 or sha256_url".to_string()),
+            (Some(sha256), None) => ImageHash::Pinned(sha256),
+            (None, Some(sha256_url)) => ImageHash::Url(sha256_url),
+            (None, None) => ImageHash::Url(format!("{}.sha256", url)),
+        };
+
+        if let ImageHash::Url(sha256_url) = &hash {
+            if !sha256_url.starts_with("https://") {
+                return Err(format!(
+                    "{} is not a https:// url, set sha256 for a http:// image",
+                    sha256_url
+                ));
+            }
+        }
+
+        Ok(Self { url, hash })
+    }
+}
+
+/// Parses a SHA-256 written by `sha256sum`, the hex digest optionally followed by the file name
+pub fn parse_sha256(text: &str) -> Result<[u8; 32], String> {
+    let hex = text.split_whitespace().next().unwrap_or("");
+    // from_str_radix would take a sign in front of a digit
+    if hex.len() != 64 || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return Err("SHA-256 must be 64 hex digits".to_string());
+    }
+
+    let mut hash = [0u8; 32];
+    for (i, byte) in hash.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+            .map_err(|_| "SHA-256 must be 64 hex digits".to_string())?;
+    }
+
+    Ok(hash)
+}
+
+/// Percent of the image downloaded, None when the server did not send the image size
+pub fn progress_percent(bytes_written: usize, image_size: Option<usize>) -> Option<u8> {
+    match image_size {
+        Some(size) if size > 0 => Some((bytes_written.min(size) * 100 / size) as u8),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHA256: &str = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn sha256_as_written_by_sha256sum() {
+        let hash = parse_sha256(&format!("{}  weather-forecaster.bin\n", SHA256)).unwrap();
+        assert_eq!(hash[0], 0x9f);
+        assert_eq!(hash[31], 0x08);
+
+        assert_eq!(parse_sha256(&SHA256.to_uppercase()), Ok(hash));
+    }
+
+    #[test]
+    fn malformed_sha256_is_an_error() {
+        assert!(parse_sha256("").is_err());
+        assert!(parse_sha256(&SHA256[..62]).is_err());
+        assert!(parse_sha256(&format!("{}00", SHA256)).is_err());
+        assert!(parse_sha256(&SHA256.replace('9', "g")).is_err());
+        assert!(parse_sha256(&SHA256.replacen("9f", "+f", 1)).is_err());
+        assert!(parse_sha256(&SHA256.replacen("9f", "é", 1)).is_err());
+    }
+
+    #[test]
+    fn ota_txt_settings() {
+        let config = OtaConfig::from_lines(&lines(&format!(
+            "url=http://192.168.1.10:8000/app.bin\nsha256={}",
+            SHA256
+        )))
+        .unwrap();
+        assert_eq!(
+            config.hash,
+            ImageHash::Pinned(parse_sha256(SHA256).unwrap())
+        );
+
+        // Without a hash setting the hash is next to a https:// image
+        let config = OtaConfig::from_lines(&lines("url=https://example.com/app.bin")).unwrap();
+        assert_eq!(
+            config.hash,
+            ImageHash::Url("https://example.com/app.bin.sha256".to_string())
+        );
+    }
+
+    #[test]
+    fn ota_txt_errors() {
+        assert!(
+            OtaConfig::from_lines(&lines("sha256_url=https://example.com/app.sha256")).is_err()
+        );
+        assert!(OtaConfig::from_lines(&lines("url=ftp://example.com/app.bin")).is_err());
+        assert!(OtaConfig::from_lines(&lines("url=http://example.com/app.bin")).is_err());
+        assert!(OtaConfig::from_lines(&lines(
+            "url=https://example.com/app.bin\nsha256_url=http://example.com/app.sha256"
+        ))
+        .is_err());
+        assert!(OtaConfig::from_lines(&lines(&format!(
+            "url=https://example.com/app.bin\nsha256={}\nsha256_url=https://example.com/app.sha256",
+            SHA256
+        )))
+        .is_err());
+        assert!(
+            OtaConfig::from_lines(&lines("url=https://example.com/app.bin\nsha256=12")).is_err()
+        );
+    }
+
+    #[test]
+    fn download_progress() {
+        assert_eq!(progress_percent(0, Some(1000)), Some(0));
+        assert_eq!(progress_percent(999, Some(1000)), Some(99));
+        assert_eq!(progress_percent(1000, Some(1000)), Some(100));
+        assert_eq!(progress_percent(2000, Some(1000)), Some(100));
+        assert_eq!(progress_percent(500, Some(0)), None);
+        assert_eq!(progress_percent(500, None), None);
+    }
+}
@@ -0,0 +1,114 @@
+//! Weather provider connection settings.
+//!
+//! The forecasts are fetched from weatherapi.com over HTTPS, the server certificate is checked
+//! against the ESP-IDF certificate bundle.  The optional PROVIDER.TXT file on the SDCard changes
+//! that, it holds one `key=value` setting per line:
+//!
+//! ```text
+//! base_url=https://192.168.1.10:8443/v1     optional, default https://api.weatherapi.com/v1
+//! cert=PROVIDER.PEM                         optional, the only certificate trusted
+//! ```
+//!
+//! `cert` is a PEM file on the SDCard holding either a custom CA certificate or the self-signed
+//! certificate of the server itself, which pins the connection to that server.  When it is given
+//! the certificate bundle is not used.
+use crate::key_value::parse_key_values;
+
+pub const DEFAULT_BASE_URL: &str = "https://api.weatherapi.com/v1";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProviderConfig {
+    pub base_url: String,
+    pub cert_file: Option<String>,
+}
+
+impl Default for ProviderConfig {
+    fn default() -> Self {
+        Self {
+            base_url: DEFAULT_BASE_URL.to_string(),
+            cert_file: None,
+        }
+    }
+}
+
+impl ProviderConfig {
+    /// Reads PROVIDER.TXT, see key_value.rs, returns the error message to log when it is not valid
+    pub fn from_lines(lines: &[String]) -> Result<Self, String> {
+        let mut config = Self::default();
+
+        parse_key_values("PROVIDER.TXT", lines, |key, value| {
+            match key {
+                "base_url" => config.base_url = value.trim_end_matches('/').to_string(),
+                "cert" if !value.is_empty() => config.cert_file = Some(value.to_string()),
+                "cert" => config.cert_file = None,
+                _ => return Ok(false),
+            }
+            Ok(true)
+        })?;
+
+        if !config.base_url.starts_with("https://") && !config.base_url.starts_with("http://") {
+            return Err(format!(
+                "{} is not a https:// or http:// url",
+                config.base_url
+            ));
+        }
+
+        Ok(config)
+    }
+
+    pub fn uses_tls(&self) -> bool {
+        self.base_url.starts_with("https://")
+    }
+
+    /// `lang` translates the condition text, English without it
+    pub fn forecast_url(&self, api_key: &str, zipcode: &str, lang: Option<&str>) -> String {
+        let url = format!(
+            "{}/forecast.json?key={}&q={}&days=3&aqi=yes&alerts=no",
+            self.base_url, api_key, zipcode
+        );
+        match lang {
+            Some(lang) => format!("{}&lang={}", url, lang),
+            None => url,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn weatherapi_over_https_by_default() {
+        let config = ProviderConfig::from_lines(&[]).unwrap();
+        assert_eq!(config, ProviderConfig::default());
+        assert!(config.uses_tls());
+        assert_eq!(
+            config.forecast_url("KEY", "90079", None),
+            "https://api.weatherapi.com/v1/forecast.json?key=KEY&q=90079&days=3&aqi=yes&alerts=no"
+        );
+    }
+
+    #[test]
+    fn provider_txt_settings() {
+        let config = ProviderConfig::from_lines(&lines(
+            "base_url=http://192.168.1.10:8080/v1/\ncert=PROVIDER.PEM",
+        ))
+        .unwrap();
+        assert!(!config.uses_tls());
+        assert_eq!(config.cert_file.as_deref(), Some("PROVIDER.PEM"));
+
+        // The trailing slash is not doubled
+        assert_eq!(
+            config.forecast_url("KEY", "90079", Some("es")),
+            "http://192.168.1.10:8080/v1/forecast.json?key=KEY&q=90079&days=3&aqi=yes&alerts=no&lang=es"
+        );
+
+        let config = ProviderConfig::from_lines(&lines("cert=")).unwrap();
+        assert_eq!(config.cert_file, None);
+        assert!(ProviderConfig::from_lines(&lines("base_url=ftp://example.com")).is_err());
+    }
+}
@@ -0,0 +1,390 @@
+//! App settings that are not covered by the other settings files.
+//!
+//! They are kept in SETTINGS.TXT, one `key=value` setting per line.  The file is written by the
+//! App when a setting is changed, a missing file or setting takes the default:
+//!
+//! ```text
+//! log_level=info      level of the records written to the log files, off, error, warn, info,
+//!                     debug or trace
+//! log_files=4         number of log files kept, from 1 to 9
+//! log_file_kb=256     size of a log file before the next one is started, from 16 to 4096
+//! theme=dark          colors of the panes, light, dark or high_contrast
+//! auto_dark=false     dark theme at night in the home city, true or false
+//! brightness=100      backlight brightness in percent, from 10 to 100
+//! dim_after_min=10    minutes without a touch before the backlight is dimmed, 0 never dims
+//! dim_brightness=30   brightness once dimmed, from 0 to 100
+//! night_brightness=10 brightness at night without a touch, 0 turns the screen off
+//! orientation=landscape
+//!                     orientation of the display, landscape, portrait, inverted_landscape or
+//!                     inverted_portrait, used from the next start
+//! locale=en           language of the UI and of the condition text, en, es or de, used from
+//!                     the next start
+//! clock_after_min=0   minutes without a touch before the clock is shown, 0 never shows it
+//! time_24h=false      24 hour clock, true or false
+//! show_seconds=false  seconds on the clocks, true or false
+//! date_order=us       order of the dates, us, iso or eu
+//! temp_unit=f         unit of the temperatures, f or c
+//! wind_unit=mph       unit of the wind speeds, mph or kph
+//! refresh_min=10      minutes between the forecasts updates, from 5 to 120
+//! home_city=1         city whose sunrise and sunset make the night, from 1 to 4
+//! night_from_sun=true night from the sunset to the sunrise of the home city, false uses the
+//!                     fixed night window below, which is also used until there is a forecast
+//! night_start=22:00   fixed night window in the local time of the home city, the same start
+//! night_end=07:00     and end is no night window
+//! ```
+//!
+//! Settings the App does not know are skipped, they may have been written by a newer firmware,
+//! see key_value.rs.
+use crate::date_format::{DateFormat, DateOrder};
+use crate::i18n::Locale;
+use crate::key_value::{self, parse_key_values};
+use crate::orientation::Orientation;
+use crate::units::{TempUnit, WindUnit};
+
+use chrono::NaiveTime;
+use core::time::Duration;
+use log::LevelFilter;
+use serde::Serialize;
+
+pub const DEFAULT_LOG_LEVEL: LevelFilter = LevelFilter::Info;
+pub const DEFAULT_LOG_FILES: usize = 4;
+pub const DEFAULT_LOG_FILE_KB: u32 = 256;
+pub const DEFAULT_THEME: ThemeName = ThemeName::Dark;
+pub const DEFAULT_BRIGHTNESS: u8 = 100;
+pub const MIN_BRIGHTNESS: u8 = 10;
+pub const CITIES_COUNT: usize = 4;
+pub const DEFAULT_REFRESH_MIN: u32 = 10;
+
+/// Colors of the panes, see theme.rs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemeName {
+    Light,
+    Dark,
+    HighContrast,
+}
+
+impl ThemeName {
+    pub const ALL: [ThemeName; 3] = [ThemeName::Light, ThemeName::Dark, ThemeName::HighContrast];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ThemeName::Light => "light",
+            ThemeName::Dark => "dark",
+            ThemeName::HighContrast => "high_contrast",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        key_value::parse_named(&Self::ALL, Self::as_str, "a theme", value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Settings {
+    pub log_level: LevelFilter,
+    pub log_files: usize,
+    pub log_file_kb: u32,
+    pub theme: ThemeName,
+    pub auto_dark: bool,
+    pub brightness: u8,
+    pub dim_after_min: u32,
+    pub dim_brightness: u8,
+    pub night_start: NaiveTime,
+    pub night_end: NaiveTime,
+    pub night_brightness: u8,
+    pub clock_after_min: u32,
+    pub time_24h: bool,
+    pub show_seconds: bool,
+    pub date_order: DateOrder,
+    pub temp_unit: TempUnit,
+    pub wind_unit: WindUnit,
+    pub refresh_min: u32,
+    pub orientation: Orientation,
+    pub locale: Locale,
+    /// Index of the home city
+    pub home_city: usize,
+    pub night_from_sun: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            log_level: DEFAULT_LOG_LEVEL,
+            log_files: DEFAULT_LOG_FILES,
+            log_file_kb: DEFAULT_LOG_FILE_KB,
+            theme: DEFAULT_THEME,
+            auto_dark: false,
+            brightness: DEFAULT_BRIGHTNESS,
+            dim_after_min: 10,
+            dim_brightness: 30,
+            night_start: NaiveTime::from_hms_opt(22, 0, 0).unwrap(),
+            night_end: NaiveTime::from_hms_opt(7, 0, 0).unwrap(),
+            night_brightness: 10,
+            clock_after_min: 0,
+            time_24h: false,
+            show_seconds: false,
+            date_order: DateOrder::Us,
+            temp_unit: TempUnit::Fahrenheit,
+            wind_unit: WindUnit::Mph,
+            refresh_min: DEFAULT_REFRESH_MIN,
+            orientation: Orientation::Landscape,
+            locale: Locale::English,
+            home_city: 0,
+            night_from_sun: true,
+        }
+    }
+}
+
+impl Settings {
+    /// Reads SETTINGS.TXT, see key_value.rs, returns the error message to log when it is not valid
+    pub fn from_lines(lines: &[String]) -> Result<Self, String> {
+        let mut settings = Self::default();
+
+        parse_key_values("SETTINGS.TXT", lines, |key, value| {
+            match key {
+                "log_level" => settings.log_level = parse_log_level(value)?,
+                "log_files" => settings.log_files = parse_number(key, value, 1, 9)?,
+                "log_file_kb" => settings.log_file_kb = parse_number(key, value, 16, 4096)?,
+                "theme" => settings.theme = ThemeName::parse(value)?,
+                "auto_dark" => settings.auto_dark = parse_bool(key, value)?,
+                "brightness" => {
+                    settings.brightness = parse_number(key, value, MIN_BRIGHTNESS, 100)?
+                }
+                "dim_after_min" => settings.dim_after_min = parse_number(key, value, 0, 1440)?,
+                "dim_brightness" => settings.dim_brightness = parse_number(key, value, 0, 100)?,
+                "night_start" => settings.night_start = parse_time(key, value)?,
+                "night_end" => settings.night_end = parse_time(key, value)?,
+                "night_brightness" => settings.night_brightness = parse_number(key, value, 0, 100)?,
+                "clock_after_min" => settings.clock_after_min = parse_number(key, value, 0, 1440)?,
+                "time_24h" => settings.time_24h = parse_bool(key, value)?,
+                "show_seconds" => settings.show_seconds = parse_bool(key, value)?,
+                "date_order" => settings.date_order = DateOrder::parse(value)?,
+                "temp_unit" => settings.temp_unit = TempUnit::parse(value)?,
+                "wind_unit" => settings.wind_unit = WindUnit::parse(value)?,
+                "refresh_min" => settings.refresh_min = parse_number(key, value, 5, 120)?,
+                "orientation" => settings.orientation = Orientation::parse(value)?,
+                "locale" => settings.locale = Locale::parse(value)?,
+                "home_city" => {
+                    settings.home_city = parse_number::<usize>(key, value, 1, CITIES_COUNT)? - 1
+                }
+                "night_from_sun" => settings.night_from_sun = parse_bool(key, value)?,
+                _ => return Ok(false),
+            }
+            Ok(true)
+        })?;
+
+        Ok(settings)
+    }
+
+    /// Contents of SETTINGS.TXT
+    pub fn to_lines(&self) -> String {
+        [
+            format!("log_level={}", self.log_level.as_str().to_lowercase()),
+            format!("log_files={}", self.log_files),
+            format!("log_file_kb={}", self.log_file_kb),
+            format!("theme={}", self.theme.as_str()),
+            format!("auto_dark={}", self.auto_dark),
+            format!("brightness={}", self.brightness),
+            format!("dim_after_min={}", self.dim_after_min),
+            format!("dim_brightness={}", self.dim_brightness),
+            format!("night_start={}", self.night_start.format("%H:%M")),
+            format!("night_end={}", self.night_end.format("%H:%M")),
+            format!("night_brightness={}", self.night_brightness),
+            format!("clock_after_min={}", self.clock_after_min),
+            format!("time_24h={}", self.time_24h),
+            format!("show_seconds={}", self.show_seconds),
+            format!("date_order={}", self.date_order.as_str()),
+            format!("temp_unit={}", self.temp_unit.as_str()),
+            format!("wind_unit={}", self.wind_unit.as_str()),
+            format!("refresh_min={}", self.refresh_min),
+            format!("orientation={}", self.orientation.as_str()),
+            format!("locale={}", self.locale.as_str()),
+            format!("home_city={}", self.home_city + 1),
+            format!("night_from_sun={}", self.night_from_sun),
+        ]
+        .iter()
+        .fold(String::new(), |lines, line| lines + line + "\n")
+    }
+
+    pub fn date_format(&self) -> DateFormat {
+        DateFormat {
+            time_24h: self.time_24h,
+            show_seconds: self.show_seconds,
+            date_order: self.date_order,
+        }
+    }
+
+    pub fn refresh_interval(&self) -> Duration {
+        Duration::from_secs(self.refresh_min as u64 * 60)
+    }
+
+    /// The settings of the Display pane
+    pub fn display_prefs(&self) -> DisplayPrefs {
+        DisplayPrefs {
+            brightness: self.brightness,
+            dim_after_min: self.dim_after_min,
+            dim_brightness: self.dim_brightness,
+            theme: self.theme,
+            auto_dark: self.auto_dark,
+            home_city: self.home_city,
+            time_24h: self.time_24h,
+            show_seconds: self.show_seconds,
+            date_order: self.date_order,
+            temp_unit: self.temp_unit,
+            wind_unit: self.wind_unit,
+            refresh_min: self.refresh_min,
+            orientation: self.orientation,
+        }
+    }
+
+    /// The settings with those of the Display pane, the others are kept
+    pub fn with_display_prefs(&self, prefs: &DisplayPrefs) -> Self {
+        Self {
+            brightness: prefs.brightness,
+            dim_after_min: prefs.dim_after_min,
+            dim_brightness: prefs.dim_brightness,
+            theme: prefs.theme,
+            auto_dark: prefs.auto_dark,
+            home_city: prefs.home_city,
+            time_24h: prefs.time_24h,
+            show_seconds: prefs.show_seconds,
+            date_order: prefs.date_order,
+            temp_unit: prefs.temp_unit,
+            wind_unit: prefs.wind_unit,
+            refresh_min: prefs.refresh_min,
+            orientation: prefs.orientation,
+            ..self.clone()
+        }
+    }
+}
+
+/// The settings of the Display pane, the pane previews and saves them without the others so the
+/// settings changed meanwhile, such as the log level set with the web API, are not overwritten
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisplayPrefs {
+    pub brightness: u8,
+    pub dim_after_min: u32,
+    pub dim_brightness: u8,
+    pub theme: ThemeName,
+    pub auto_dark: bool,
+    /// Index of the home city
+    pub home_city: usize,
+    pub time_24h: bool,
+    pub show_seconds: bool,
+    pub date_order: DateOrder,
+    pub temp_unit: TempUnit,
+    pub wind_unit: WindUnit,
+    pub refresh_min: u32,
+    /// Used from the next start
+    pub orientation: Orientation,
+}
+
+impl Default for DisplayPrefs {
+    fn default() -> Self {
+        Settings::default().display_prefs()
+    }
+}
+
+pub fn parse_log_level(value: &str) -> Result<LevelFilter, String> {
+    value
+        .parse()
+        .map_err(|_| format!("'{}' is not a log level", value))
+}
+
+fn parse_bool(key: &str, value: &str) -> Result<bool, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} must be true or false", key))
+}
+
+fn parse_time(key: &str, value: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(value, "%H:%M")
+        .map_err(|_| format!("{} must be a time like 22:30", key))
+}
+
+fn parse_number<T>(key: &str, value: &str, min: T, max: T) -> Result<T, String>
+where
+    T: std::str::FromStr + PartialOrd + std::fmt::Display + Copy,
+{
+    value
+        .parse()
+        .ok()
+        .filter(|number| *number >= min && *number <= max)
+        .ok_or_else(|| format!("{} must be a number from {} to {}", key, min, max))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn settings_txt_round_trip() {
+        let settings = Settings {
+            log_level: LevelFilter::Debug,
+            theme: ThemeName::HighContrast,
+            night_start: NaiveTime::from_hms_opt(23, 30, 0).unwrap(),
+            date_order: DateOrder::Iso,
+            temp_unit: TempUnit::Celsius,
+            home_city: 2,
+            ..Settings::default()
+        };
+
+        assert_eq!(
+            Settings::from_lines(&lines(&settings.to_lines())),
+            Ok(settings)
+        );
+    }
+
+    #[test]
+    fn settings_txt_skips_unknown_settings() {
+        let settings = Settings::from_lines(&lines(
+            "# newer firmware
+sparkles=on
+brightness = 50",
+        ));
+        assert_eq!(settings.map(|settings| settings.brightness), Ok(50));
+    }
+
+    #[test]
+    fn settings_txt_errors() {
+        for (text, error) in [
+            ("brightness=5", "brightness must be a number from 10 to 100"),
+            ("home_city=5", "home_city must be a number from 1 to 4"),
+            ("auto_dark=yes", "auto_dark must be true or false"),
+            ("night_end=7", "night_end must be a time like 22:30"),
+            ("theme", "Line 'theme' is not key=value"),
+        ] {
+            assert_eq!(Settings::from_lines(&lines(text)), Err(error.to_string()));
+        }
+    }
+
+    #[test]
+    fn display_prefs_keep_the_other_settings() {
+        let settings = Settings {
+            log_level: LevelFilter::Debug,
+            locale: Locale::German,
+            brightness: 40,
+            temp_unit: TempUnit::Celsius,
+            ..Settings::default()
+        };
+        assert_eq!(
+            settings.with_display_prefs(&settings.display_prefs()),
+            settings
+        );
+
+        let defaults = settings.with_display_prefs(&DisplayPrefs::default());
+        assert_eq!(
+            defaults.display_prefs(),
+            Settings::default().display_prefs()
+        );
+        assert_eq!(
+            (defaults.log_level, defaults.locale),
+            (LevelFilter::Debug, Locale::German)
+        );
+    }
+}
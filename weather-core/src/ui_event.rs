@@ -0,0 +1,82 @@
+//! Events of the UI.
+//!
+//! The LVGL event handlers don't act on the panes, they push a `UiEvent` with what was clicked on
+//! the `UiEventQueue` and the UI loop hands the events to the `PaneRouter` one at a time.  An
+//! event sequence can be pushed the same way to walk the panes through a flow.
+use crate::nav_stack::PaneId;
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Button {
+    Edit,
+    Save,
+    Exit,
+    Defaults,
+}
+
+/// The sliders of the Display pane
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LevelSlider {
+    Brightness,
+    DimLevel,
+}
+
+/// The rows of buttons of the Display pane where one button is checked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Choice {
+    DimAfter,
+    Theme,
+    AutoDark,
+    HomeCity,
+    ClockFormat,
+    Seconds,
+    DateOrder,
+    TempUnit,
+    WindUnit,
+    Refresh,
+    Orientation,
+}
+
+/// The text areas that take the keyboard
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextField {
+    WifiSsid,
+    WifiPassword,
+    CityName(usize),
+    Zipcode(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UiEvent {
+    /// Index of the nav bar button
+    NavButtonPressed(u16),
+    NetworkStatusClicked,
+    ButtonClicked(PaneId, Button),
+    TextFieldClicked(TextField),
+    /// Index of the city or the period button of the History pane
+    HistoryCityPressed(u16),
+    HistoryPeriodPressed(u16),
+    /// Value of a Display pane slider while it is moved and once it is released
+    SliderMoved(LevelSlider, u8),
+    SliderReleased(LevelSlider),
+    /// Index of the button pressed in a row of the Display pane
+    ChoicePressed(Choice, u16),
+    ClockClicked,
+}
+
+/// The events waiting for the UI loop, a clone is kept by every event handler
+#[derive(Debug, Clone, Default)]
+pub struct UiEventQueue(Rc<RefCell<VecDeque<UiEvent>>>);
+
+impl UiEventQueue {
+    pub fn push(&self, event: UiEvent) {
+        self.0.borrow_mut().push_back(event);
+    }
+
+    pub fn pop(&self) -> Option<UiEvent> {
+        self.0.borrow_mut().pop_front()
+    }
+}
@@ -0,0 +1,110 @@
+//! Units of the temperatures and wind speeds shown by the panes.
+//!
+//! weatherapi and the history files give the temperatures in Fahrenheit and the wind speeds in
+//! miles per hour, they are converted to the units of the settings when they are shown.
+use crate::i18n;
+use crate::key_value;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TempUnit {
+    #[default]
+    Fahrenheit,
+    Celsius,
+}
+
+impl TempUnit {
+    pub const ALL: [TempUnit; 2] = [TempUnit::Fahrenheit, TempUnit::Celsius];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TempUnit::Fahrenheit => "f",
+            TempUnit::Celsius => "c",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        key_value::parse_named(&Self::ALL, Self::as_str, "a temperature unit", value)
+    }
+
+    /// Symbol shown after the temperatures
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            TempUnit::Fahrenheit => "F",
+            TempUnit::Celsius => "C",
+        }
+    }
+
+    /// The temperature in the unit
+    pub fn convert(&self, temp_f: f64) -> f64 {
+        match self {
+            TempUnit::Fahrenheit => temp_f,
+            TempUnit::Celsius => (temp_f - 32.0) * 5.0 / 9.0,
+        }
+    }
+
+    /// A temperature without decimals followed by the symbol, 72F
+    pub fn format(&self, temp_f: f64) -> String {
+        // A temperature a little below zero is rounded to -0, it is shown as 0
+        let temp = self.convert(temp_f).round();
+        let temp = if temp == 0.0 { 0.0 } else { temp };
+        format!("{}{}", i18n::number(temp, 0), self.symbol())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WindUnit {
+    #[default]
+    Mph,
+    Kph,
+}
+
+impl WindUnit {
+    pub const ALL: [WindUnit; 2] = [WindUnit::Mph, WindUnit::Kph];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WindUnit::Mph => "mph",
+            WindUnit::Kph => "kph",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        key_value::parse_named(&Self::ALL, Self::as_str, "a wind unit", value)
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            WindUnit::Mph => "mph",
+            WindUnit::Kph => "km/h",
+        }
+    }
+
+    /// A wind speed without decimals
+    pub fn format(&self, speed_mph: f64) -> String {
+        let speed = match self {
+            WindUnit::Mph => speed_mph,
+            WindUnit::Kph => speed_mph * 1.609344,
+        };
+        i18n::number(speed, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn temperatures_are_rounded_without_minus_zero() {
+        assert_eq!(TempUnit::Fahrenheit.format(72.4), "72F");
+        assert_eq!(TempUnit::Celsius.format(212.0), "100C");
+        assert_eq!(TempUnit::Celsius.format(31.5), "0C");
+        assert_eq!(TempUnit::Fahrenheit.format(-0.3), "0F");
+        assert_eq!(TempUnit::Celsius.format(30.0), "-1C");
+    }
+
+    #[test]
+    fn wind_speeds_are_converted() {
+        assert_eq!(WindUnit::Mph.format(10.4), "10");
+        assert_eq!(WindUnit::Kph.format(10.0), "16");
+    }
+}
@@ -0,0 +1,220 @@
+//! Weather condition icons.
+//!
+//! weatherapi.com gives each weather condition a code, the same code is used day and night and
+//! the `is_day` flag tells them apart.  `icon_for_code` maps every code of the provider's list,
+//! https://www.weatherapi.com/docs/weather_conditions.json, to one of the icons drawn by
+//! `WeatherIconWidget`.  An icon is made of a few parts, the sun or the moon, the cloud glyph of
+//! custom-fonts/cloud24.c, rain or snow marks, a lightning bolt and fog lines, so the table and the
+//! parts have no hardware and can be checked on the host.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WeatherIcon {
+    ClearDay,
+    ClearNight,
+    PartlyCloudyDay,
+    PartlyCloudyNight,
+    Cloudy,
+    Overcast,
+    Fog,
+    RainShowersDay,
+    RainShowersNight,
+    SnowShowersDay,
+    SnowShowersNight,
+    Drizzle,
+    Rain,
+    HeavyRain,
+    Sleet,
+    Snow,
+    HeavySnow,
+    Thunder,
+    ThunderSnow,
+}
+
+/// Icon of a weatherapi.com condition code, None for a code that is not in the provider's list
+pub fn icon_for_code(code: i32, is_day: bool) -> Option<WeatherIcon> {
+    let day_or_night = |day, night| if is_day { day } else { night };
+
+    let icon = match code {
+        // Sunny or Clear
+        1000 => day_or_night(WeatherIcon::ClearDay, WeatherIcon::ClearNight),
+        1003 => day_or_night(WeatherIcon::PartlyCloudyDay, WeatherIcon::PartlyCloudyNight),
+        1006 => WeatherIcon::Cloudy,
+        1009 => WeatherIcon::Overcast,
+        // Mist, fog and freezing fog
+        1030 | 1135 | 1147 => WeatherIcon::Fog,
+        // Patchy or light rain and rain at times
+        1063 | 1180 | 1186 | 1240 => {
+            day_or_night(WeatherIcon::RainShowersDay, WeatherIcon::RainShowersNight)
+        }
+        // Patchy snow
+        1066 | 1210 | 1216 | 1255 => {
+            day_or_night(WeatherIcon::SnowShowersDay, WeatherIcon::SnowShowersNight)
+        }
+        1150 | 1153 => WeatherIcon::Drizzle,
+        1183 | 1189 => WeatherIcon::Rain,
+        1192 | 1195 | 1243 | 1246 => WeatherIcon::HeavyRain,
+        // Sleet, freezing drizzle or rain and ice pellets
+        1069 | 1072 | 1168 | 1171 | 1198 | 1201 | 1204 | 1207 | 1237 | 1249 | 1252 | 1261
+        | 1264 => WeatherIcon::Sleet,
+        1114 | 1213 | 1219 => WeatherIcon::Snow,
+        1117 | 1222 | 1225 | 1258 => WeatherIcon::HeavySnow,
+        1087 | 1273 | 1276 => WeatherIcon::Thunder,
+        1279 | 1282 => WeatherIcon::ThunderSnow,
+        _ => return None,
+    };
+
+    Some(icon)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sky {
+    Sun,
+    Moon,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloudShade {
+    Light,
+    Dark,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Precipitation {
+    Drizzle,
+    Rain,
+    HeavyRain,
+    Sleet,
+    Snow,
+    HeavySnow,
+}
+
+impl Precipitation {
+    /// Marks drawn under the cloud
+    pub fn marks(&self) -> &'static str {
+        match self {
+            Precipitation::Drizzle => ", , ,",
+            Precipitation::Rain => "/ / /",
+            Precipitation::HeavyRain => "/////",
+            Precipitation::Sleet => "/ * /",
+            Precipitation::Snow => "* * *",
+            Precipitation::HeavySnow => "*****",
+        }
+    }
+
+    pub fn is_snow(&self) -> bool {
+        matches!(self, Precipitation::Snow | Precipitation::HeavySnow)
+    }
+}
+
+/// The parts an icon is drawn with, the sun or the moon is drawn smaller behind the cloud
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct IconParts {
+    pub sky: Option<Sky>,
+    pub cloud: Option<CloudShade>,
+    pub precipitation: Option<Precipitation>,
+    pub thunder: bool,
+    pub fog: bool,
+}
+
+impl WeatherIcon {
+    pub fn parts(&self) -> IconParts {
+        let sky = |sky| IconParts {
+            sky: Some(sky),
+            ..IconParts::default()
+        };
+        let cloud = |shade, precipitation| IconParts {
+            cloud: Some(shade),
+            precipitation,
+            ..IconParts::default()
+        };
+        let showers = |sky, precipitation| IconParts {
+            sky: Some(sky),
+            cloud: Some(CloudShade::Light),
+            precipitation: Some(precipitation),
+            ..IconParts::default()
+        };
+
+        match self {
+            WeatherIcon::ClearDay => sky(Sky::Sun),
+            WeatherIcon::ClearNight => sky(Sky::Moon),
+            WeatherIcon::PartlyCloudyDay => IconParts {
+                sky: Some(Sky::Sun),
+                cloud: Some(CloudShade::Light),
+                ..IconParts::default()
+            },
+            WeatherIcon::PartlyCloudyNight => IconParts {
+                sky: Some(Sky::Moon),
+                cloud: Some(CloudShade::Light),
+                ..IconParts::default()
+            },
+            WeatherIcon::Cloudy => cloud(CloudShade::Light, None),
+            WeatherIcon::Overcast => cloud(CloudShade::Dark, None),
+            WeatherIcon::Fog => IconParts {
+                fog: true,
+                ..IconParts::default()
+            },
+            WeatherIcon::RainShowersDay => showers(Sky::Sun, Precipitation::Rain),
+            WeatherIcon::RainShowersNight => showers(Sky::Moon, Precipitation::Rain),
+            WeatherIcon::SnowShowersDay => showers(Sky::Sun, Precipitation::Snow),
+            WeatherIcon::SnowShowersNight => showers(Sky::Moon, Precipitation::Snow),
+            WeatherIcon::Drizzle => cloud(CloudShade::Light, Some(Precipitation::Drizzle)),
+            WeatherIcon::Rain => cloud(CloudShade::Dark, Some(Precipitation::Rain)),
+            WeatherIcon::HeavyRain => cloud(CloudShade::Dark, Some(Precipitation::HeavyRain)),
+            WeatherIcon::Sleet => cloud(CloudShade::Dark, Some(Precipitation::Sleet)),
+            WeatherIcon::Snow => cloud(CloudShade::Light, Some(Precipitation::Snow)),
+            WeatherIcon::HeavySnow => cloud(CloudShade::Dark, Some(Precipitation::HeavySnow)),
+            WeatherIcon::Thunder => IconParts {
+                thunder: true,
+                ..cloud(CloudShade::Dark, None)
+            },
+            WeatherIcon::ThunderSnow => IconParts {
+                thunder: true,
+                ..cloud(CloudShade::Dark, Some(Precipitation::Snow))
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The condition codes of weatherapi.com
+    const CONDITION_CODES: [i32; 48] = [
+        1000, 1003, 1006, 1009, 1030, 1063, 1066, 1069, 1072, 1087, 1114, 1117, 1135, 1147, 1150,
+        1153, 1168, 1171, 1180, 1183, 1186, 1189, 1192, 1195, 1198, 1201, 1204, 1207, 1210, 1213,
+        1216, 1219, 1222, 1225, 1237, 1240, 1243, 1246, 1249, 1252, 1255, 1258, 1261, 1264, 1273,
+        1276, 1279, 1282,
+    ];
+
+    #[test]
+    fn every_condition_code_has_an_icon() {
+        for code in CONDITION_CODES {
+            let day = icon_for_code(code, true);
+            let night = icon_for_code(code, false);
+            assert!(day.is_some() && night.is_some(), "{}", code);
+
+            // Only the icons with the sun or the moon differ at night
+            let (day, night) = (day.unwrap().parts(), night.unwrap().parts());
+            assert_eq!(
+                IconParts { sky: None, ..day },
+                IconParts { sky: None, ..night }
+            );
+            if day.sky.is_some() {
+                assert_eq!(
+                    (day.sky, night.sky),
+                    (Some(Sky::Sun), Some(Sky::Moon)),
+                    "{}",
+                    code
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn unknown_condition_code_has_no_icon() {
+        for code in [0, 999, 1001, 1283] {
+            assert_eq!(icon_for_code(code, true), None);
+        }
+    }
+}
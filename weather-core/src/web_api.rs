@@ -0,0 +1,682 @@
+//! Routing and serialization for the on-device HTTP JSON API.
+//!
+//! Nothing in here touches the hardware, the HTTP server hands every request to `handle_request`
+//! together with the latest `ApiSnapshot` published by the model and writes back the response.
+//! Configuration updates are validated here and returned as `ApiRequest`s, the model takes them
+//! the same way as the changes made in the settings panes.
+//!
+//! The forecasts are served as numbers in the units of weatherapi, the text shown on the panes
+//! follows the locale and the unit settings and is not part of the API.
+//!
+//! The GET endpoints are open to the local network.  The PUT endpoints change the Wifi
+//! credentials and install firmware, they are only served when a token is set in the optional
+//! API.TXT file on the SDCard and sent with an `Authorization: Bearer <token>` header:
+//!
+//! ```text
+//! token=a long random string   token of the PUT requests, at least 16 characters
+//! ```
+//!
+//! Endpoints:
+//! - `GET /api/forecast` - the forecast of each city
+//! - `GET /api/status`   - the network status
+//! - `GET /api/config`   - the cities settings, the wifi network name, the log level, the theme, the
+//!   home city and the orientation
+//! - `PUT /api/config`   - update the cities settings, the wifi credentials, the log level, the
+//!   theme, the home city and/or the orientation, the reply tells that the orientation applies
+//!   after a restart
+//! - `PUT /api/ota`      - download and install the firmware from the url in OTA.TXT
+use crate::cities::{self, CityConditions, CityInfo};
+use crate::i18n::Locale;
+use crate::key_value::parse_key_values;
+use crate::orientation::Orientation;
+use crate::settings::{self, Settings, ThemeName, CITIES_COUNT};
+
+use log::LevelFilter;
+use serde::{Deserialize, Serialize};
+
+// Largest request body accepted by the API
+pub const MAX_BODY_SIZE: usize = 2048;
+
+// Shortest token accepted in API.TXT
+const MIN_TOKEN_LEN: usize = 16;
+
+// Note of the reply to an orientation change
+const ORIENTATION_NOTE: &str = "The orientation applies after a restart";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ApiMethod {
+    Get,
+    Put,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ApiResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+/// Changes asked for by the API, they are handed to the model
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApiRequest {
+    UpdateCitiesInfo(Vec<CityInfo>),
+    UpdateWifiCreds(String, String),
+    UpdateFirmware,
+    SetLogLevel(LevelFilter),
+    SetTheme(ThemeName, bool),
+    SetHomeCity(usize),
+    /// Saved and used from the next start
+    SetOrientation(Orientation),
+}
+
+/// API settings read from API.TXT
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiConfig {
+    pub token: String,
+}
+
+impl ApiConfig {
+    /// Reads API.TXT, see key_value.rs, returns the error message to log when it is not valid
+    pub fn from_lines(lines: &[String]) -> Result<Self, String> {
+        let mut token = None;
+        parse_key_values("API.TXT", lines, |key, value| {
+            match key {
+                "token" => token = Some(value.to_string()),
+                _ => return Ok(false),
+            }
+            Ok(true)
+        })?;
+
+        match token {
+            Some(token) if token.len() >= MIN_TOKEN_LEN => Ok(Self { token }),
+            Some(_) => Err(format!(
+                "token must be at least {} characters",
+                MIN_TOKEN_LEN
+            )),
+            None => Err("Missing token setting".to_string()),
+        }
+    }
+}
+
+/// The data served by the API, kept up to date by the model
+#[derive(Debug, Default)]
+pub struct ApiSnapshot {
+    pub cities_info: Vec<CityInfo>,
+    pub forecasts: Vec<Option<ApiForecast>>,
+    pub status: Option<ApiStatus>,
+    pub settings: Settings,
+    /// The PUT requests are refused without API.TXT
+    pub config: Option<ApiConfig>,
+}
+
+/// Forecast of a city in the units of weatherapi
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ApiForecast {
+    pub current: CityConditions,
+    pub days: Vec<ApiForecastDay>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ApiForecastDay {
+    pub date_epoch: i64,
+    pub max_temp_f: f64,
+    pub min_temp_f: f64,
+    pub condition: String,
+}
+
+/// Network status, `wifi_state` is Disconnected, Connecting, Connected or BackingOff
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ApiStatus {
+    pub wifi_state: String,
+    pub ssid: String,
+    pub rssi: Option<i8>,
+    pub ip_address: Option<String>,
+    pub time_synced: bool,
+    pub last_fetch_secs: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct CityForecastJson<'a> {
+    city: &'a CityInfo,
+    forecast: Option<&'a ApiForecast>,
+}
+
+#[derive(Serialize)]
+struct ConfigJson<'a> {
+    cities: &'a [CityInfo],
+    wifi: WifiConfigJson<'a>,
+    log_level: String,
+    theme: ThemeName,
+    auto_dark: bool,
+    /// Number of the home city, from 1
+    home_city: usize,
+    orientation: &'static str,
+}
+
+#[derive(Serialize)]
+struct WifiConfigJson<'a> {
+    ssid: &'a str,
+}
+
+#[derive(Deserialize)]
+struct ConfigUpdateJson {
+    cities: Option<Vec<CityInfo>>,
+    wifi: Option<WifiCredentialsJson>,
+    log_level: Option<String>,
+    theme: Option<String>,
+    auto_dark: Option<bool>,
+    home_city: Option<usize>,
+    orientation: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct WifiCredentialsJson {
+    ssid: String,
+    pass: String,
+}
+
+/// Routes the request, returns the response along with the requests to send to the model.
+/// `authorization` is the Authorization header of the request.
+pub fn handle_request(
+    method: ApiMethod,
+    path: &str,
+    authorization: Option<&str>,
+    body: &[u8],
+    snapshot: &ApiSnapshot,
+) -> (ApiResponse, Vec<ApiRequest>) {
+    // Ignore any query string
+    let path = path.split('?').next().unwrap_or(path);
+
+    if method == ApiMethod::Put && matches!(path, "/api/config" | "/api/ota") {
+        if let Err(response) = check_token(authorization, snapshot) {
+            return (response, Vec::new());
+        }
+    }
+
+    match (method, path) {
+        (ApiMethod::Get, "/api/forecast") => (get_forecast(snapshot), Vec::new()),
+        (ApiMethod::Get, "/api/status") => (get_status(snapshot), Vec::new()),
+        (ApiMethod::Get, "/api/config") => (get_config(snapshot), Vec::new()),
+        (ApiMethod::Put, "/api/config") => match put_config(body, snapshot) {
+            Ok(api_requests) => {
+                let sets_orientation = api_requests
+                    .iter()
+                    .any(|request| matches!(request, ApiRequest::SetOrientation(_)));
+                let response = if sets_orientation {
+                    accepted_response_with_note(ORIENTATION_NOTE)
+                } else {
+                    accepted_response()
+                };
+                (response, api_requests)
+            }
+            Err(error) => (error_response(400, &error), Vec::new()),
+        },
+        (ApiMethod::Put, "/api/ota") => (accepted_response(), vec![ApiRequest::UpdateFirmware]),
+        (_, "/api/forecast" | "/api/status" | "/api/ota") => {
+            (error_response(405, "Method not allowed"), Vec::new())
+        }
+        _ => (error_response(404, "Not found"), Vec::new()),
+    }
+}
+
+fn check_token(authorization: Option<&str>, snapshot: &ApiSnapshot) -> Result<(), ApiResponse> {
+    let Some(config) = &snapshot.config else {
+        return Err(error_response(
+            403,
+            "Changes are disabled, there is no API.TXT",
+        ));
+    };
+
+    match authorization.and_then(|value| value.strip_prefix("Bearer ")) {
+        Some(token) if same_token(token.trim(), &config.token) => Ok(()),
+        _ => Err(error_response(401, "Missing or wrong token")),
+    }
+}
+
+// Compares every byte so the time taken doesn't tell how much of the token is right
+fn same_token(given: &str, token: &str) -> bool {
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn get_forecast(snapshot: &ApiSnapshot) -> ApiResponse {
+    let forecasts: Vec<CityForecastJson> = snapshot
+        .cities_info
+        .iter()
+        .enumerate()
+        .map(|(i, city)| CityForecastJson {
+            city,
+            forecast: snapshot.forecasts.get(i).and_then(|f| f.as_ref()),
+        })
+        .collect();
+
+    json_response(200, &forecasts)
+}
+
+fn get_status(snapshot: &ApiSnapshot) -> ApiResponse {
+    match &snapshot.status {
+        Some(status) => json_response(200, status),
+        None => error_response(503, "Status not available yet"),
+    }
+}
+
+fn get_config(snapshot: &ApiSnapshot) -> ApiResponse {
+    let ssid = snapshot
+        .status
+        .as_ref()
+        .map(|status| status.ssid.as_str())
+        .unwrap_or("");
+
+    json_response(
+        200,
+        &ConfigJson {
+            cities: &snapshot.cities_info,
+            wifi: WifiConfigJson { ssid },
+            log_level: snapshot.settings.log_level.as_str().to_lowercase(),
+            theme: snapshot.settings.theme,
+            auto_dark: snapshot.settings.auto_dark,
+            home_city: snapshot.settings.home_city + 1,
+            orientation: snapshot.settings.orientation.as_str(),
+        },
+    )
+}
+
+fn put_config(body: &[u8], snapshot: &ApiSnapshot) -> Result<Vec<ApiRequest>, String> {
+    if body.len() > MAX_BODY_SIZE {
+        return Err("Request body is too large".to_string());
+    }
+
+    let update: ConfigUpdateJson =
+        serde_json::from_slice(body).map_err(|e| format!("Invalid JSON - {}", e))?;
+
+    let mut api_requests = Vec::new();
+
+    if let Some(cities_info) = update.cities {
+        if cities_info.len() != CITIES_COUNT {
+            return Err(format!(
+                "Expected {} cities, got {}",
+                CITIES_COUNT,
+                cities_info.len()
+            ));
+        }
+
+        // The errors of the API are in English whatever the locale of the panes
+        cities::check_cities_info(&cities_info, &Locale::English.catalog().text)?;
+        api_requests.push(ApiRequest::UpdateCitiesInfo(cities_info));
+    }
+
+    if let Some(wifi) = update.wifi {
+        check_wifi_credentials(&wifi)?;
+        api_requests.push(ApiRequest::UpdateWifiCreds(wifi.ssid, wifi.pass));
+    }
+
+    if let Some(log_level) = update.log_level {
+        let log_level = settings::parse_log_level(&log_level)?;
+        api_requests.push(ApiRequest::SetLogLevel(log_level));
+    }
+
+    // A missing theme or auto_dark keeps the current one
+    if update.theme.is_some() || update.auto_dark.is_some() {
+        let theme_name = match update.theme {
+            Some(theme_name) => ThemeName::parse(&theme_name)?,
+            None => snapshot.settings.theme,
+        };
+        let auto_dark = update.auto_dark.unwrap_or(snapshot.settings.auto_dark);
+        api_requests.push(ApiRequest::SetTheme(theme_name, auto_dark));
+    }
+
+    if let Some(home_city) = update.home_city {
+        if home_city == 0 || home_city > CITIES_COUNT {
+            return Err(format!(
+                "home_city must be a number from 1 to {}",
+                CITIES_COUNT
+            ));
+        }
+        api_requests.push(ApiRequest::SetHomeCity(home_city - 1));
+    }
+
+    if let Some(orientation) = update.orientation {
+        let orientation = Orientation::parse(&orientation)?;
+        api_requests.push(ApiRequest::SetOrientation(orientation));
+    }
+
+    if api_requests.is_empty() {
+        return Err("Nothing to update".to_string());
+    }
+
+    Ok(api_requests)
+}
+
+fn check_wifi_credentials(wifi: &WifiCredentialsJson) -> Result<(), String> {
+    if wifi.ssid.is_empty() || wifi.ssid.len() > 32 {
+        return Err("SSID must be 1 to 32 characters".to_string());
+    }
+
+    if !wifi.pass.is_empty() && (wifi.pass.len() < 8 || wifi.pass.len() > 64) {
+        return Err("Password must be 8 to 64 characters".to_string());
+    }
+
+    Ok(())
+}
+
+fn accepted_response() -> ApiResponse {
+    json_response(202, &serde_json::json!({ "status": "accepted" }))
+}
+
+fn accepted_response_with_note(note: &str) -> ApiResponse {
+    json_response(
+        202,
+        &serde_json::json!({ "status": "accepted", "note": note }),
+    )
+}
+
+fn json_response<T: Serialize + ?Sized>(status: u16, value: &T) -> ApiResponse {
+    match serde_json::to_string(value) {
+        Ok(body) => ApiResponse { status, body },
+        Err(e) => error_response(500, &e.to_string()),
+    }
+}
+
+fn error_response(status: u16, error: &str) -> ApiResponse {
+    ApiResponse {
+        status,
+        body: serde_json::json!({ "error": error }).to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    const TOKEN: &str = "0123456789abcdef";
+    const AUTHORIZATION: Option<&str> = Some("Bearer 0123456789abcdef");
+
+    fn city(city_name: &str, zipcode: &str) -> CityInfo {
+        CityInfo {
+            city_name: city_name.to_string(),
+            state: "CA".to_string(),
+            zipcode: zipcode.to_string(),
+            timezone: "US/Pacific".to_string(),
+        }
+    }
+
+    fn snapshot() -> ApiSnapshot {
+        ApiSnapshot {
+            cities_info: vec![
+                city("Los Angeles", "90079"),
+                city("San Diego", "92101"),
+                city("San Jose", "95113"),
+                city("Fresno", "93721"),
+            ],
+            forecasts: vec![None; CITIES_COUNT],
+            status: Some(ApiStatus {
+                wifi_state: "Connected".to_string(),
+                ssid: "my network".to_string(),
+                rssi: Some(-60),
+                ip_address: Some("192.168.1.20".to_string()),
+                time_synced: true,
+                last_fetch_secs: Some(30),
+            }),
+            settings: Settings::default(),
+            config: Some(ApiConfig {
+                token: TOKEN.to_string(),
+            }),
+        }
+    }
+
+    fn put(path: &str, body: &str, snapshot: &ApiSnapshot) -> (ApiResponse, Vec<ApiRequest>) {
+        handle_request(
+            ApiMethod::Put,
+            path,
+            AUTHORIZATION,
+            body.as_bytes(),
+            snapshot,
+        )
+    }
+
+    fn error(response: &ApiResponse) -> String {
+        let body: Value = serde_json::from_str(&response.body).unwrap();
+        body["error"].as_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn get_config_serves_the_settings() {
+        let mut snapshot = snapshot();
+        snapshot.settings.home_city = 1;
+        snapshot.settings.auto_dark = true;
+
+        let (response, api_requests) =
+            handle_request(ApiMethod::Get, "/api/config?x=1", None, b"", &snapshot);
+        assert_eq!(response.status, 200);
+        assert!(api_requests.is_empty());
+
+        let config: Value = serde_json::from_str(&response.body).unwrap();
+        assert_eq!(config["cities"][1]["city_name"], "San Diego");
+        assert_eq!(config["wifi"]["ssid"], "my network");
+        assert!(config["wifi"].get("pass").is_none());
+        assert_eq!(config["log_level"], "info");
+        assert_eq!(config["theme"], "dark");
+        assert_eq!(config["auto_dark"], true);
+        assert_eq!(config["home_city"], 2);
+        assert_eq!(config["orientation"], "landscape");
+    }
+
+    #[test]
+    fn orientation_change_applies_after_restart() {
+        let (response, api_requests) =
+            put("/api/config", r#"{"orientation": "portrait"}"#, &snapshot());
+        assert_eq!(response.status, 202);
+        assert_eq!(
+            api_requests,
+            vec![ApiRequest::SetOrientation(Orientation::Portrait)]
+        );
+        let body: Value = serde_json::from_str(&response.body).unwrap();
+        assert_eq!(body["note"], ORIENTATION_NOTE);
+
+        // The other changes apply at once
+        let (response, _) = put("/api/config", r#"{"home_city": 2}"#, &snapshot());
+        let body: Value = serde_json::from_str(&response.body).unwrap();
+        assert!(body.get("note").is_none());
+
+        let (response, api_requests) =
+            put("/api/config", r#"{"orientation": "sideways"}"#, &snapshot());
+        assert_eq!(response.status, 400);
+        assert!(api_requests.is_empty());
+    }
+
+    #[test]
+    fn get_forecast_serves_numbers() {
+        let mut snapshot = snapshot();
+        snapshot.forecasts[0] = Some(ApiForecast {
+            current: CityConditions {
+                temp_f: 72.5,
+                feels_like_f: 71.0,
+                humidity: 40,
+                pressure_mb: 1013.0,
+                wind_mph: 5.6,
+                wind_gust_mph: 9.4,
+                wind_dir: "WSW".to_string(),
+                uv: 6.0,
+                aqi: 2,
+                condition: "Sunny".to_string(),
+                last_updated_epoch: 1_790_000_000,
+            },
+            days: vec![ApiForecastDay {
+                date_epoch: 1_789_948_800,
+                max_temp_f: 80.1,
+                min_temp_f: 60.3,
+                condition: "Sunny".to_string(),
+            }],
+        });
+
+        let (response, _) = handle_request(ApiMethod::Get, "/api/forecast", None, b"", &snapshot);
+        let forecasts: Value = serde_json::from_str(&response.body).unwrap();
+        assert_eq!(forecasts[0]["forecast"]["current"]["temp_f"], 72.5);
+        assert_eq!(
+            forecasts[0]["forecast"]["current"]["last_updated_epoch"],
+            1_790_000_000
+        );
+        assert_eq!(forecasts[0]["forecast"]["days"][0]["max_temp_f"], 80.1);
+        assert!(forecasts[1]["forecast"].is_null());
+    }
+
+    #[test]
+    fn put_needs_the_token() {
+        let mut snapshot = snapshot();
+        let body = br#"{"auto_dark": true}"#;
+
+        for (authorization, status) in [
+            (None, 401),
+            (Some("Bearer wrong"), 401),
+            (Some("0123456789abcdef"), 401),
+            (AUTHORIZATION, 202),
+        ] {
+            let (response, _) = handle_request(
+                ApiMethod::Put,
+                "/api/config",
+                authorization,
+                body,
+                &snapshot,
+            );
+            assert_eq!(response.status, status, "{:?}", authorization);
+        }
+
+        snapshot.config = None;
+        let (response, api_requests) = put("/api/ota", "", &snapshot);
+        assert_eq!(response.status, 403);
+        assert!(api_requests.is_empty());
+    }
+
+    #[test]
+    fn put_ota_starts_the_update() {
+        let (response, api_requests) = put("/api/ota", "", &snapshot());
+        assert_eq!(response.status, 202);
+        assert_eq!(api_requests, vec![ApiRequest::UpdateFirmware]);
+    }
+
+    #[test]
+    fn put_config_updates_only_what_is_given() {
+        let snapshot = snapshot();
+
+        let (response, api_requests) = put("/api/config", r#"{"theme": "light"}"#, &snapshot);
+        assert_eq!(response.status, 202);
+        assert_eq!(
+            api_requests,
+            vec![ApiRequest::SetTheme(ThemeName::Light, false)]
+        );
+
+        let (_, api_requests) = put(
+            "/api/config",
+            r#"{"auto_dark": true, "log_level": "DEBUG", "home_city": 2}"#,
+            &snapshot,
+        );
+        assert_eq!(
+            api_requests,
+            vec![
+                ApiRequest::SetLogLevel(LevelFilter::Debug),
+                ApiRequest::SetTheme(ThemeName::Dark, true),
+                ApiRequest::SetHomeCity(1),
+            ]
+        );
+
+        let (_, api_requests) = put(
+            "/api/config",
+            r#"{"wifi": {"ssid": "home", "pass": ""}, "cities": [
+                {"city_name": "A", "state": "NY", "zipcode": "10001", "timezone": "US/Eastern"},
+                {"city_name": "B", "state": "IL", "zipcode": "60614", "timezone": "US/Central"},
+                {"city_name": "C", "state": "CO", "zipcode": "80264", "timezone": "US/Mountain"},
+                {"city_name": "D", "state": "CA", "zipcode": "90079", "timezone": "US/Pacific"}]}"#,
+            &snapshot,
+        );
+        assert_eq!(api_requests.len(), 2);
+        assert!(
+            matches!(&api_requests[0], ApiRequest::UpdateCitiesInfo(cities) if cities[1].city_name == "B")
+        );
+        assert_eq!(
+            api_requests[1],
+            ApiRequest::UpdateWifiCreds("home".to_string(), String::new())
+        );
+    }
+
+    #[test]
+    fn put_config_rejects_invalid_updates() {
+        let snapshot = snapshot();
+
+        for (body, message) in [
+            ("{}", "Nothing to update"),
+            (r#"{"unknown": 1}"#, "Nothing to update"),
+            (
+                r#"{"home_city": 0}"#,
+                "home_city must be a number from 1 to 4",
+            ),
+            (
+                r#"{"home_city": 5}"#,
+                "home_city must be a number from 1 to 4",
+            ),
+            (r#"{"theme": "pink"}"#, "'pink' is not a theme"),
+            (r#"{"log_level": "loud"}"#, "'loud' is not a log level"),
+            (
+                r#"{"wifi": {"ssid": "", "pass": ""}}"#,
+                "SSID must be 1 to 32",
+            ),
+            (
+                r#"{"wifi": {"ssid": "home", "pass": "short"}}"#,
+                "Password must be 8",
+            ),
+            (r#"{"cities": []}"#, "Expected 4 cities, got 0"),
+            ("not json", "Invalid JSON"),
+        ] {
+            let (response, api_requests) = put("/api/config", body, &snapshot);
+            assert_eq!(response.status, 400, "{}", body);
+            assert!(
+                error(&response).starts_with(message),
+                "{}",
+                error(&response)
+            );
+            assert!(api_requests.is_empty());
+        }
+
+        let body = format!(r#"{{"wifi": {{"ssid": "{}"}}}}"#, "x".repeat(MAX_BODY_SIZE));
+        let (response, _) = put("/api/config", &body, &snapshot);
+        assert_eq!(error(&response), "Request body is too large");
+    }
+
+    #[test]
+    fn put_config_checks_the_cities() {
+        let body = r#"{"cities": [
+            {"city_name": "A", "state": "NY", "zipcode": "1000", "timezone": "US/Eastern"},
+            {"city_name": "B", "state": "IL", "zipcode": "60614", "timezone": "Mars/Base"},
+            {"city_name": "C", "state": "CO", "zipcode": "80264", "timezone": "US/Mountain"},
+            {"city_name": "D", "state": "CA", "zipcode": "90079", "timezone": "US/Pacific"}]}"#;
+        let (response, api_requests) = put("/api/config", body, &snapshot());
+        assert_eq!(response.status, 400);
+        assert_eq!(error(&response), "City 1 - Zipcode must be 5 digits");
+        assert!(api_requests.is_empty());
+    }
+
+    #[test]
+    fn unknown_routes_and_methods() {
+        let snapshot = snapshot();
+        let (response, _) = handle_request(ApiMethod::Get, "/api/nothing", None, b"", &snapshot);
+        assert_eq!(response.status, 404);
+        let (response, _) = handle_request(ApiMethod::Get, "/api/ota", None, b"", &snapshot);
+        assert_eq!(response.status, 405);
+    }
+
+    #[test]
+    fn api_txt_needs_a_long_token() {
+        let lines = |text: &str| -> Vec<String> { text.lines().map(str::to_string).collect() };
+        assert_eq!(
+            ApiConfig::from_lines(&lines("token = 0123456789abcdef")),
+            Ok(ApiConfig {
+                token: TOKEN.to_string()
+            })
+        );
+        assert!(ApiConfig::from_lines(&lines("token=short")).is_err());
+        assert!(ApiConfig::from_lines(&lines("# no token")).is_err());
+    }
+}